@@ -0,0 +1,57 @@
+//! Chromatic adaptation between white points.
+
+use crate::ciexyz::adapt_mat;
+
+/// Returns the Bradford chromatic adaptation matrix which maps CIE XYZ values under `src_white`
+/// to those under `dst_white`.
+///
+/// White points are given in xy-chromaticity coordinates, like the constants in
+/// [`consts`](crate::consts). The matrix is in row-major order, and is the identity matrix if
+/// both white points are the same.
+pub fn bradford_adaptation(src_white: [f32; 2], dst_white: [f32; 2]) -> [[f32; 3]; 3] {
+    let mat = adapt_mat(src_white, dst_white);
+    [
+        [mat[0], mat[1], mat[2]],
+        [mat[3], mat[4], mat[5]],
+        [mat[6], mat[7], mat[8]],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::*;
+
+    #[test]
+    fn identity() {
+        for white in [ILLUMINANT_D65, ILLUMINANT_E, ILLUMINANT_DCI, ILLUMINANT_D50] {
+            assert_eq!(
+                bradford_adaptation(white, white),
+                [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            );
+        }
+    }
+
+    #[test]
+    fn d65_to_d50() {
+        // `chad` tag of the sRGB profile generated by libjxl, in s15Fixed16Number.
+        #[rustfmt::skip]
+        const LIBJXL_CHAD: [[i32; 3]; 3] = [
+            [68672, 1501, -3286],
+            [1938, 64912, -1117],
+            [-605, 987, 49281],
+        ];
+
+        let mat = bradford_adaptation(ILLUMINANT_D65, ILLUMINANT_D50);
+        let quantized = mat.map(|row| row.map(|f| (f * 65536.0 + 0.5) as i32));
+        assert_eq!(quantized, LIBJXL_CHAD);
+
+        // D65 white should be mapped to D50 white.
+        let src = crate::ciexyz::illuminant_to_xyz(ILLUMINANT_D65);
+        let dst = crate::ciexyz::illuminant_to_xyz(ILLUMINANT_D50);
+        for (row, expected) in mat.iter().zip(dst) {
+            let actual = row[0] * src[0] + row[1] * src[1] + row[2] * src[2];
+            assert!((actual - expected).abs() < 1e-5, "{actual} != {expected}");
+        }
+    }
+}
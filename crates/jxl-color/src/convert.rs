@@ -462,7 +462,10 @@ impl ColorTransform {
             min_nits,
         };
 
-        if intensity_target > 255.0 && !target_encoding.is_hdr() {
+        // Skip tone mapping if the whole luminance range should be mapped linearly.
+        let linear_below = tone_mapping.linear_below_nits(255.0);
+        if intensity_target > 255.0 && !target_encoding.is_hdr() && linear_below < intensity_target
+        {
             if current_encoding.colour_space == ColourSpace::Grey {
                 ops.push(ColorTransformOp::ToneMapLumaRec2408 {
                     hdr_params,
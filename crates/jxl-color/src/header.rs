@@ -329,6 +329,21 @@ define_bundle! {
     }
 }
 
+impl ToneMapping {
+    /// Returns the luminance in nits below which the image should be mapped linearly.
+    ///
+    /// If `relative_to_max_display` is set, `linear_below` is a ratio to the peak luminance of the
+    /// target display, which is given as `display_luminance`.
+    #[inline]
+    pub fn linear_below_nits(&self, display_luminance: f32) -> f32 {
+        if self.relative_to_max_display {
+            self.linear_below * display_luminance
+        } else {
+            self.linear_below
+        }
+    }
+}
+
 impl Customxy {
     /// Returns the xy-chromaticity coordinate as floating point values.
     #[inline]
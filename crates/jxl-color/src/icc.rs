@@ -3,6 +3,7 @@
 //! - [`read_icc`] and [`decode_icc`] can be used to read embedded ICC profile from the bitstream.
 //! - [`colour_encoding_to_icc`] can be used to create an ICC profile to embed into the decoded
 //!   image file, or to be used by the color management system for various purposes.
+//! - [`build_icc`] creates an RGB ICC profile from arbitrary primaries and white point.
 
 mod decode;
 mod parse;
@@ -11,7 +12,7 @@ mod synthesize;
 pub use decode::{decode_icc, read_icc};
 pub(crate) use parse::parse_icc;
 pub(crate) use parse::parse_icc_raw;
-pub use synthesize::colour_encoding_to_icc;
+pub use synthesize::{build_icc, colour_encoding_to_icc};
 
 #[derive(Debug)]
 #[non_exhaustive]
@@ -1,6 +1,6 @@
 use crate::{
-    ciexyz::*, consts::*, tf, ColourSpace, EnumColourEncoding, Primaries, RenderingIntent,
-    TransferFunction, WhitePoint,
+    ciexyz::*, consts::*, tf, ColourSpace, Customxy, EnumColourEncoding, Primaries,
+    RenderingIntent, TransferFunction, WhitePoint,
 };
 
 use super::IccTag;
@@ -84,6 +84,30 @@ fn create_para(ty: u16, params: &[u32]) -> Vec<u8> {
     out
 }
 
+/// Creates an ICCv4 RGB matrix/TRC profile with the given primaries, white point and transfer
+/// function.
+///
+/// `primaries` and `white` are xy-chromaticity coordinates, with primaries in the order of red,
+/// green and blue. Colorants are adapted to D50 using the Bradford transform, which is recorded
+/// in the `chad` tag.
+///
+/// # Panics
+/// Panics if `transfer` is [`TransferFunction::Unknown`].
+pub fn build_icc(primaries: [[f32; 2]; 3], white: [f32; 2], transfer: TransferFunction) -> Vec<u8> {
+    let to_customxy = |[x, y]: [f32; 2]| Customxy {
+        x: (x * 1e6).round() as i32,
+        y: (y * 1e6).round() as i32,
+    };
+    let [red, green, blue] = primaries.map(to_customxy);
+    colour_encoding_to_icc(&EnumColourEncoding {
+        colour_space: ColourSpace::Rgb,
+        white_point: WhitePoint::Custom(to_customxy(white)),
+        primaries: Primaries::Custom { red, green, blue },
+        tf: transfer,
+        rendering_intent: RenderingIntent::Relative,
+    })
+}
+
 /// Creates an ICCv4 profile from the given [`EnumColourEncoding`].
 pub fn colour_encoding_to_icc(colour_encoding: &EnumColourEncoding) -> Vec<u8> {
     let &EnumColourEncoding {
@@ -287,3 +311,112 @@ pub fn colour_encoding_to_icc(colour_encoding: &EnumColourEncoding) -> Vec<u8> {
     out[..4].copy_from_slice(&total_len.to_be_bytes());
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(bytes[offset..][..4].try_into().unwrap())
+    }
+
+    /// Reads the tag table, checking that tag data is aligned and within the profile.
+    fn read_tags(profile: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        assert_eq!(read_u32(profile, 0) as usize, profile.len());
+        assert_eq!(&profile[36..40], b"acsp");
+        assert_eq!(profile[8], 4);
+
+        let tag_count = read_u32(profile, 128) as usize;
+        let data_start = 132 + tag_count * 12;
+        (0..tag_count)
+            .map(|idx| {
+                let entry = &profile[132 + idx * 12..][..12];
+                let offset = read_u32(entry, 4) as usize;
+                let len = read_u32(entry, 8) as usize;
+                assert_eq!(offset % 4, 0);
+                assert!(offset >= data_start && offset + len <= profile.len());
+                (entry[..4].try_into().unwrap(), &profile[offset..][..len])
+            })
+            .collect()
+    }
+
+    fn find_tag<'a>(tags: &[([u8; 4], &'a [u8])], tag: &[u8; 4]) -> &'a [u8] {
+        tags.iter()
+            .find(|(t, _)| t == tag)
+            .unwrap_or_else(|| panic!("missing tag {}", String::from_utf8_lossy(tag)))
+            .1
+    }
+
+    fn read_s15fixed16(data: &[u8]) -> Vec<f32> {
+        data.chunks_exact(4)
+            .map(|v| i32::from_be_bytes(v.try_into().unwrap()) as f32 / 65536.0)
+            .collect()
+    }
+
+    #[test]
+    fn build_icc_matrix() {
+        let primaries = PRIMARIES_P3;
+        let white = ILLUMINANT_DCI;
+        let profile = build_icc(primaries, white, TransferFunction::Srgb);
+        let tags = read_tags(&profile);
+        assert_eq!(&profile[16..20], b"RGB ");
+        assert_eq!(&profile[20..24], b"XYZ ");
+
+        let chad = adapt_mat(white, ILLUMINANT_D50);
+        let chad_data = find_tag(&tags, b"chad");
+        assert_eq!(&chad_data[..4], b"sf32");
+        for (actual, expected) in read_s15fixed16(&chad_data[8..]).into_iter().zip(chad) {
+            assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+        }
+
+        let p_pcs = matmul3(&chad, &primaries_to_xyz_mat(primaries, white));
+        let mut white_pcs = [0f32; 3];
+        for (idx, tag) in [b"rXYZ", b"gXYZ", b"bXYZ"].into_iter().enumerate() {
+            let data = find_tag(&tags, tag);
+            assert_eq!(&data[..4], b"XYZ ");
+            let xyz = read_s15fixed16(&data[8..]);
+            for (c, &actual) in xyz.iter().enumerate() {
+                let expected = p_pcs[c * 3 + idx];
+                assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+                white_pcs[c] += actual;
+            }
+        }
+        // Colorants add up to D50.
+        for (actual, expected) in white_pcs.into_iter().zip([0.9642, 1.0, 0.8249]) {
+            assert!((actual - expected).abs() < 1e-3, "{actual} != {expected}");
+        }
+    }
+
+    #[test]
+    fn build_icc_trc() {
+        let profile = build_icc(PRIMARIES_SRGB, ILLUMINANT_D65, TransferFunction::Srgb);
+        let tags = read_tags(&profile);
+        let trc = find_tag(&tags, b"rTRC");
+        assert_eq!(&trc[..4], b"para");
+        assert_eq!(find_tag(&tags, b"gTRC"), trc);
+        assert_eq!(find_tag(&tags, b"bTRC"), trc);
+        let encoding = crate::icc::parse_icc(&profile).unwrap();
+        assert_eq!(encoding.primaries, Primaries::Srgb);
+        assert_eq!(encoding.white_point, WhitePoint::D65);
+        assert_eq!(encoding.tf, TransferFunction::Srgb);
+
+        let profile = build_icc(PRIMARIES_SRGB, ILLUMINANT_D65, TransferFunction::Linear);
+        let tags = read_tags(&profile);
+        // curv with zero entries means identity.
+        assert_eq!(find_tag(&tags, b"rTRC"), b"curv\0\0\0\0\0\0\0\0");
+        let encoding = crate::icc::parse_icc(&profile).unwrap();
+        assert_eq!(encoding.tf, TransferFunction::Linear);
+
+        let gamma = TransferFunction::Gamma {
+            g: 4_545_455,
+            inverted: false,
+        };
+        let profile = build_icc(PRIMARIES_SRGB, ILLUMINANT_D65, gamma);
+        let tags = read_tags(&profile);
+        let trc = find_tag(&tags, b"rTRC");
+        assert_eq!(&trc[..4], b"para");
+        assert_eq!(u16::from_be_bytes([trc[8], trc[9]]), 0);
+        let g = read_u32(trc, 12) as f32 / 65536.0;
+        assert!((g - 0.4545455).abs() < 1e-4, "{g}");
+    }
+}
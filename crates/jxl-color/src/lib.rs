@@ -7,9 +7,12 @@
 //! transformations can be done by creating a [`ColorTransform`].
 //!
 //! # Modules
+//! - [`adapt`] provides chromatic adaptation between white points.
 //! - [`consts`] defines constants used by the various colorspaces.
 //! - [`icc`] provides functions related to ICC profiles.
+//! - [`xyb`] provides functions converting XYB samples to linear sRGB.
 
+pub mod adapt;
 mod ciexyz;
 mod cms;
 pub mod consts;
@@ -20,7 +23,7 @@ mod gamut;
 pub mod header;
 pub mod icc;
 mod tf;
-mod xyb;
+pub mod xyb;
 mod ycbcr;
 
 pub use cms::*;
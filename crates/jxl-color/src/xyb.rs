@@ -1,3 +1,59 @@
+//! Conversion from XYB to linear sRGB.
+//!
+//! XYB samples are expected in the scale used by the JPEG XL codestream, where linear sRGB white
+//! `(1, 1, 1)` at 255 nits corresponds to XYB of about `(0, 0.845, 0.845)`.
+
+use jxl_bitstream::BundleDefault;
+
+use crate::OpsinInverseMatrix;
+
+/// Converts an XYB sample to linear sRGB, using the default opsin inverse matrix and bias.
+///
+/// `intensity_target` is the peak luminance of the image in nits, and the output is scaled so that
+/// `1.0` corresponds to it. Use `255.0` for SDR images.
+pub fn xyb_to_linear_srgb(xyb: [f32; 3], intensity_target: f32) -> [f32; 3] {
+    let [mut x, mut y, mut b] = xyb;
+    xyb_to_linear_srgb_planar(
+        std::slice::from_mut(&mut x),
+        std::slice::from_mut(&mut y),
+        std::slice::from_mut(&mut b),
+        intensity_target,
+    );
+    [x, y, b]
+}
+
+/// Converts planar XYB samples to linear sRGB in place, using the default opsin inverse matrix and
+/// bias.
+///
+/// See [`xyb_to_linear_srgb`] for the scaling of input and output.
+///
+/// # Panics
+/// Panics if the lengths of the channels are different.
+pub fn xyb_to_linear_srgb_planar(
+    x: &mut [f32],
+    y: &mut [f32],
+    b: &mut [f32],
+    intensity_target: f32,
+) {
+    let oim = OpsinInverseMatrix::default_with_context(());
+    let inv_mat = oim.inv_mat;
+    #[rustfmt::skip]
+    let matrix = [
+        inv_mat[0][0], inv_mat[0][1], inv_mat[0][2],
+        inv_mat[1][0], inv_mat[1][1], inv_mat[1][2],
+        inv_mat[2][0], inv_mat[2][1], inv_mat[2][2],
+    ];
+
+    run(
+        [&mut *x, &mut *y, &mut *b],
+        oim.opsin_bias,
+        intensity_target,
+    );
+    for ((x, y), b) in x.iter_mut().zip(y).zip(b) {
+        [*x, *y, *b] = crate::ciexyz::matmul3vec(&matrix, &[*x, *y, *b]);
+    }
+}
+
 pub(crate) fn run(xyb: [&mut [f32]; 3], ob: [f32; 3], intensity_target: f32) {
     #[cfg(target_arch = "x86_64")]
     {
@@ -39,6 +95,9 @@ fn run_generic(xyb: [&mut [f32]; 3], ob: [f32; 3], intensity_target: f32) {
     if x.len() != y.len() || y.len() != b.len() {
         panic!("Grid size mismatch");
     }
+    // Opsin bias is negative. `f32::cbrt` is the signed cube root, which is what we want here;
+    // `powf(1.0 / 3.0)` would return NaN. Inverse gamma below is a cube, so negative samples are
+    // handled without special casing.
     let cbrt_ob = ob.map(|v| v.cbrt());
 
     for ((x, y), b) in x.iter_mut().zip(&mut *y).zip(&mut *b) {
@@ -58,3 +117,117 @@ fn run_generic(xyb: [&mut [f32]; 3], ob: [f32; 3], intensity_target: f32) {
         *b = (g_s * g_s).mul_add(g_s, ob[2]) * itscale;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    const OPSIN_BIAS: [f32; 3] = [-0.0037930732; 3];
+
+    fn signed_cbrt(v: f32) -> f32 {
+        v.signum() * v.abs().cbrt()
+    }
+
+    #[test]
+    fn negative_xyb() {
+        // Slightly negative and slightly positive linear LMS values, including ones below opsin
+        // bias.
+        let lms = [
+            [-1e-4, -0.002, -0.005],
+            [-0.01, 1e-3, -1e-6],
+            [0.0, -0.004, 2e-4],
+        ];
+
+        let mut x = Vec::new();
+        let mut y = Vec::new();
+        let mut b = Vec::new();
+        for [l, m, s] in lms {
+            let [g_l, g_m, g_s] = [l, m, s]
+                .into_iter()
+                .zip(OPSIN_BIAS)
+                .map(|(v, ob)| signed_cbrt(v - ob) + signed_cbrt(ob))
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            x.push((g_l - g_m) / 2.0);
+            y.push((g_l + g_m) / 2.0);
+            b.push(g_s);
+        }
+        assert!(y.iter().chain(&b).any(|&v| v < 0.0));
+
+        super::run([&mut x, &mut y, &mut b], OPSIN_BIAS, 255.0);
+        for (idx, expected) in lms.into_iter().enumerate() {
+            let actual = [x[idx], y[idx], b[idx]];
+            for (actual, expected) in actual.into_iter().zip(expected) {
+                assert!(actual.is_finite());
+                assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+                if expected.abs() > 1e-5 {
+                    assert_eq!(actual.signum(), expected.signum());
+                }
+            }
+        }
+    }
+
+    /// Linear sRGB samples and XYB values computed with the forward transform of libjxl.
+    #[allow(clippy::excessive_precision)]
+    const REFERENCE: [([f32; 3], [f32; 3]); 7] = [
+        ([0.0, 0.0, 0.0], [0.0, 0.0, 0.0]),
+        (
+            [1.0, 1.0, 1.0],
+            [0.0, 0.8453085619621621, 0.8453085619621623],
+        ),
+        (
+            [0.18, 0.18, 0.18],
+            [0.0, 0.41260590090169674, 0.41260590090169674],
+        ),
+        (
+            [1.0, 0.0, 0.0],
+            [0.02810008316127735, 0.4881882010413151, 0.47165897565357434],
+        ),
+        (
+            [0.0, 1.0, 0.0],
+            [
+                -0.015386116472573375,
+                0.7147813727246909,
+                0.43707675721000455,
+            ],
+        ),
+        (
+            [0.0, 0.0, 1.0],
+            [0.0, 0.27812819734781813, 0.6661398621316059],
+        ),
+        (
+            [0.5, 0.25, 0.75],
+            [0.005774355792947716, 0.5547466759326951, 0.6830287327099718],
+        ),
+    ];
+
+    #[test]
+    fn xyb_to_linear_srgb_reference() {
+        for (rgb, xyb) in REFERENCE {
+            let actual = super::xyb_to_linear_srgb(xyb, 255.0);
+            for (actual, expected) in actual.into_iter().zip(rgb) {
+                assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+            }
+
+            // Output is scaled relative to the intensity target.
+            let actual = super::xyb_to_linear_srgb(xyb, 510.0);
+            for (actual, expected) in actual.into_iter().zip(rgb) {
+                assert!(
+                    (actual - expected / 2.0).abs() < 1e-4,
+                    "{actual} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn xyb_to_linear_srgb_planar_matches() {
+        let mut x = REFERENCE.map(|(_, xyb)| xyb[0]);
+        let mut y = REFERENCE.map(|(_, xyb)| xyb[1]);
+        let mut b = REFERENCE.map(|(_, xyb)| xyb[2]);
+        super::xyb_to_linear_srgb_planar(&mut x, &mut y, &mut b, 255.0);
+        for (idx, (_, xyb)) in REFERENCE.into_iter().enumerate() {
+            let expected = super::xyb_to_linear_srgb(xyb, 255.0);
+            assert_eq!([x[idx], y[idx], b[idx]], expected);
+        }
+    }
+}
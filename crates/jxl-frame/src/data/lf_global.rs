@@ -26,6 +26,8 @@ pub struct LfGlobalParams<'a, 'b> {
     pub frame_header: &'a FrameHeader,
     pub tracker: Option<&'b AllocTracker>,
     pub allow_partial: bool,
+    /// Maximum total estimated area of splines. Defaults to the limit of Level 10.
+    pub max_spline_area: Option<u64>,
 }
 
 impl<'a, 'b> LfGlobalParams<'a, 'b> {
@@ -40,6 +42,7 @@ impl<'a, 'b> LfGlobalParams<'a, 'b> {
             frame_header,
             tracker,
             allow_partial,
+            max_spline_area: None,
         }
     }
 }
@@ -51,9 +54,11 @@ impl<S: Sample> Bundle<LfGlobalParams<'_, '_>> for LfGlobal<S> {
         let LfGlobalParams {
             image_header,
             frame_header: header,
+            max_spline_area,
             ..
         } = params;
-        let image_size = (header.width * header.height) as u64;
+        // Frame dimensions can be up to 2^30, compute in u64 so that it doesn't overflow.
+        let image_size = header.width as u64 * header.height as u64;
 
         let patches = header
             .flags
@@ -129,28 +134,9 @@ impl<S: Sample> Bundle<LfGlobalParams<'_, '_>> for LfGlobal<S> {
                     lf_chan_corr.base_correlation_b,
                 )
             });
-            let estimated_area = splines.estimate_area(base_correlation_xb);
-
-            // Maximum total_estimated_area_reached for Level 10
-            let max_estimated_area = (1u64 << 42).min(1024 * image_size + (1u64 << 32));
-            if estimated_area > max_estimated_area {
-                tracing::error!(
-                    estimated_area,
-                    max_estimated_area,
-                    "Too large estimated area for splines"
-                );
-                return Err(jxl_bitstream::Error::ProfileConformance(
-                    "too large estimated area for splines",
-                )
-                .into());
-            }
-            // Maximum total_estimated_area_reached for Level 5
-            if estimated_area > (1u64 << 30).min(8 * image_size + (1u64 << 25)) {
-                tracing::warn!(
-                    "Large estimated_area of splines, expect slower decoding: {}",
-                    estimated_area
-                );
-            }
+            let max_estimated_area =
+                max_spline_area.unwrap_or_else(|| Splines::max_estimated_area(image_size));
+            splines.check_estimated_area(base_correlation_xb, image_size, max_estimated_area)?;
         }
 
         let gmodular = read_bits!(bitstream, Bundle(GlobalModular::<S>), params)?;
@@ -209,6 +195,7 @@ impl<S: Sample> Bundle<LfGlobalParams<'_, '_>> for GlobalModular<S> {
             frame_header: header,
             tracker,
             allow_partial,
+            ..
         } = params;
         let span = tracing::span!(tracing::Level::TRACE, "Decode GlobalModular");
         let _guard = span.enter();
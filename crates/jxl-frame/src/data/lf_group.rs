@@ -1,5 +1,5 @@
 use jxl_bitstream::{Bitstream, Bundle};
-use jxl_grid::AllocTracker;
+use jxl_grid::{AlignedGrid, AllocTracker};
 use jxl_modular::{image::TransformedModularSubimage, MaConfig, Sample};
 use jxl_vardct::{HfMetadata, HfMetadataParams, LfCoeff, LfCoeffParams, Quantizer};
 
@@ -28,6 +28,24 @@ pub struct LfGroup<S: Sample> {
     pub partial: bool,
 }
 
+impl<S: Sample> LfGroup<S> {
+    /// Returns quantized LF coefficients of the LF group in X, Y, B order.
+    ///
+    /// Coefficients are returned as decoded, before dequantization, chroma-from-luma and adaptive
+    /// smoothing are applied. Each sample corresponds to an 8x8 block, so chroma channels may have
+    /// smaller dimensions if chroma subsampling is used.
+    ///
+    /// Returns `None` if the frame is not VarDCT-encoded, or LF coefficients are taken from an LF
+    /// frame.
+    pub fn lf_coefficients(&self) -> Option<[&AlignedGrid<S>; 3]> {
+        let channels = self.lf_coeff.as_ref()?.lf_quant.image()?.image_channels();
+        let [y, x, b, ..] = channels else {
+            return None;
+        };
+        Some([x, y, b])
+    }
+}
+
 impl<S: Sample> Bundle<LfGroupParams<'_, '_, '_, S>> for LfGroup<S> {
     type Error = crate::Error;
 
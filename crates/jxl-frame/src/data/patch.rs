@@ -1,7 +1,7 @@
 use jxl_bitstream::{unpack_signed, Bitstream, Bundle};
 use jxl_image::ImageHeader;
 
-use crate::{FrameHeader, Result};
+use crate::{Error, FrameHeader, Result};
 
 #[derive(Debug)]
 pub struct Patches {
@@ -162,7 +162,29 @@ impl Bundle<(&ImageHeader, &FrameHeader)> for Patches {
                     let raw_mode = decoder.read_varint(bitstream, 5)?;
                     let mode = PatchBlendMode::try_from(raw_mode)?;
                     let alpha_channel = if raw_mode >= 4 && alpha_channel_indices.len() >= 2 {
-                        decoder.read_varint(bitstream, 8)?
+                        let alpha_channel = decoder.read_varint(bitstream, 8)?;
+                        let Some(info) = image_header.metadata.ec_info.get(alpha_channel as usize)
+                        else {
+                            tracing::error!(
+                                alpha_channel,
+                                num_extra,
+                                "Patch alpha channel index out of bounds"
+                            );
+                            return Err(Error::InconsistentMetadata(
+                                "patch alpha channel index out of bounds",
+                            ));
+                        };
+                        if !info.is_alpha() {
+                            tracing::error!(
+                                alpha_channel,
+                                ?info,
+                                "Patch alpha channel is not the type of Alpha"
+                            );
+                            return Err(Error::InconsistentMetadata(
+                                "patch alpha channel is not the type of Alpha",
+                            ));
+                        }
+                        alpha_channel
                     } else {
                         alpha_channel_indices.first().copied().unwrap_or_default()
                     };
@@ -1,11 +1,27 @@
 use jxl_bitstream::{unpack_signed, Bitstream, Bundle};
 use jxl_coding::Decoder;
 
-use crate::{FrameHeader, Result};
+use crate::{Error, FrameHeader, Result};
 
 const MAX_NUM_SPLINES: usize = 1 << 24;
 const MAX_NUM_CONTROL_POINTS: usize = 1 << 20;
 
+/// Returns the maximum number of splines allowed in a frame with `num_pixels` pixels.
+fn max_num_splines(num_pixels: u64) -> usize {
+    (num_pixels / 4).min(MAX_NUM_SPLINES as u64) as usize
+}
+
+/// Returns the maximum number of control points allowed in a frame with `num_pixels` pixels.
+fn max_num_control_points(num_pixels: u64) -> usize {
+    (num_pixels / 2).min(MAX_NUM_CONTROL_POINTS as u64) as usize
+}
+
+/// Returns the maximum total estimated area of splines allowed by Level 5 in a frame with
+/// `num_pixels` pixels.
+fn max_estimated_area_level5(num_pixels: u64) -> u64 {
+    (1u64 << 30).min(8 * num_pixels + (1u64 << 25))
+}
+
 /// Holds quantized splines
 #[derive(Debug)]
 pub struct Splines {
@@ -21,8 +37,9 @@ impl Bundle<&FrameHeader> for Splines {
         decoder.begin(bitstream)?;
 
         let num_splines = decoder.read_varint(bitstream, 2)? as usize;
-        let num_pixels = (header.width * header.height) as usize;
-        let max_num_splines = usize::min(MAX_NUM_SPLINES, num_pixels / 4);
+        // Frame dimensions can be up to 2^30, compute in u64 so that it doesn't overflow.
+        let num_pixels = header.width as u64 * header.height as u64;
+        let max_num_splines = max_num_splines(num_pixels);
         if num_splines >= max_num_splines {
             tracing::error!(num_splines, max_num_splines, "Too many splines");
             return Err(jxl_bitstream::Error::ProfileConformance("too many splines").into());
@@ -67,6 +84,44 @@ impl Bundle<&FrameHeader> for Splines {
 }
 
 impl Splines {
+    /// Returns the maximum total estimated area of splines allowed by Level 10 in a frame with
+    /// `num_pixels` pixels.
+    pub fn max_estimated_area(num_pixels: u64) -> u64 {
+        (1u64 << 42).min(1024 * num_pixels + (1u64 << 32))
+    }
+
+    /// Checks whether the total estimated area of splines is within `max_estimated_area`, and
+    /// returns the estimated area.
+    ///
+    /// A warning is emitted if the area exceeds the limit of Level 5 for a frame with
+    /// `num_pixels` pixels.
+    pub fn check_estimated_area(
+        &self,
+        base_correlation_xb: Option<(f32, f32)>,
+        num_pixels: u64,
+        max_estimated_area: u64,
+    ) -> Result<u64> {
+        let estimated_area = self.estimate_area(base_correlation_xb);
+        if estimated_area > max_estimated_area {
+            tracing::error!(
+                estimated_area,
+                max_estimated_area,
+                "Too large estimated area for splines"
+            );
+            return Err(Error::SplineAreaTooLarge {
+                estimated_area,
+                max_estimated_area,
+            });
+        }
+        if estimated_area > max_estimated_area_level5(num_pixels) {
+            tracing::warn!(
+                "Large estimated_area of splines, expect slower decoding: {}",
+                estimated_area
+            );
+        }
+        Ok(estimated_area)
+    }
+
     pub(crate) fn estimate_area(&self, base_correlation_xb: Option<(f32, f32)>) -> u64 {
         let base_correlation_xb = base_correlation_xb.unwrap_or((0.0, 1.0));
         let corr_x = base_correlation_xb.0.abs().ceil() as u64;
@@ -80,22 +135,25 @@ impl Splines {
                     quant_color_dct
                         .into_iter()
                         .map(|q| div_ceil_qa(q.unsigned_abs(), quant_adjust))
-                        .sum()
+                        .fold(0u64, u64::saturating_add)
                 });
 
-                color_xyb[0] += corr_x * color_xyb[1];
-                color_xyb[2] += corr_b * color_xyb[1];
-                log2_ceil(1u64 + color_xyb.into_iter().max().unwrap()) as u64
+                color_xyb[0] = color_xyb[0].saturating_add(corr_x.saturating_mul(color_xyb[1]));
+                color_xyb[2] = color_xyb[2].saturating_add(corr_b.saturating_mul(color_xyb[1]));
+                log2_ceil(color_xyb.into_iter().max().unwrap().saturating_add(1)) as u64
             };
 
+            // Saturate so that extreme coefficients can't wrap the estimate below the limit.
             let mut width_estimate = 0u64;
             for quant_sigma_dct in quant_spline.sigma_dct {
                 let quant_sigma_dct = quant_sigma_dct.unsigned_abs();
                 let weight = 1 + div_ceil_qa(quant_sigma_dct, quant_adjust);
-                width_estimate += weight * weight * log_color;
+                width_estimate = width_estimate
+                    .saturating_add(weight.saturating_mul(weight).saturating_mul(log_color));
             }
 
-            total_area += width_estimate * quant_spline.manhattan_distance;
+            total_area = total_area
+                .saturating_add(width_estimate.saturating_mul(quant_spline.manhattan_distance));
         }
 
         total_area
@@ -104,7 +162,9 @@ impl Splines {
 
 #[inline]
 fn log2_ceil(x: u64) -> u32 {
-    x.next_power_of_two().trailing_zeros()
+    x.checked_next_power_of_two()
+        .map(|x| x.trailing_zeros())
+        .unwrap_or(u64::BITS)
 }
 
 #[inline]
@@ -121,7 +181,7 @@ fn div_ceil_qa(dividend: u32, quant_adjust: i32) -> u64 {
 
 struct QuantSplineParams<'d> {
     start_point: (i64, i64),
-    num_pixels: usize,
+    num_pixels: u64,
     decoder: &'d mut Decoder,
     acc_control_points: usize,
 }
@@ -129,7 +189,7 @@ struct QuantSplineParams<'d> {
 impl<'d> QuantSplineParams<'d> {
     fn new(
         start_point: (i64, i64),
-        num_pixels: usize,
+        num_pixels: u64,
         decoder: &'d mut Decoder,
         acc_control_points: usize,
     ) -> Self {
@@ -167,22 +227,53 @@ impl Bundle<QuantSplineParams<'_>> for QuantSpline {
 
         let num_points = decoder.read_varint(bitstream, 3)? as usize;
         let acc_num_points = acc_control_points + num_points;
-        let max_num_points = usize::min(MAX_NUM_CONTROL_POINTS, num_pixels / 2);
+        let max_num_points = max_num_control_points(num_pixels);
         if acc_num_points > max_num_points {
             tracing::error!(num_points, max_num_points, "Too many spline points");
             return Err(jxl_bitstream::Error::ProfileConformance("too many spline points").into());
         }
 
-        let mut quant_points = Vec::with_capacity(1 + num_points);
-        let mut cur_value = start_point;
-        let mut cur_delta = (0, 0);
-        let mut manhattan_distance = 0u64;
-        quant_points.push(cur_value);
+        let mut deltas = Vec::with_capacity(num_points);
         for _ in 0..num_points {
-            let prev_value = cur_value;
             let delta_x = unpack_signed(decoder.read_varint(bitstream, 4)?) as i64;
             let delta_y = unpack_signed(decoder.read_varint(bitstream, 4)?) as i64;
+            deltas.push((delta_x, delta_y));
+        }
+
+        let mut xyb_dct = [[0; 32]; 3];
+        for color_dct in &mut xyb_dct {
+            for i in color_dct {
+                *i = read_quant_dct(bitstream, decoder)?;
+            }
+        }
+
+        let mut sigma_dct = [0; 32];
+        for i in &mut sigma_dct {
+            *i = read_quant_dct(bitstream, decoder)?;
+        }
 
+        Self::from_raw(start_point, &deltas, xyb_dct, sigma_dct)
+    }
+}
+
+impl QuantSpline {
+    /// Creates a quantized spline from values as they are stored in the bitstream.
+    ///
+    /// `deltas` are second-order differences of control points following `start`, so a spline
+    /// with a constant `deltas[0]` followed by zeros is a straight line.
+    pub fn from_raw(
+        start: (i64, i64),
+        deltas: &[(i64, i64)],
+        xyb_dct: [[i32; 32]; 3],
+        sigma_dct: [i32; 32],
+    ) -> Result<Self> {
+        let mut quant_points = Vec::with_capacity(1 + deltas.len());
+        let mut cur_value = start;
+        let mut cur_delta = (0, 0);
+        let mut manhattan_distance = 0u64;
+        quant_points.push(cur_value);
+        for &(delta_x, delta_y) in deltas {
+            let prev_value = cur_value;
             cur_delta.0 += delta_x;
             cur_delta.1 += delta_y;
             manhattan_distance += (cur_delta.0.abs() + cur_delta.1.abs()) as u64;
@@ -201,18 +292,6 @@ impl Bundle<QuantSplineParams<'_>> for QuantSpline {
             quant_points.push(cur_value);
         }
 
-        let mut xyb_dct = [[0; 32]; 3];
-        for color_dct in &mut xyb_dct {
-            for i in color_dct {
-                *i = unpack_signed(decoder.read_varint(bitstream, 5)?);
-            }
-        }
-
-        let mut sigma_dct = [0; 32];
-        for i in &mut sigma_dct {
-            *i = unpack_signed(decoder.read_varint(bitstream, 5)?);
-        }
-
         Ok(Self {
             quant_points,
             manhattan_distance,
@@ -220,4 +299,188 @@ impl Bundle<QuantSplineParams<'_>> for QuantSpline {
             sigma_dct,
         })
     }
+
+    /// Dequantizes DCT32 coefficients of XYB channels and σ parameter.
+    ///
+    /// `base_correlations_xb` is the base correlation of X and B channels to Y, defaults to
+    /// `(0.0, 1.0)`.
+    pub fn dequant_dct(
+        &self,
+        quant_adjust: i32,
+        base_correlations_xb: Option<(f32, f32)>,
+    ) -> ([[f32; 32]; 3], [f32; 32]) {
+        let mut xyb_dct = [[0f32; 32]; 3];
+        let mut sigma_dct = [0f32; 32];
+
+        let quant_adjust = quant_adjust as f32;
+        let inverted_qa = if quant_adjust >= 0.0 {
+            1.0 / (1.0 + quant_adjust / 8.0)
+        } else {
+            1.0 - quant_adjust / 8.0
+        };
+
+        const CHANNEL_WEIGHTS: [f32; 4] = [0.0042, 0.075, 0.07, 0.3333];
+        for ((out, quant), weight) in xyb_dct.iter_mut().zip(&self.xyb_dct).zip(CHANNEL_WEIGHTS) {
+            for (out, &quant) in out.iter_mut().zip(quant) {
+                *out = quant as f32 * weight * inverted_qa;
+            }
+        }
+        let (corr_x, corr_b) = base_correlations_xb.unwrap_or((0.0, 1.0));
+        let [x, y, b] = &mut xyb_dct;
+        for ((x, b), &y) in x.iter_mut().zip(b.iter_mut()).zip(&*y) {
+            *x += corr_x * y;
+            *b += corr_b * y;
+        }
+
+        for (sigma_dct, quant_sigma_dct) in sigma_dct.iter_mut().zip(self.sigma_dct) {
+            *sigma_dct = quant_sigma_dct as f32 * CHANNEL_WEIGHTS[3] * inverted_qa;
+        }
+
+        (xyb_dct, sigma_dct)
+    }
+}
+
+/// Reads a quantized DCT32 coefficient of a spline.
+///
+/// libjxl rejects `i32::MIN`, whose magnitude doesn't fit in `i32`; every other value is
+/// conformant, and the rendered area is bounded separately by [`Splines::estimate_area`].
+fn read_quant_dct(bitstream: &mut Bitstream, decoder: &mut Decoder) -> Result<i32> {
+    let value = unpack_signed(decoder.read_varint(bitstream, 5)?);
+    if value == i32::MIN {
+        tracing::error!(value, "Spline DCT coefficient out of range");
+        return Err(Error::InvalidSpline("DCT coefficient out of range"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_of_large_frames() {
+        // 65536 * 65536 wraps to zero in u32.
+        let num_pixels = 65536u64 * 65536;
+        assert_eq!(max_num_splines(num_pixels), MAX_NUM_SPLINES);
+        assert_eq!(max_num_control_points(num_pixels), MAX_NUM_CONTROL_POINTS);
+
+        let num_pixels = (1u64 << 30) * (1 << 30);
+        assert_eq!(max_num_splines(num_pixels), MAX_NUM_SPLINES);
+        assert_eq!(max_num_control_points(num_pixels), MAX_NUM_CONTROL_POINTS);
+    }
+
+    #[test]
+    fn abusive_spline_area() {
+        let quant_spline = QuantSpline {
+            quant_points: Vec::new(),
+            manhattan_distance: 1 << 16,
+            xyb_dct: [[1 << 20; 32]; 3],
+            sigma_dct: [1 << 12; 32],
+        };
+        let splines = Splines {
+            quant_splines: vec![quant_spline; 64],
+            quant_adjust: 0,
+        };
+        let num_pixels = 256 * 256;
+        let estimated_area = splines.estimate_area(None);
+        assert!(estimated_area > Splines::max_estimated_area(num_pixels));
+
+        let err = splines
+            .check_estimated_area(None, num_pixels, Splines::max_estimated_area(num_pixels))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::SplineAreaTooLarge { estimated_area: area, .. } if area == estimated_area
+        ));
+
+        // The limit can be raised for streams which don't conform to Level 10.
+        let area = splines
+            .check_estimated_area(None, num_pixels, u64::MAX - 1)
+            .unwrap();
+        assert_eq!(area, estimated_area);
+
+        // Extreme coefficients saturate instead of wrapping around.
+        let mut splines = splines;
+        splines.quant_splines[0].sigma_dct = [i32::MAX; 32];
+        splines.quant_splines[0].manhattan_distance = u64::MAX;
+        splines.quant_splines[0].xyb_dct = [[i32::MIN + 1; 32]; 3];
+        splines.quant_adjust = i32::MIN + 1;
+        assert_eq!(splines.estimate_area(Some((-f32::MAX, f32::MAX))), u64::MAX);
+        let err = splines
+            .check_estimated_area(None, num_pixels, u64::MAX - 1)
+            .unwrap_err();
+        assert!(matches!(err, Error::SplineAreaTooLarge { .. }));
+    }
+
+    #[test]
+    fn limits_of_small_frames() {
+        assert_eq!(max_num_splines(10 * 10), 25);
+        assert_eq!(max_num_control_points(10 * 10), 50);
+        assert_eq!(max_num_splines(3), 0);
+    }
+
+    /// Encodes a spline with no control points, whose first X coefficient has the raw value
+    /// `0xffffffff - clear_bit` and the rest are zero.
+    fn spline_bytes(clear_bit: u8) -> Vec<u8> {
+        let mut bytes = vec![0x12, 0x16, 0x28, 0x00, 0xf4, 0xff, 0xff, 0xff, 0x0f];
+        bytes[4] &= !(clear_bit << 5);
+        bytes.resize(bytes.len() + 16, 0);
+        bytes
+    }
+
+    fn parse_spline(bytes: &[u8]) -> Result<QuantSpline> {
+        let mut bitstream = Bitstream::new(bytes);
+        let mut decoder = Decoder::parse(&mut bitstream, 6)?;
+        decoder.begin(&mut bitstream)?;
+        QuantSpline::parse(
+            &mut bitstream,
+            QuantSplineParams::new((0, 0), 64 * 64, &mut decoder, 0),
+        )
+    }
+
+    #[test]
+    fn extreme_dct_coefficients() {
+        let spline = parse_spline(&spline_bytes(1)).unwrap();
+        assert_eq!(spline.xyb_dct[0][0], i32::MAX);
+        assert!(spline.xyb_dct[0][1..].iter().all(|&v| v == 0));
+        assert_eq!(spline.sigma_dct, [0; 32]);
+
+        let err = parse_spline(&spline_bytes(0)).unwrap_err();
+        assert!(matches!(err, Error::InvalidSpline(_)));
+    }
+
+    #[test]
+    fn from_raw() {
+        // Straight line from (4, 16), advancing by (8, 0) each point.
+        let mut xyb_dct = [[0; 32]; 3];
+        xyb_dct[0][0] = 10;
+        xyb_dct[1][0] = 20;
+        xyb_dct[1][1] = -4;
+        let mut sigma_dct = [0; 32];
+        sigma_dct[0] = 3;
+        let spline =
+            QuantSpline::from_raw((4, 16), &[(8, 0), (0, 0), (0, 0)], xyb_dct, sigma_dct).unwrap();
+        assert_eq!(spline.quant_points, [(4, 16), (12, 16), (20, 16), (28, 16)]);
+        assert_eq!(spline.manhattan_distance, 24);
+
+        let (xyb, sigma) = spline.dequant_dct(0, None);
+        assert_eq!(xyb[0][0], 10.0 * 0.0042);
+        assert_eq!(xyb[1][..2], [20.0 * 0.075, -4.0 * 0.075]);
+        assert!(xyb[1][2..].iter().all(|&v| v == 0.0));
+        // B is fully correlated to Y by default.
+        assert_eq!(xyb[2][..2], [20.0 * 0.075, -4.0 * 0.075]);
+        assert_eq!(sigma[0], 3.0 * 0.3333);
+
+        // Positive quant_adjust makes coefficients smaller, negative ones larger.
+        let (xyb, sigma) = spline.dequant_dct(8, Some((0.5, 0.0)));
+        assert_eq!(xyb[1][0], 20.0 * 0.075 / 2.0);
+        assert_eq!(xyb[0][0], 10.0 * 0.0042 / 2.0 + 0.5 * xyb[1][0]);
+        assert_eq!(xyb[2][0], 0.0);
+        assert_eq!(sigma[0], 3.0 * 0.3333 / 2.0);
+        let (_, sigma) = spline.dequant_dct(-8, None);
+        assert_eq!(sigma[0], 3.0 * 0.3333 * 2.0);
+
+        let err = QuantSpline::from_raw((4, 16), &[(8, 0), (-8, 0)], xyb_dct, sigma_dct);
+        assert!(err.is_err());
+    }
 }
@@ -8,6 +8,9 @@ use jxl_bitstream::{read_bits, Bitstream, Bundle};
 pub struct Toc {
     num_lf_groups: usize,
     num_groups: usize,
+    group_dim: u32,
+    groups_per_row: u32,
+    lf_groups_per_row: u32,
     groups: Vec<TocGroup>,
     bitstream_to_original: Vec<usize>,
     original_to_bitstream: Vec<usize>,
@@ -112,6 +115,15 @@ impl Toc {
         self.groups.len() <= 1
     }
 
+    /// Returns the position of the group in the bitstream.
+    ///
+    /// If the TOC is permuted, the position is looked up from the permutation signalled in the
+    /// TOC; otherwise groups are stored in the order of LfGlobal, LF groups, HfGlobal, then pass
+    /// groups of each pass.
+    ///
+    /// # Panics
+    /// Panics if `kind` is `All` for a frame with multiple groups, or any other kind for a frame
+    /// with a single group.
     pub fn group_index_bitstream_order(&self, kind: TocGroupKind) -> usize {
         let original_order = match kind {
             TocGroupKind::All if self.is_single_entry() => 0,
@@ -146,6 +158,7 @@ impl Toc {
         self.total_size
     }
 
+    /// Returns an iterator over groups in the order they are stored in the bitstream.
     pub fn iter_bitstream_order(&self) -> impl Iterator<Item = TocGroup> + Send {
         let groups = if self.bitstream_to_original.is_empty() {
             self.groups.clone()
@@ -157,6 +170,140 @@ impl Toc {
         };
         groups.into_iter()
     }
+
+    /// Returns an iterator over groups in raster order, so that groups at the top-left of the
+    /// frame are visited first.
+    ///
+    /// LfGlobal and HfGlobal are visited first, followed by LF groups and pass groups sorted by
+    /// the position of their top-left corner, in `(top, left)` order. An LF group is visited
+    /// before the pass groups it contains, and passes of a pass group are visited in pass order.
+    ///
+    /// The order depends only on the frame dimension, while [`iter_bitstream_order`] follows the
+    /// order of groups in the bitstream. If the TOC is not permuted, the bitstream order is
+    /// LfGlobal, all LF groups, HfGlobal, then pass groups of each pass; with a permuted TOC it
+    /// can be arbitrary.
+    ///
+    /// [`iter_bitstream_order`]: Self::iter_bitstream_order
+    pub fn iter_raster_order(&self) -> impl Iterator<Item = TocGroup> + Send {
+        let lf_group_dim = self.group_dim * 8;
+        let position = |kind: TocGroupKind| match kind {
+            TocGroupKind::All | TocGroupKind::LfGlobal | TocGroupKind::HfGlobal => None,
+            TocGroupKind::LfGroup(lf_group_idx) => Some((
+                (lf_group_idx / self.lf_groups_per_row) * lf_group_dim,
+                (lf_group_idx % self.lf_groups_per_row) * lf_group_dim,
+            )),
+            TocGroupKind::GroupPass { group_idx, .. } => Some((
+                (group_idx / self.groups_per_row) * self.group_dim,
+                (group_idx % self.groups_per_row) * self.group_dim,
+            )),
+        };
+
+        let mut groups = self.groups.clone();
+        groups.sort_by_key(|group| (position(group.kind), group.kind));
+        groups.into_iter()
+    }
+
+    /// Returns the pass group of pass `pass_idx` which contains the given pixel.
+    ///
+    /// Coordinates are in color samples of the frame, that is, before upsampling is applied.
+    /// Returns the `All` group if the frame has only one group, and `None` if the coordinates or
+    /// the pass index are out of bounds.
+    pub fn group_for_pixel(
+        &self,
+        header: &crate::FrameHeader,
+        x: u32,
+        y: u32,
+        pass_idx: u32,
+    ) -> Option<TocGroup> {
+        if !Self::is_pixel_in_bounds(header, x, y) || pass_idx >= header.passes.num_passes {
+            return None;
+        }
+        if self.is_single_entry() {
+            return self.groups.first().copied();
+        }
+
+        let group_dim = header.group_dim();
+        let group_idx = (y / group_dim) * header.groups_per_row() + x / group_dim;
+        let idx =
+            1 + self.num_lf_groups + 1 + pass_idx as usize * self.num_groups + group_idx as usize;
+        self.groups.get(idx).copied()
+    }
+
+    /// Returns the LF group which contains the given pixel.
+    ///
+    /// Coordinates are in color samples of the frame, that is, before upsampling is applied.
+    /// Returns the `All` group if the frame has only one group, and `None` if the coordinates are
+    /// out of bounds.
+    pub fn lf_group_for_pixel(
+        &self,
+        header: &crate::FrameHeader,
+        x: u32,
+        y: u32,
+    ) -> Option<TocGroup> {
+        if !Self::is_pixel_in_bounds(header, x, y) {
+            return None;
+        }
+        if self.is_single_entry() {
+            return self.groups.first().copied();
+        }
+
+        let lf_group_dim = header.lf_group_dim();
+        let lf_group_idx = (y / lf_group_dim) * header.lf_groups_per_row() + x / lf_group_dim;
+        self.groups.get(1 + lf_group_idx as usize).copied()
+    }
+
+    fn is_pixel_in_bounds(header: &crate::FrameHeader, x: u32, y: u32) -> bool {
+        x < header.color_sample_width() && y < header.color_sample_height()
+    }
+
+    /// Returns a human-readable description of the TOC, listing each group in bitstream order.
+    ///
+    /// Each line describes one group with its kind, offset from the beginning of frame header,
+    /// and size in bytes. Intended for debugging.
+    pub fn describe(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "TOC: {} {}, {} LF group(s), {} group(s), {} bytes total{}",
+            self.groups.len(),
+            if self.groups.len() == 1 {
+                "entry"
+            } else {
+                "entries"
+            },
+            self.num_lf_groups,
+            self.num_groups,
+            self.total_size,
+            if self.bitstream_to_original.is_empty() {
+                ""
+            } else {
+                ", permuted"
+            },
+        )
+        .unwrap();
+
+        for (idx, group) in self.iter_bitstream_order().enumerate() {
+            let kind = match group.kind {
+                TocGroupKind::All => String::from("All"),
+                TocGroupKind::LfGlobal => String::from("LfGlobal"),
+                TocGroupKind::LfGroup(lf_group_idx) => format!("LfGroup({lf_group_idx})"),
+                TocGroupKind::HfGlobal => String::from("HfGlobal"),
+                TocGroupKind::GroupPass {
+                    pass_idx,
+                    group_idx,
+                } => format!("GroupPass(pass {pass_idx}, group {group_idx})"),
+            };
+            writeln!(
+                out,
+                "  #{idx}: {kind}, offset {}, size {}",
+                group.offset, group.size
+            )
+            .unwrap();
+        }
+        out
+    }
 }
 
 impl Toc {
@@ -267,6 +414,9 @@ impl Bundle<&crate::FrameHeader> for Toc {
         Ok(Self {
             num_lf_groups: ctx.num_lf_groups() as usize,
             num_groups: num_groups as usize,
+            group_dim: ctx.group_dim(),
+            groups_per_row: ctx.groups_per_row(),
+            lf_groups_per_row: ctx.lf_groups_per_row(),
             groups,
             bitstream_to_original,
             original_to_bitstream,
@@ -274,3 +424,99 @@ impl Bundle<&crate::FrameHeader> for Toc {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use jxl_bitstream::{Bitstream, Bundle};
+    use jxl_image::ImageHeader;
+
+    use super::*;
+    use crate::FrameHeader;
+
+    fn frame_header(width: u32, height: u32, num_passes: u32) -> FrameHeader {
+        // 8x8 image with default metadata, followed by a default frame header.
+        let image_header =
+            ImageHeader::parse(&mut Bitstream::new(&[0xff, 0x0a, 0x41, 0x06]), ()).unwrap();
+        let mut header = FrameHeader::parse(&mut Bitstream::new(&[0x01]), &image_header).unwrap();
+        header.width = width;
+        header.height = height;
+        header.passes.num_passes = num_passes;
+        header
+    }
+
+    fn toc(header: &FrameHeader) -> Toc {
+        // Every entry is a zero-sized group.
+        Toc::parse(&mut Bitstream::new(&[0; 1024]), header).unwrap()
+    }
+
+    fn group_idx(group: Option<TocGroup>) -> Option<(u32, u32)> {
+        group.map(|group| match group.kind {
+            TocGroupKind::GroupPass {
+                pass_idx,
+                group_idx,
+            } => (pass_idx, group_idx),
+            kind => panic!("unexpected group kind {kind:?}"),
+        })
+    }
+
+    #[test]
+    fn group_for_pixel_corners() {
+        // 3x2 groups of 256x256 in 2x1 LF groups of 2048x2048, 2 passes.
+        let header = frame_header(2100, 300, 2);
+        let toc = toc(&header);
+        assert_eq!(header.group_dim(), 256);
+
+        let g = |x, y, pass_idx| group_idx(toc.group_for_pixel(&header, x, y, pass_idx));
+        assert_eq!(g(0, 0, 0), Some((0, 0)));
+        assert_eq!(g(2099, 0, 0), Some((0, 8)));
+        assert_eq!(g(0, 299, 0), Some((0, 9)));
+        assert_eq!(g(2099, 299, 1), Some((1, 17)));
+        assert_eq!(g(2100, 0, 0), None);
+        assert_eq!(g(0, 300, 0), None);
+        assert_eq!(g(0, 0, 2), None);
+
+        let lf = |x, y| {
+            toc.lf_group_for_pixel(&header, x, y)
+                .map(|group| group.kind)
+        };
+        assert_eq!(lf(0, 0), Some(TocGroupKind::LfGroup(0)));
+        assert_eq!(lf(2047, 299), Some(TocGroupKind::LfGroup(0)));
+        assert_eq!(lf(2048, 0), Some(TocGroupKind::LfGroup(1)));
+        assert_eq!(lf(2099, 299), Some(TocGroupKind::LfGroup(1)));
+        assert_eq!(lf(2100, 299), None);
+    }
+
+    #[test]
+    fn group_for_pixel_center() {
+        let header = frame_header(2100, 300, 2);
+        let toc = toc(&header);
+
+        let g = |x, y, pass_idx| group_idx(toc.group_for_pixel(&header, x, y, pass_idx));
+        assert_eq!(g(1050, 150, 0), Some((0, 4)));
+        assert_eq!(g(255, 255, 0), Some((0, 0)));
+        assert_eq!(g(256, 256, 1), Some((1, 10)));
+
+        // The returned group is the one at the matching TOC entry.
+        let group = toc.group_for_pixel(&header, 1050, 150, 1).unwrap();
+        let idx = toc.group_index_bitstream_order(group.kind);
+        let expected = toc.iter_bitstream_order().nth(idx).unwrap();
+        assert_eq!(group.kind, expected.kind);
+        assert_eq!(group.offset, expected.offset);
+    }
+
+    #[test]
+    fn group_for_pixel_single_entry() {
+        let header = frame_header(200, 100, 1);
+        let toc = toc(&header);
+        assert!(toc.is_single_entry());
+
+        for (x, y) in [(0, 0), (199, 99), (100, 50)] {
+            let group = toc.group_for_pixel(&header, x, y, 0).unwrap();
+            assert_eq!(group.kind, TocGroupKind::All);
+            let group = toc.lf_group_for_pixel(&header, x, y).unwrap();
+            assert_eq!(group.kind, TocGroupKind::All);
+        }
+        assert!(toc.group_for_pixel(&header, 200, 0, 0).is_none());
+        assert!(toc.lf_group_for_pixel(&header, 0, 100).is_none());
+    }
+}
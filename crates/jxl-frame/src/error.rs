@@ -7,7 +7,17 @@ pub enum Error {
     Modular(jxl_modular::Error),
     VarDct(jxl_vardct::Error),
     InvalidTocPermutation,
-    IncompleteFrameData { field: &'static str },
+    IncompleteFrameData {
+        field: &'static str,
+    },
+    InconsistentHeader(&'static str),
+    InconsistentMetadata(&'static str),
+    InvalidSpline(&'static str),
+    InvalidPassConfig(&'static str),
+    SplineAreaTooLarge {
+        estimated_area: u64,
+        max_estimated_area: u64,
+    },
     HadError,
 }
 
@@ -53,6 +63,20 @@ impl std::fmt::Display for Error {
             Self::IncompleteFrameData { field } => {
                 write!(f, "incomplete frame data: {} is missing", field)
             }
+            Self::InconsistentHeader(msg) => write!(f, "inconsistent frame header: {}", msg),
+            Self::InconsistentMetadata(msg) => {
+                write!(f, "frame is inconsistent with image metadata: {}", msg)
+            }
+            Self::InvalidSpline(msg) => write!(f, "invalid spline: {}", msg),
+            Self::InvalidPassConfig(msg) => write!(f, "invalid pass configuration: {}", msg),
+            Self::SplineAreaTooLarge {
+                estimated_area,
+                max_estimated_area,
+            } => write!(
+                f,
+                "estimated area of splines too large: {} > {}",
+                estimated_area, max_estimated_area
+            ),
             Self::HadError => write!(f, "previous parsing errored"),
         }
     }
@@ -70,6 +70,8 @@ define_bundle! {
                 },
             ))
             cond(!all_default && frame_type.is_normal_frame()),
+        /// Blending information for each extra channel, in the order of `ec_info` of the image
+        /// metadata.
         pub ec_blending_info:
             ty(Vec[Bundle(BlendingInfo)]; headers.metadata.ec_info.len())
             ctx((
@@ -84,7 +86,20 @@ define_bundle! {
                     size: &headers.size,
                 },
             ))
-            cond(!all_default && frame_type.is_normal_frame()),
+            cond(!all_default && frame_type.is_normal_frame())
+            default(
+                headers
+                    .metadata
+                    .ec_info
+                    .iter()
+                    .map(|_| BlendingInfo {
+                        mode: BlendMode::Replace,
+                        alpha_channel: 0,
+                        clamp: false,
+                        source: 0,
+                    })
+                    .collect()
+            ),
         pub duration:
             ty(U32(0, 1, u(8), u(32)))
             cond(!all_default && frame_type.is_normal_frame() && headers.metadata.animation.is_some())
@@ -216,6 +231,29 @@ impl FrameHeader {
         self.frame_type.is_normal_frame() && (self.is_last || self.duration != 0)
     }
 
+    /// Returns how long this frame is presented.
+    ///
+    /// Duration of `0xffffffff` ticks is interpreted as [`FrameDuration::UntilUserInput`].
+    #[inline]
+    pub fn frame_duration(&self) -> FrameDuration {
+        FrameDuration::from_ticks(self.duration)
+    }
+
+    /// Returns whether this frame can be rendered without any of the previous frames.
+    ///
+    /// A frame is independent if it replaces the whole canvas in every channel, and doesn't use
+    /// patches or an LF frame. Players can seek to an independent keyframe and render from there,
+    /// as long as the non-displayed frames right before it are also independent.
+    pub fn is_independent(&self) -> bool {
+        self.resets_canvas
+            && self
+                .ec_blending_info
+                .iter()
+                .all(|info| info.mode == BlendMode::Replace)
+            && !self.flags.patches()
+            && !self.flags.use_lf_frame()
+    }
+
     #[inline]
     pub fn can_reference(&self) -> bool {
         !self.is_last
@@ -353,6 +391,10 @@ impl FrameHeader {
         lf_group_col + lf_group_row * self.lf_groups_per_row()
     }
 
+    /// Returns whether the group collides with the given region, in `(left, top, width, height)`.
+    ///
+    /// Trailing groups in each row and column are treated as if they have full group dimension,
+    /// so those are included when the region touches the right or bottom edge of the frame.
     pub fn is_group_collides_region(&self, group_idx: u32, region: (u32, u32, u32, u32)) -> bool {
         let group_dim = self.group_dim();
         let group_per_row = self.groups_per_row();
@@ -361,6 +403,11 @@ impl FrameHeader {
         is_aabb_collides(region, (group_left, group_top, group_dim, group_dim))
     }
 
+    /// Returns whether the LF group collides with the given region, in
+    /// `(left, top, width, height)`.
+    ///
+    /// Trailing LF groups are treated as if they have full LF group dimension, similarly to
+    /// [`is_group_collides_region`][Self::is_group_collides_region].
     pub fn is_lf_group_collides_region(
         &self,
         lf_group_idx: u32,
@@ -374,6 +421,28 @@ impl FrameHeader {
     }
 }
 
+/// Presentation duration of a frame.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FrameDuration {
+    /// The frame is presented for the given number of ticks.
+    Ticks(u32),
+    /// The frame is presented until user input, e.g. at the end of a page of a multi-page image.
+    UntilUserInput,
+}
+
+impl FrameDuration {
+    /// Interprets the `duration` field of a frame header, where `0xffffffff` ticks means
+    /// [`FrameDuration::UntilUserInput`].
+    #[inline]
+    pub fn from_ticks(duration: u32) -> Self {
+        if duration == u32::MAX {
+            Self::UntilUserInput
+        } else {
+            Self::Ticks(duration)
+        }
+    }
+}
+
 #[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
 #[repr(u8)]
 pub enum FrameType {
@@ -509,5 +578,8 @@ impl BlendMode {
 fn is_aabb_collides(rect0: (u32, u32, u32, u32), rect1: (u32, u32, u32, u32)) -> bool {
     let (x0, y0, w0, h0) = rect0;
     let (x1, y1, w1, h1) = rect1;
-    (x0 < x1 + w1) && (x0 + w0 > x1) && (y0 < y1 + h1) && (y0 + h0 > y1)
+    (x0 < x1.saturating_add(w1))
+        && (x0.saturating_add(w0) > x1)
+        && (y0 < y1.saturating_add(h1))
+        && (y0.saturating_add(h0) > y1)
 }
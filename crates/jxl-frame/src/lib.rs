@@ -52,6 +52,7 @@ pub struct Frame {
     reading_data_index: usize,
     pass_shifts: BTreeMap<u32, (i32, i32)>,
     lz77_mode: Lz77Mode,
+    max_spline_area: Option<u64>,
 }
 
 #[derive(Debug, Default)]
@@ -83,6 +84,24 @@ pub struct FrameContext<'a> {
     pub image_header: Arc<ImageHeader>,
     pub tracker: Option<&'a AllocTracker>,
     pub pool: JxlThreadPool,
+    /// Maximum total estimated area of splines. Defaults to the limit of Level 10 if `None`.
+    pub max_spline_area: Option<u64>,
+}
+
+impl<'a> FrameContext<'a> {
+    /// Creates a context with the default spline area limit.
+    pub fn new(
+        image_header: Arc<ImageHeader>,
+        tracker: Option<&'a AllocTracker>,
+        pool: JxlThreadPool,
+    ) -> Self {
+        Self {
+            image_header,
+            tracker,
+            pool,
+            max_spline_area: None,
+        }
+    }
 }
 
 impl Bundle<FrameContext<'_>> for Frame {
@@ -93,6 +112,7 @@ impl Bundle<FrameContext<'_>> for Frame {
             image_header,
             tracker,
             pool,
+            max_spline_area,
         } = ctx;
         let tracker = tracker.cloned();
 
@@ -135,14 +155,20 @@ impl Bundle<FrameContext<'_>> for Frame {
                         ?alpha_ec_info,
                         "blending_info.alpha_channel is not the type of Alpha",
                     );
-                    return Err(jxl_bitstream::Error::ValidationFailed(
+                    return Err(Error::InconsistentMetadata(
                         "blending_info.alpha_channel is not the type of Alpha",
-                    )
-                    .into());
+                    ));
                 }
             }
         }
 
+        if header.encoding == header::Encoding::Modular && header.flags.use_lf_frame() {
+            tracing::error!("Modular frame uses LF frame, which is only for VarDCT");
+            return Err(Error::InconsistentHeader(
+                "Modular frame cannot use LF frame",
+            ));
+        }
+
         if header.flags.use_lf_frame() && header.lf_level >= 4 {
             return Err(jxl_bitstream::Error::ValidationFailed("lf_level out of range").into());
         }
@@ -182,6 +208,39 @@ impl Bundle<FrameContext<'_>> for Frame {
             }
         }
 
+        let passes = &header.passes;
+        let mut prev_downsample = 16u32;
+        let mut prev_last_pass = None;
+        for (&downsample, &last_pass) in passes.downsample.iter().zip(&passes.last_pass) {
+            if !downsample.is_power_of_two() {
+                tracing::error!(downsample, "Downsampling factor is not a power of two");
+                return Err(Error::InvalidPassConfig(
+                    "downsampling factor is not a power of two",
+                ));
+            }
+            if downsample >= prev_downsample {
+                tracing::error!(
+                    ?passes.downsample,
+                    "Downsampling factors are not strictly decreasing"
+                );
+                return Err(Error::InvalidPassConfig(
+                    "downsampling factors are not strictly decreasing",
+                ));
+            }
+            if prev_last_pass.is_some_and(|prev| last_pass <= prev)
+                || last_pass >= passes.num_passes
+            {
+                tracing::error!(
+                    ?passes.last_pass,
+                    num_passes = passes.num_passes,
+                    "Invalid last_pass of downsampling factors"
+                );
+                return Err(Error::InvalidPassConfig("invalid last_pass in passes"));
+            }
+            prev_downsample = downsample;
+            prev_last_pass = Some(last_pass);
+        }
+
         if header.width == 0 || header.height == 0 {
             return Err(jxl_bitstream::Error::ValidationFailed(
                 "Invalid crop dimensions for frame: zero width or height",
@@ -193,7 +252,6 @@ impl Bundle<FrameContext<'_>> for Frame {
         toc.adjust_offsets(base_offset);
         let data = toc.iter_bitstream_order().map(GroupData::from).collect();
 
-        let passes = &header.passes;
         let mut pass_shifts = BTreeMap::new();
         let mut maxshift = 3i32;
         for (&downsample, &last_pass) in passes.downsample.iter().zip(&passes.last_pass) {
@@ -214,6 +272,7 @@ impl Bundle<FrameContext<'_>> for Frame {
             reading_data_index: 0,
             pass_shifts,
             lz77_mode: bitstream.lz77_mode(),
+            max_spline_area,
         })
     }
 }
@@ -224,6 +283,12 @@ impl Frame {
         self.tracker.as_ref()
     }
 
+    /// Returns the thread pool used to decode the frame.
+    #[inline]
+    pub fn pool(&self) -> &JxlThreadPool {
+        &self.pool
+    }
+
     pub fn image_header(&self) -> &ImageHeader {
         &self.image_header
     }
@@ -244,6 +309,30 @@ impl Frame {
         &self.toc
     }
 
+    /// Returns the number of LF groups in the frame.
+    #[inline]
+    pub fn num_lf_groups(&self) -> u32 {
+        self.header.num_lf_groups()
+    }
+
+    /// Returns the number of pass groups in a single pass of the frame.
+    #[inline]
+    pub fn num_pass_groups(&self) -> u32 {
+        self.header.num_groups()
+    }
+
+    /// Returns the dimension of a pass group, in color channel samples.
+    #[inline]
+    pub fn group_dim(&self) -> u32 {
+        self.header.group_dim()
+    }
+
+    /// Returns the dimension of an LF group, in color channel samples.
+    #[inline]
+    pub fn lf_group_dim(&self) -> u32 {
+        self.header.lf_group_dim()
+    }
+
     pub fn pass_shifts(&self) -> &BTreeMap<u32, (i32, i32)> {
         &self.pass_shifts
     }
@@ -274,6 +363,76 @@ impl Frame {
     pub fn is_loading_done(&self) -> bool {
         self.reading_data_index >= self.data.len()
     }
+
+    /// Returns TOC groups which are not completely loaded yet, in bitstream order.
+    ///
+    /// Groups not in the returned list are fully loaded and can be parsed, so a partially loaded
+    /// frame can still be rendered with the missing groups left out.
+    pub fn missing_groups(&self) -> Vec<TocGroupKind> {
+        self.data[self.reading_data_index.min(self.data.len())..]
+            .iter()
+            .map(|group| group.toc_group.kind)
+            .collect()
+    }
+
+    /// Returns the number of bytes needed to complete the group currently being loaded, or
+    /// `None` if the frame is fully loaded.
+    pub fn bytes_left_in_group(&self) -> Option<usize> {
+        let group = self.data.get(self.reading_data_index)?;
+        Some(group.toc_group.size as usize - group.bytes.len())
+    }
+
+    /// Returns the number of passes which are completely loaded.
+    ///
+    /// A pass is complete if all of LfGlobal, LfGroups and HfGlobal, and the pass groups of the
+    /// pass and preceding passes are loaded.
+    pub fn num_loaded_passes(&self) -> u32 {
+        let num_passes = self.header.passes.num_passes;
+        if self.is_loading_done() {
+            return num_passes;
+        }
+        if self.toc.is_single_entry() {
+            return 0;
+        }
+
+        let mut loaded_passes = num_passes;
+        for group in &self.data[self.reading_data_index..] {
+            match group.toc_group.kind {
+                TocGroupKind::GroupPass { pass_idx, .. } => {
+                    loaded_passes = loaded_passes.min(pass_idx);
+                }
+                _ => return 0,
+            }
+        }
+        loaded_passes
+    }
+
+    /// Returns whether all groups needed to decode the given region are fully loaded.
+    ///
+    /// The region is in `(left, top, width, height)`, in color channel samples of the frame. Use
+    /// [`adjust_region`][Self::adjust_region] to get the region of the frame from the cropping
+    /// region of the image. Groups which are not loaded are left out while rendering, so this can
+    /// be used to check whether the requested region was actually populated.
+    pub fn is_region_decoded(&self, region: (u32, u32, u32, u32)) -> bool {
+        if self.is_loading_done() {
+            return true;
+        }
+        if self.toc.is_single_entry() {
+            return false;
+        }
+
+        self.data[self.reading_data_index..]
+            .iter()
+            .all(|group| match group.toc_group.kind {
+                TocGroupKind::All | TocGroupKind::LfGlobal | TocGroupKind::HfGlobal => false,
+                TocGroupKind::LfGroup(lf_group_idx) => !self
+                    .header
+                    .is_lf_group_collides_region(lf_group_idx, region),
+                TocGroupKind::GroupPass { group_idx, .. } => {
+                    !self.header.is_group_collides_region(group_idx, region)
+                }
+            })
+    }
 }
 
 impl Frame {
@@ -289,12 +448,15 @@ impl Frame {
             bitstream.set_lz77_mode(self.lz77_mode);
             let lf_global = LfGlobal::parse(
                 &mut bitstream,
-                LfGlobalParams::new(
-                    &self.image_header,
-                    &self.header,
-                    self.tracker.as_ref(),
-                    false,
-                ),
+                LfGlobalParams {
+                    max_spline_area: self.max_spline_area,
+                    ..LfGlobalParams::new(
+                        &self.image_header,
+                        &self.header,
+                        self.tracker.as_ref(),
+                        false,
+                    )
+                },
             );
             match lf_global {
                 Ok(lf_global) => {
@@ -319,12 +481,15 @@ impl Frame {
             bitstream.set_lz77_mode(self.lz77_mode);
             LfGlobal::parse(
                 &mut bitstream,
-                LfGlobalParams::new(
-                    &self.image_header,
-                    &self.header,
-                    self.tracker.as_ref(),
-                    allow_partial,
-                ),
+                LfGlobalParams {
+                    max_spline_area: self.max_spline_area,
+                    ..LfGlobalParams::new(
+                        &self.image_header,
+                        &self.header,
+                        self.tracker.as_ref(),
+                        allow_partial,
+                    )
+                },
             )
         })
     }
@@ -541,6 +706,10 @@ impl Frame {
         }
     }
 
+    /// Returns the bitstream of the pass group, or `None` if the group is not loaded yet.
+    ///
+    /// The bitstream borrows the data already buffered in the frame, so decoding pass groups in
+    /// parallel doesn't make additional copies of the group data.
     pub fn pass_group_bitstream(
         &self,
         pass_idx: u32,
@@ -561,11 +730,15 @@ impl Frame {
             bitstream.set_lz77_mode(self.lz77_mode);
             let mut offset = self.all_group_offsets.pass_group.load(Ordering::Relaxed);
             if offset == 0 {
-                let hf_global = self.try_parse_hf_global::<i32>(None)?;
-                if let Err(e) = hf_global {
+                // Modular frames don't have HfGlobal, in which case `None` is returned after
+                // recording the offset of the pass group.
+                if let Some(Err(e)) = self.try_parse_hf_global::<i32>(None) {
                     return Some(Err(e));
                 }
                 offset = self.all_group_offsets.pass_group.load(Ordering::Relaxed);
+                if offset == 0 {
+                    return None;
+                }
             }
             bitstream.skip_bits(offset).unwrap();
 
@@ -128,6 +128,35 @@ impl<S: Default + Clone> AlignedGrid<S> {
         out.buf.extend_from_slice(self.buf());
         Ok(out)
     }
+
+    /// Changes the dimension of the grid, reusing the allocated buffer.
+    ///
+    /// Returns `false` without modifying the grid if the buffer is too small for the new
+    /// dimension. Contents of the grid are unspecified after reshaping.
+    pub fn try_reshape(&mut self, width: usize, height: usize) -> bool {
+        let len = width * height;
+        if self.offset + len > self.buf.capacity() {
+            return false;
+        }
+
+        self.buf.resize(self.offset + len, S::default());
+        self.width = width;
+        self.height = height;
+        true
+    }
+
+    /// Returns the transposed grid, with width and height swapped.
+    ///
+    /// The new buffer is recorded in the same tracker as the original buffer.
+    pub fn transpose(&self) -> Result<Self, Error> {
+        let mut out = Self::empty_aligned(self.height, self.width, self.tracker().as_ref())?;
+        let buf = self.buf();
+        for x in 0..self.width {
+            out.buf
+                .extend((0..self.height).map(|y| buf[y * self.width + x].clone()));
+        }
+        Ok(out)
+    }
 }
 
 impl<S> AlignedGrid<S> {
@@ -185,6 +214,103 @@ impl<S> AlignedGrid<S> {
     pub fn as_subgrid_mut(&mut self) -> MutableSubgrid<S> {
         MutableSubgrid::from(self)
     }
+
+    /// Transposes the square grid in place.
+    ///
+    /// # Panics
+    /// Panics if the grid is not square.
+    pub fn transpose_in_place(&mut self) {
+        assert_eq!(
+            self.width, self.height,
+            "in-place transpose requires a square grid"
+        );
+
+        let size = self.width;
+        let buf = self.buf_mut();
+        for y in 0..size {
+            for x in (y + 1)..size {
+                buf.swap(y * size + x, x * size + y);
+            }
+        }
+    }
+}
+
+impl AlignedGrid<f32> {
+    /// Samples the grid at the given position using bilinear interpolation.
+    ///
+    /// Integer coordinates correspond to pixel centers. Coordinates outside of the grid are
+    /// clamped to the edges. Returns zero if the grid is empty.
+    pub fn sample_bilinear(&self, x: f32, y: f32) -> f32 {
+        if self.width == 0 || self.height == 0 {
+            return 0.0;
+        }
+        self.as_subgrid().sample_bilinear(x, y)
+    }
+
+    /// Resizes the grid to the given dimensions using bilinear interpolation.
+    ///
+    /// Scaling factors don't need to be integers. The new buffer is recorded in the same tracker
+    /// as the original buffer.
+    pub fn resize_bilinear(&self, width: usize, height: usize) -> Result<Self, Error> {
+        let mut out = Self::with_alloc_tracker(width, height, self.tracker().as_ref())?;
+        if width == 0 || height == 0 {
+            return Ok(out);
+        }
+
+        let scale_x = self.width as f32 / width as f32;
+        let scale_y = self.height as f32 / height as f32;
+        for (y, row) in out.buf_mut().chunks_exact_mut(width).enumerate() {
+            let sy = (y as f32 + 0.5) * scale_y - 0.5;
+            for (x, v) in row.iter_mut().enumerate() {
+                let sx = (x as f32 + 0.5) * scale_x - 0.5;
+                *v = self.sample_bilinear(sx, sy);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Computes the histogram of samples, with `bins` bins of equal width in `range`.
+    ///
+    /// Samples outside of `range` are counted in the first or the last bin, and NaN samples are
+    /// ignored.
+    ///
+    /// # Panics
+    /// Panics if `bins` is zero, or `range` is empty.
+    pub fn histogram(&self, bins: usize, (min, max): (f32, f32)) -> Vec<u32> {
+        assert!(bins > 0, "histogram should have at least one bin");
+        assert!(min < max, "histogram range should not be empty");
+
+        let mut out = vec![0u32; bins];
+        let scale = bins as f32 / (max - min);
+        for &v in self.buf() {
+            if v.is_nan() {
+                continue;
+            }
+            let idx = ((v - min) * scale) as isize;
+            out[idx.clamp(0, bins as isize - 1) as usize] += 1;
+        }
+        out
+    }
+
+    /// Clamps every sample to `[min, max]` in place.
+    ///
+    /// NaN samples are left as is.
+    ///
+    /// # Panics
+    /// Panics if `min > max`, or either bound is NaN.
+    pub fn clamp(&mut self, min: f32, max: f32) {
+        assert!(min <= max, "clamp range should be valid");
+        for v in self.buf_mut() {
+            *v = v.clamp(min, max);
+        }
+    }
+
+    /// Applies `f` to every sample in place.
+    pub fn map(&mut self, f: impl Fn(f32) -> f32) {
+        for v in self.buf_mut() {
+            *v = f(*v);
+        }
+    }
 }
 
 /// `[AlignedGrid]` with padding.
@@ -273,3 +399,153 @@ impl<S: Clone> PaddedGrid<S> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_bilinear() {
+        let mut grid = AlignedGrid::<f32>::with_alloc_tracker(2, 2, None).unwrap();
+        grid.buf_mut().copy_from_slice(&[0.0, 1.0, 2.0, 3.0]);
+
+        assert_eq!(grid.sample_bilinear(0.0, 0.0), 0.0);
+        assert_eq!(grid.sample_bilinear(1.0, 1.0), 3.0);
+        assert_eq!(grid.sample_bilinear(0.5, 0.0), 0.5);
+        assert_eq!(grid.sample_bilinear(0.5, 0.5), 1.5);
+        // Clamped to edges.
+        assert_eq!(grid.sample_bilinear(-3.0, 0.0), 0.0);
+        assert_eq!(grid.sample_bilinear(5.0, 5.0), 3.0);
+    }
+
+    #[test]
+    fn resize_bilinear_gradient() {
+        let (width, height) = (10, 6);
+        let mut grid = AlignedGrid::<f32>::with_alloc_tracker(width, height, None).unwrap();
+        for (idx, v) in grid.buf_mut().iter_mut().enumerate() {
+            *v = (idx % width) as f32 + (idx / width) as f32 * 0.5;
+        }
+
+        // Non-integer scale in both directions.
+        let resized = grid.resize_bilinear(23, 9).unwrap();
+        assert_eq!(resized.width(), 23);
+        assert_eq!(resized.height(), 9);
+
+        for row in resized.buf().chunks_exact(23) {
+            assert!(row.windows(2).all(|w| w[0] <= w[1]), "{row:?}");
+        }
+        for x in 0..23 {
+            let column = (0..9)
+                .map(|y| *resized.get(x, y).unwrap())
+                .collect::<Vec<_>>();
+            assert!(column.windows(2).all(|w| w[0] <= w[1]), "{column:?}");
+        }
+
+        let min = resized.buf().iter().copied().fold(f32::INFINITY, f32::min);
+        let max = resized
+            .buf()
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        assert!(min >= 0.0 && max <= 9.0 + 5.0 * 0.5);
+    }
+
+    #[test]
+    fn histogram_gradient() {
+        let (width, height) = (64, 4);
+        let mut grid = AlignedGrid::<f32>::with_alloc_tracker(width, height, None).unwrap();
+        for (idx, v) in grid.buf_mut().iter_mut().enumerate() {
+            *v = ((idx % width) as f32 + 0.5) / width as f32;
+        }
+
+        let histogram = grid.histogram(8, (0.0, 1.0));
+        assert_eq!(histogram, [32; 8]);
+
+        // Out of range samples are counted in the edge bins, and NaN is ignored.
+        grid.buf_mut()[..3].copy_from_slice(&[-1.0, 2.0, f32::NAN]);
+        let histogram = grid.histogram(4, (0.25, 0.75));
+        assert_eq!(histogram.iter().sum::<u32>(), 255);
+        assert_eq!(histogram[1..3], [32, 32]);
+        assert_eq!(histogram[0], 96 - 2);
+        assert_eq!(histogram[3], 96 + 1);
+    }
+
+    #[test]
+    fn clamp_bounds() {
+        let mut grid = AlignedGrid::<f32>::with_alloc_tracker(3, 2, None).unwrap();
+        grid.buf_mut()
+            .copy_from_slice(&[-0.5, 0.0, 0.25, 1.0, 1.5, f32::INFINITY]);
+
+        grid.clamp(0.0, 1.0);
+        assert_eq!(grid.buf(), [0.0, 0.0, 0.25, 1.0, 1.0, 1.0]);
+
+        grid.buf_mut()[0] = f32::NAN;
+        grid.clamp(0.5, 0.5);
+        assert!(grid.buf()[0].is_nan());
+        assert!(grid.buf()[1..].iter().all(|&v| v == 0.5));
+    }
+
+    #[test]
+    fn map_scale() {
+        let mut grid = AlignedGrid::<f32>::with_alloc_tracker(4, 3, None).unwrap();
+        for (idx, v) in grid.buf_mut().iter_mut().enumerate() {
+            *v = idx as f32;
+        }
+
+        grid.map(|v| v * 0.5 - 1.0);
+        for (idx, &v) in grid.buf().iter().enumerate() {
+            assert_eq!(v, idx as f32 * 0.5 - 1.0);
+        }
+        assert_eq!(*grid.get(3, 2).unwrap(), 4.5);
+    }
+
+    #[test]
+    fn transpose_non_square() {
+        let (width, height) = (5, 3);
+        let mut grid = AlignedGrid::<u32>::with_alloc_tracker(width, height, None).unwrap();
+        for (idx, v) in grid.buf_mut().iter_mut().enumerate() {
+            *v = idx as u32;
+        }
+
+        let transposed = grid.transpose().unwrap();
+        assert_eq!(transposed.width(), height);
+        assert_eq!(transposed.height(), width);
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(transposed.get(y, x), grid.get(x, y));
+            }
+        }
+        assert_eq!(transposed.transpose().unwrap().buf(), grid.buf());
+    }
+
+    #[test]
+    fn transpose_square_in_place() {
+        let mut grid = AlignedGrid::<u32>::with_alloc_tracker(4, 4, None).unwrap();
+        for (idx, v) in grid.buf_mut().iter_mut().enumerate() {
+            *v = idx as u32;
+        }
+
+        let expected = grid.transpose().unwrap();
+        grid.transpose_in_place();
+        assert_eq!(grid.buf(), expected.buf());
+        assert_eq!(*grid.get(3, 1).unwrap(), 13);
+    }
+
+    #[test]
+    fn try_reshape() {
+        let mut grid = AlignedGrid::<f32>::with_alloc_tracker(10, 6, None).unwrap();
+        let ptr = grid.buf().as_ptr();
+
+        assert!(grid.try_reshape(7, 8));
+        assert_eq!((grid.width(), grid.height()), (7, 8));
+        assert_eq!(grid.buf().len(), 56);
+        assert_eq!(grid.buf().as_ptr(), ptr);
+
+        // Growing back to the original size doesn't reallocate.
+        assert!(grid.try_reshape(6, 10));
+        assert_eq!(grid.buf().as_ptr(), ptr);
+
+        assert!(!grid.try_reshape(20, 6));
+        assert_eq!((grid.width(), grid.height()), (6, 10));
+    }
+}
@@ -187,6 +187,25 @@ impl<'g, V> SharedSubgrid<'g, V> {
 }
 
 impl<'g> SharedSubgrid<'g, f32> {
+    /// Samples the subgrid at the given position using bilinear interpolation.
+    ///
+    /// Integer coordinates correspond to pixel centers. Coordinates outside of the subgrid are
+    /// clamped to the edges.
+    pub fn sample_bilinear(&self, x: f32, y: f32) -> f32 {
+        let x = x.clamp(0.0, (self.width - 1) as f32);
+        let y = y.clamp(0.0, (self.height - 1) as f32);
+        let x0 = x.floor() as usize;
+        let y0 = y.floor() as usize;
+        let x1 = (x0 + 1).min(self.width - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+        let fx = x - x0 as f32;
+        let fy = y - y0 as f32;
+
+        let top = self.get(x0, y0) * (1.0 - fx) + self.get(x1, y0) * fx;
+        let bottom = self.get(x0, y1) * (1.0 - fx) + self.get(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
     pub fn as_vectored<V: SimdVector>(&self) -> Option<SharedSubgrid<'g, V>> {
         assert!(
             V::available(),
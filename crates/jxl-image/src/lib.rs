@@ -64,20 +64,32 @@ impl<Ctx> Bundle<Ctx> for ImageHeader {
 }
 
 impl ImageHeader {
+    /// Returns the image dimensions in `(width, height)`.
+    ///
+    /// If `apply_orientation` is `true`, the dimensions are of the image with orientation applied,
+    /// which has width and height swapped if the orientation transposes the image. Otherwise
+    /// the dimensions are of the encoded image.
+    #[inline]
+    pub fn dimensions(&self, apply_orientation: bool) -> (u32, u32) {
+        let SizeHeader { width, height, .. } = self.size;
+        if apply_orientation {
+            let (width, height, _, _) = self.metadata.apply_orientation(width, height, 0, 0, false);
+            (width, height)
+        } else {
+            (width, height)
+        }
+    }
+
     /// Returns the image width with orientation applied.
     #[inline]
     pub fn width_with_orientation(&self) -> u32 {
-        self.metadata
-            .apply_orientation(self.size.width, self.size.height, 0, 0, false)
-            .0
+        self.dimensions(true).0
     }
 
     /// Returns the image height with orientation applied.
     #[inline]
     pub fn height_with_orientation(&self) -> u32 {
-        self.metadata
-            .apply_orientation(self.size.width, self.size.height, 0, 0, false)
-            .1
+        self.dimensions(true).1
     }
 }
 
@@ -282,7 +294,7 @@ pub struct ExtraChannelInfo {
     pub bit_depth: BitDepth,
     /// `dim_shift` used to decode Modular image.
     pub dim_shift: u32,
-    /// Name of the channel.
+    /// Name of the channel, validated as UTF-8. Empty if the channel is unnamed.
     pub name: Name,
 }
 
@@ -464,21 +476,35 @@ impl BitDepth {
                 let sample = sample as u32;
                 let mantissa_bits = bits_per_sample - exp_bits - 1;
                 let mantissa_mask = (1u32 << mantissa_bits) - 1;
-                let exp_mask = ((1u32 << (bits_per_sample - 1)) - 1) ^ mantissa_mask;
+                let exp_mask = (1u32 << exp_bits) - 1;
 
                 let is_signed = (sample & (1u32 << (bits_per_sample - 1))) != 0;
                 let mantissa = sample & mantissa_mask;
-                let exp = ((sample & exp_mask) >> mantissa_bits) as i32;
-                let exp = exp - ((1 << (exp_bits - 1)) - 1);
+                let exp = (sample >> mantissa_bits) & exp_mask;
+                let bias = (1i32 << (exp_bits - 1)) - 1;
 
-                // TODO: handle subnormal values.
                 let f32_mantissa_bits = f32::MANTISSA_DIGITS - 1;
                 let mantissa = match mantissa_bits.cmp(&f32_mantissa_bits) {
                     std::cmp::Ordering::Less => mantissa << (f32_mantissa_bits - mantissa_bits),
                     std::cmp::Ordering::Greater => mantissa >> (mantissa_bits - f32_mantissa_bits),
                     _ => mantissa,
                 };
-                let exp = (exp + 127) as u32;
+
+                let (exp, mantissa) = if exp == exp_mask {
+                    // Infinity or NaN.
+                    (0xff, mantissa)
+                } else if exp != 0 {
+                    ((exp as i32 - bias + 127) as u32, mantissa)
+                } else if mantissa == 0 || exp_bits >= 8 {
+                    // Zero, or subnormal value which is also subnormal in f32.
+                    (0, mantissa)
+                } else {
+                    // Subnormal value, which is normal in f32 if exponent is narrower.
+                    let shift = mantissa.leading_zeros() - (31 - f32_mantissa_bits);
+                    let exp = 1 - bias - shift as i32 + 127;
+                    let mantissa = (mantissa << shift) & ((1u32 << f32_mantissa_bits) - 1);
+                    (exp as u32, mantissa)
+                };
                 let sign = is_signed as u32;
 
                 let bits = (sign << 31) | (exp << f32_mantissa_bits) | mantissa;
@@ -589,3 +615,49 @@ impl ImageMetadata {
         0.11452620, -0.03348048, -0.01605681, -0.02070339, -0.00458223,
     ];
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float16_bit_depth() {
+        // float_sample = 1, bits_per_sample = 16 (selector 1), exp_bits = 1 + 4
+        let mut bitstream = Bitstream::new(&[0x23]);
+        let bit_depth = BitDepth::parse(&mut bitstream, ()).unwrap();
+        assert!(matches!(
+            bit_depth,
+            BitDepth::FloatSample {
+                bits_per_sample: 16,
+                exp_bits: 5,
+            }
+        ));
+
+        let cases = [
+            (0x0000, 0.0),
+            (0x3c00, 1.0),
+            (0xc000, -2.0),
+            (0x3555, 0.333_251_95),
+            (0x7bff, 65504.0),
+            (0x0400, 6.103_515_6e-5),
+            (0x0001, 5.960_464_5e-8),
+            (0x83ff, -6.097_555e-5),
+            (0x7c00, f32::INFINITY),
+            (0xfc00, f32::NEG_INFINITY),
+        ];
+        for (sample, expected) in cases {
+            assert_eq!(
+                bit_depth.parse_integer_sample(sample),
+                expected,
+                "{sample:#06x}"
+            );
+            // Samples may also come from sign-extended 16-bit buffers.
+            assert_eq!(
+                bit_depth.parse_integer_sample(sample as i16 as i32),
+                expected,
+                "{sample:#06x}"
+            );
+        }
+        assert!(bit_depth.parse_integer_sample(0x7e00).is_nan());
+    }
+}
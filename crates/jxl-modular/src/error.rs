@@ -11,6 +11,8 @@ pub enum Error {
     InvalidPaletteParams,
     /// Decoded Squeeze transform parameters are invalid.
     InvalidSqueezeParams,
+    /// Too many transforms are applied to an image, including the ones of the parent image.
+    TooManyTransforms,
     Bitstream(jxl_bitstream::Error),
     Decoder(jxl_coding::Error),
     Buffer(jxl_grid::Error),
@@ -46,6 +48,7 @@ impl std::fmt::Display for Error {
             Self::InvalidRctParams => write!(f, "invalid Rct transform parameters"),
             Self::InvalidPaletteParams => write!(f, "invalid Palette transform parameters"),
             Self::InvalidSqueezeParams => write!(f, "invalid Squeeze transform parameters"),
+            Self::TooManyTransforms => write!(f, "too many Modular transforms"),
             Bitstream(err) => write!(f, "bitstream error: {}", err),
             Decoder(err) => write!(f, "entropy decoder error: {}", err),
             Buffer(err) => write!(f, "{}", err),
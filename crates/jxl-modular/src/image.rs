@@ -423,6 +423,7 @@ impl<'dest, S: Sample> TransformedModularSubimage<'dest, S> {
             bitstream,
             &channels,
             global_ma_config,
+            self.header.transform.len(),
             tracker,
         )?;
 
@@ -441,11 +442,19 @@ impl<'dest, S: Sample> TransformedModularSubimage<'dest, S> {
     }
 
     pub fn finish(mut self, pool: &jxl_threadpool::JxlThreadPool) -> bool {
+        // Transforms are listed in the order the encoder applied them, so the inverse is applied
+        // last-in-first-out; each inverse expects the channel layout left by the transforms that
+        // precede it.
         for tr in self.header.transform.iter().rev() {
             tr.inverse(&mut self.grid, self.bit_depth, pool);
         }
         !self.partial
     }
+
+    #[cfg(test)]
+    pub(crate) fn grids_mut(&mut self) -> &mut [TransformedGrid<'dest, S>] {
+        &mut self.grid
+    }
 }
 
 impl<'dest, S: Sample> TransformedModularSubimage<'dest, S> {
@@ -3,7 +3,7 @@
 //! A Modular image represents a set of grids (two-dimensional arrays) of integer values. Modular
 //! images are used mainly for lossless images, but lossy VarDCT images also use them to store
 //! various information, such as quantized LF images and varblock configurations.
-use jxl_bitstream::{define_bundle, read_bits, Bitstream, Bundle};
+use jxl_bitstream::{read_bits, Bitstream, Bundle};
 
 mod error;
 pub mod image;
@@ -107,6 +107,7 @@ impl<S: Sample> Bundle<ModularParams<'_, '_>> for ModularData<S> {
             bitstream,
             &channels,
             params.ma_config,
+            0,
             params.tracker,
         )?;
         Ok(Self {
@@ -122,13 +123,42 @@ impl<S: Sample> Bundle<ModularParams<'_, '_>> for ModularData<S> {
     }
 }
 
-define_bundle! {
-    #[derive(Debug, Clone)]
-    struct ModularHeader error(crate::Error) {
-        use_global_tree: ty(Bool),
-        wp_params: ty(Bundle(predictor::WpHeader)),
-        nb_transforms: ty(U32(0, 1, 2 + u(4), 18 + u(8))),
-        transform: ty(Vec[Bundle(transform::TransformInfo)]; nb_transforms) ctx(&wp_params),
+/// Maximum number of transforms applied to a subimage, counting the transforms of the images
+/// containing it.
+const MAX_TRANSFORM_DEPTH: usize = 512;
+
+#[derive(Debug, Clone)]
+struct ModularHeader {
+    use_global_tree: bool,
+    wp_params: predictor::WpHeader,
+    transform: Vec<transform::TransformInfo>,
+}
+
+/// Context is the maximum number of transforms allowed.
+impl Bundle<usize> for ModularHeader {
+    type Error = crate::Error;
+
+    fn parse(bitstream: &mut Bitstream, max_transforms: usize) -> Result<Self> {
+        let use_global_tree = bitstream.read_bool()?;
+        let wp_params = read_bits!(bitstream, Bundle(predictor::WpHeader))?;
+        let nb_transforms = read_bits!(bitstream, U32(0, 1, 2 + u(4), 18 + u(8)))? as usize;
+        // Check before reading transforms, so that long transform chain is rejected early.
+        if nb_transforms > max_transforms {
+            tracing::error!(nb_transforms, max_transforms, "Too many transforms");
+            return Err(crate::Error::TooManyTransforms);
+        }
+
+        let transform = std::iter::repeat_with(|| {
+            read_bits!(bitstream, Bundle(transform::TransformInfo), &wp_params)
+        })
+        .take(nb_transforms)
+        .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            use_global_tree,
+            wp_params,
+            transform,
+        })
     }
 }
 
@@ -202,16 +232,11 @@ fn read_and_validate_local_modular_header(
     bitstream: &mut Bitstream,
     channels: &ModularChannels,
     global_ma_config: Option<&MaConfig>,
+    parent_transforms: usize,
     tracker: Option<&AllocTracker>,
 ) -> Result<(ModularHeader, MaConfig)> {
-    let mut header = bitstream.read_bundle::<ModularHeader>()?;
-    if header.nb_transforms > 512 {
-        tracing::error!(
-            nb_transforms = header.nb_transforms,
-            "nb_transforms too large"
-        );
-        return Err(jxl_bitstream::Error::ProfileConformance("nb_transforms too large").into());
-    }
+    let max_transforms = MAX_TRANSFORM_DEPTH.saturating_sub(parent_transforms);
+    let mut header = bitstream.read_bundle_with_ctx::<ModularHeader, _>(max_transforms)?;
 
     let mut tr_channels = channels.clone();
     for tr in &mut header.transform {
@@ -249,3 +274,53 @@ fn read_and_validate_local_modular_header(
 
     Ok((header, ma_ctx))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a Modular header with `nb_transforms` RCTs, using the default WP parameters.
+    fn write_rct_chain(nb_transforms: u32) -> Vec<u8> {
+        let mut bits = vec![false, true];
+        let mut push = |value: u32, n: u32| {
+            bits.extend((0..n).map(|i| (value >> i) & 1 != 0));
+        };
+        // nb_transforms = 18 + u(8)
+        push(3, 2);
+        push(nb_transforms - 18, 8);
+        for _ in 0..nb_transforms {
+            // TransformId::Rct, begin_c = 0, rct_type = 6
+            push(0, 2);
+            push(0, 2);
+            push(0, 3);
+            push(0, 2);
+        }
+        bits.chunks(8)
+            .map(|byte| byte.iter().rev().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+            .collect()
+    }
+
+    #[test]
+    fn transform_depth_limit() {
+        let data = write_rct_chain(273);
+        let mut bitstream = Bitstream::new(&data);
+        let header = bitstream
+            .read_bundle_with_ctx::<ModularHeader, _>(MAX_TRANSFORM_DEPTH)
+            .unwrap();
+        assert_eq!(header.transform.len(), 273);
+
+        // Nested image under an image with 273 transforms exceeds the limit.
+        let mut bitstream = Bitstream::new(&data);
+        let err = bitstream
+            .read_bundle_with_ctx::<ModularHeader, _>(MAX_TRANSFORM_DEPTH - 273)
+            .unwrap_err();
+        assert!(matches!(err, Error::TooManyTransforms), "{err:?}");
+
+        // The limit is checked before reading transforms.
+        let mut bitstream = Bitstream::new(&data[..3]);
+        let err = bitstream
+            .read_bundle_with_ctx::<ModularHeader, _>(0)
+            .unwrap_err();
+        assert!(matches!(err, Error::TooManyTransforms), "{err:?}");
+    }
+}
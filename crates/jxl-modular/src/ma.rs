@@ -13,6 +13,10 @@ use crate::{sample::Sealed, Result, Sample};
 /// Meta-adaptive (MA) tree is a decision tree that controls how the sample is decoded in the given
 /// context. The configuration consists of two components: the MA tree itself, and the distribution
 /// information of an entropy decoder. These components are read from the bitstream.
+///
+/// Cloning `MaConfig` is cheap, as the tree and the distributions are shared. A global MA tree is
+/// parsed once in `LfGlobal` and then cloned for each group using it; each group clones its own
+/// entropy decoder from [`decoder`][Self::decoder], so decoder state doesn't leak between groups.
 #[derive(Debug, Clone)]
 pub struct MaConfig {
     num_tree_nodes: usize,
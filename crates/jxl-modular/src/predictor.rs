@@ -176,6 +176,10 @@ impl<'prev, 'a, S: Sample> PredictorState<'prev, 'a, S> {
         }
     }
 
+    /// Resets the state to decode a new channel.
+    ///
+    /// Error state of the self-correcting predictor is local to a channel in a single group, so it
+    /// is reinitialized here. This allows groups to be decoded independently of each other.
     pub fn reset(
         &mut self,
         width: u32,
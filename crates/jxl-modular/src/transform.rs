@@ -492,3 +492,326 @@ impl SqueezeParams {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use jxl_bitstream::BundleDefault;
+    use jxl_grid::AlignedGrid;
+
+    use super::*;
+    use crate::{
+        image::ModularImageDestination, ChannelShift, MaConfig, MaConfigParams, ModularChannels,
+        ModularHeader,
+    };
+
+    const WIDTH: usize = 8;
+    const HEIGHT: usize = 2;
+
+    fn forward_ycocg([r, g, b]: [i32; 3]) -> [i32; 3] {
+        let co = r - b;
+        let tmp = b + (co >> 1);
+        let cg = g - tmp;
+        let y = tmp + (cg >> 1);
+        [y, co, cg]
+    }
+
+    fn forward_squeeze_h(row: &[i32]) -> (Vec<i32>, Vec<i32>) {
+        let avg = row
+            .chunks(2)
+            .map(|pair| match *pair {
+                [a, b] => a - (a - b) / 2,
+                [a] => a,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut residu = Vec::new();
+        let mut left = avg[0];
+        for (x, pair) in row.chunks_exact(2).enumerate() {
+            let next_avg = avg.get(x + 1).copied().unwrap_or(avg[x]);
+            let diff = pair[0] - pair[1];
+            residu.push(diff - squeeze::tendency_i32(left, avg[x], next_avg));
+            left = pair[1];
+        }
+        (avg, residu)
+    }
+
+    #[test]
+    fn stacked_transforms_roundtrip() {
+        let colours = [[200, 10, 30], [15, 120, 240], [90, 90, 90]];
+        let pattern: [i32; WIDTH * HEIGHT] = [0, 0, 1, 2, 2, 1, 0, 2, 1, 1, 1, 0, 2, 0, 0, 1];
+        let original = (0..3)
+            .map(|c| {
+                pattern
+                    .iter()
+                    .map(|&idx| colours[idx as usize][c])
+                    .collect::<Vec<i32>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Encoder side: RCT, then Palette over the RCT output, then Squeeze of the indices.
+        let ycocg = colours.map(forward_ycocg);
+        let transforms = [
+            TransformInfo::Rct(Rct {
+                begin_c: 0,
+                rct_type: 6,
+            }),
+            TransformInfo::Palette(Palette {
+                begin_c: 0,
+                num_c: 3,
+                nb_colours: colours.len() as u32,
+                nb_deltas: 0,
+                d_pred: Predictor::Zero,
+                wp_header: None,
+            }),
+            TransformInfo::Squeeze(Squeeze {
+                num_sq: 1,
+                sp: vec![SqueezeParams {
+                    horizontal: true,
+                    in_place: true,
+                    begin_c: 1,
+                    num_c: 1,
+                }],
+            }),
+        ];
+
+        // Inverse transforms are applied by the subimage, in reverse order.
+        let header = ModularHeader {
+            use_global_tree: false,
+            wp_params: WpHeader::default_with_context(()),
+            transform: transforms.to_vec(),
+        };
+        let channels = ModularChannels {
+            info: (0..3)
+                .map(|_| {
+                    ModularChannelInfo::new(
+                        WIDTH as u32,
+                        HEIGHT as u32,
+                        ChannelShift::from_shift(0),
+                    )
+                })
+                .collect(),
+            nb_meta_channels: 0,
+        };
+        let mut image = ModularImageDestination::<i32>::new(
+            header,
+            single_leaf_ma_config(),
+            256,
+            8,
+            channels,
+            None,
+        )
+        .unwrap();
+        let mut subimage = image.prepare_subimage().unwrap();
+        let grids = subimage.grids_mut();
+        assert_eq!(grids.len(), 3);
+
+        for (idx, colour) in ycocg.iter().enumerate() {
+            for (c, &v) in colour.iter().enumerate() {
+                *grids[0].grid_mut().get_mut(idx, c) = v;
+            }
+        }
+        for y in 0..HEIGHT {
+            let (avg, residu) = forward_squeeze_h(&pattern[y * WIDTH..][..WIDTH]);
+            grids[1].grid_mut().get_row_mut(y).copy_from_slice(&avg);
+            grids[2].grid_mut().get_row_mut(y).copy_from_slice(&residu);
+        }
+
+        subimage.finish(&JxlThreadPool::none());
+        for (grid, expected) in image.image_channels().iter().zip(&original) {
+            assert_eq!(grid.buf(), &expected[..]);
+        }
+    }
+
+    /// Parses an MA tree with a single leaf, whose entropy decoders only have symbol 0.
+    fn single_leaf_ma_config() -> MaConfig {
+        let mut bits = Vec::new();
+        let mut push = |value: u32, n: u32| {
+            bits.extend((0..n).map(|i| (value >> i) & 1 != 0));
+        };
+        // Tree decoder: no LZ77, all contexts in a single cluster, prefix code with one symbol
+        push(0, 1);
+        push(1, 1);
+        push(0, 2);
+        push(1, 1);
+        push(15, 4);
+        push(0, 1);
+        // Data decoder of the leaf
+        push(0, 1);
+        push(1, 1);
+        push(15, 4);
+        push(0, 1);
+        let data = bits
+            .chunks(8)
+            .map(|byte| byte.iter().rev().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+            .collect::<Vec<_>>();
+
+        let mut bitstream = Bitstream::new(&data);
+        MaConfig::parse(
+            &mut bitstream,
+            MaConfigParams {
+                tracker: None,
+                node_limit: 1,
+            },
+        )
+        .unwrap()
+    }
+
+    /// Image stored as rows of samples.
+    type Plane = Vec<Vec<i32>>;
+
+    fn forward_squeeze(plane: &Plane, horizontal: bool) -> (Plane, Plane) {
+        if horizontal {
+            plane.iter().map(|row| forward_squeeze_h(row)).unzip()
+        } else {
+            let (avg, residu) = forward_squeeze(&transpose(plane), true);
+            (transpose(&avg), transpose(&residu))
+        }
+    }
+
+    fn transpose(plane: &Plane) -> Plane {
+        let width = plane.first().map(|row| row.len()).unwrap_or(0);
+        (0..width)
+            .map(|x| plane.iter().map(|row| row[x]).collect())
+            .collect()
+    }
+
+    fn squeeze_channels(width: u32, height: u32, num_channels: usize) -> ModularChannels {
+        ModularChannels {
+            info: (0..num_channels)
+                .map(|_| ModularChannelInfo::new(width, height, ChannelShift::from_shift(0)))
+                .collect(),
+            nb_meta_channels: 0,
+        }
+    }
+
+    fn default_params(width: u32, height: u32, num_channels: usize) -> Vec<(u32, u32, bool, bool)> {
+        let mut tr = TransformInfo::Squeeze(Squeeze {
+            num_sq: 0,
+            sp: Vec::new(),
+        });
+        tr.prepare_transform_info(&mut squeeze_channels(width, height, num_channels))
+            .unwrap();
+        let TransformInfo::Squeeze(sq) = tr else {
+            unreachable!()
+        };
+        sq.sp
+            .iter()
+            .map(|sp| (sp.begin_c, sp.num_c, sp.in_place, sp.horizontal))
+            .collect()
+    }
+
+    #[test]
+    fn default_squeeze_params() {
+        // Tall image: chroma first, then vertical squeeze before alternating.
+        assert_eq!(
+            default_params(20, 40, 3),
+            [
+                (1, 2, false, true),
+                (1, 2, false, false),
+                (0, 3, true, false),
+                (0, 3, true, true),
+                (0, 3, true, false),
+                (0, 3, true, true),
+                (0, 3, true, false),
+            ]
+        );
+        // Wide image: starts with horizontal squeeze.
+        assert_eq!(
+            default_params(40, 20, 1),
+            [
+                (0, 1, true, true),
+                (0, 1, true, false),
+                (0, 1, true, true),
+                (0, 1, true, false),
+                (0, 1, true, true),
+            ]
+        );
+        // Square images are squeezed vertically first.
+        assert_eq!(
+            default_params(9, 9, 1),
+            [(0, 1, true, false), (0, 1, true, true)]
+        );
+        // Small images are not squeezed, except for chroma.
+        assert!(default_params(8, 8, 1).is_empty());
+        assert_eq!(
+            default_params(8, 8, 3),
+            [(1, 2, false, true), (1, 2, false, false)]
+        );
+    }
+
+    #[test]
+    fn default_squeeze_roundtrip() {
+        const WIDTH: u32 = 21;
+        const HEIGHT: u32 = 37;
+        let original = (0..3)
+            .map(|c| {
+                (0..HEIGHT as i32)
+                    .map(|y| {
+                        (0..WIDTH as i32)
+                            .map(|x| (x * 7 + y * 13 + c * 5 + x * y) % 61 - 20)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Plane>>();
+
+        let mut tr = TransformInfo::Squeeze(Squeeze {
+            num_sq: 0,
+            sp: Vec::new(),
+        });
+        tr.prepare_transform_info(&mut squeeze_channels(WIDTH, HEIGHT, 3))
+            .unwrap();
+        let TransformInfo::Squeeze(sq) = &tr else {
+            unreachable!()
+        };
+        assert!(!sq.sp.is_empty());
+
+        // Encoder side: apply squeeze steps in order, placing residuals the same way as the
+        // decoder does.
+        let mut planes = original.clone();
+        for sp in &sq.sp {
+            let begin = sp.begin_c as usize;
+            let end = begin + sp.num_c as usize;
+            let mut residu = Vec::new();
+            for plane in &mut planes[begin..end] {
+                let (avg, r) = forward_squeeze(plane, sp.horizontal);
+                *plane = avg;
+                residu.push(r);
+            }
+            if sp.in_place {
+                residu.extend(planes.drain(end..));
+            }
+            planes.extend(residu);
+        }
+
+        let mut image = [(); 3].map(|_| {
+            AlignedGrid::<i32>::with_alloc_tracker(WIDTH as usize, HEIGHT as usize, None).unwrap()
+        });
+        let mut channels = squeeze_channels(WIDTH, HEIGHT, 3);
+        let mut grids = image
+            .iter_mut()
+            .map(|g| g.as_subgrid_mut().into())
+            .collect::<Vec<_>>();
+        tr.transform_channels(&mut channels, &mut Vec::new(), &mut grids)
+            .unwrap();
+        assert_eq!(grids.len(), planes.len());
+
+        for (grid, plane) in grids.iter_mut().zip(&planes) {
+            let grid = grid.grid_mut();
+            assert_eq!(grid.height(), plane.len());
+            for (y, row) in plane.iter().enumerate() {
+                assert_eq!(grid.width(), row.len());
+                grid.get_row_mut(y).copy_from_slice(row);
+            }
+        }
+
+        tr.inverse(&mut grids, 8, &JxlThreadPool::none());
+        drop(grids);
+
+        for (grid, expected) in image.iter().zip(&original) {
+            let expected = expected.concat();
+            assert_eq!(grid.buf(), &expected[..]);
+        }
+    }
+}
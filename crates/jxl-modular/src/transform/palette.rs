@@ -171,3 +171,72 @@ fn inverse_simple<S: Sample>(palette: SharedSubgrid<S>, targets: Vec<MutableSubg
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use jxl_grid::AlignedGrid;
+
+    use super::*;
+
+    fn three_channel_palette(nb_colours: u32) -> (Palette, AlignedGrid<i32>) {
+        let palette = Palette {
+            begin_c: 0,
+            num_c: 3,
+            nb_colours,
+            nb_deltas: 0,
+            d_pred: Predictor::Zero,
+            wp_header: None,
+        };
+        let mut grid = AlignedGrid::with_alloc_tracker(nb_colours as usize, 3, None).unwrap();
+        for c in 0..3 {
+            for idx in 0..nb_colours as usize {
+                *grid.get_mut(idx, c).unwrap() = (idx * 10 + c) as i32;
+            }
+        }
+        (palette, grid)
+    }
+
+    fn run_inverse(
+        palette: &Palette,
+        palette_grid: &AlignedGrid<i32>,
+        indices: &[i32],
+    ) -> Vec<[i32; 3]> {
+        let width = indices.len();
+        let mut targets =
+            [(); 3].map(|_| AlignedGrid::<i32>::with_alloc_tracker(width, 1, None).unwrap());
+        targets[0].buf_mut().copy_from_slice(indices);
+
+        let [a, b, c] = &mut targets;
+        let subgrids = vec![a.as_subgrid_mut(), b.as_subgrid_mut(), c.as_subgrid_mut()];
+        palette.inverse_inner(palette_grid.as_subgrid(), subgrids, 8);
+
+        (0..width)
+            .map(|x| targets.each_ref().map(|g| *g.get(x, 0).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn three_channels() {
+        let (palette, palette_grid) = three_channel_palette(4);
+        let out = run_inverse(&palette, &palette_grid, &[0, 3, 1, 2, 3]);
+        assert_eq!(
+            out,
+            [
+                [0, 1, 2],
+                [30, 31, 32],
+                [10, 11, 12],
+                [20, 21, 22],
+                [30, 31, 32]
+            ]
+        );
+    }
+
+    #[test]
+    fn three_channels_implicit_colours() {
+        let (palette, palette_grid) = three_channel_palette(2);
+        // Indices past `nb_colours` refer to implicit colors; every channel should be filled.
+        let out = run_inverse(&palette, &palette_grid, &[1, 2 + 0b10_01_11]);
+        assert_eq!(out[0], [10, 11, 12]);
+        assert_eq!(out[1], [3 * 255 / 4 + 32, 255 / 4 + 32, 2 * 255 / 4 + 32]);
+    }
+}
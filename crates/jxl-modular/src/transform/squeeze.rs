@@ -1101,7 +1101,7 @@ unsafe fn inverse_v_i16_wasm32_simd128(merged: &mut MutableSubgrid<'_, i16>) {
     }
 }
 
-fn tendency_i32(a: i32, b: i32, c: i32) -> i32 {
+pub(super) fn tendency_i32(a: i32, b: i32, c: i32) -> i32 {
     let a = Wrapping(a);
     let b = Wrapping(b);
     let c = Wrapping(c);
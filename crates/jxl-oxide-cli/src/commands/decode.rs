@@ -63,6 +63,9 @@ pub struct DecodeArgs {
     ///               Transfer function is not set for this preset; one should be provided, e.g. rec2100,tf=pq
     #[arg(long, value_parser = super::parse_color_encoding, verbatim_doc_comment)]
     pub target_colorspace: Option<EnumColourEncoding>,
+    /// Apply ordered dithering to color channels when writing 8-bit output
+    #[arg(long)]
+    pub dither: bool,
     /// (unstable) Path to target ICC profile
     #[arg(long)]
     pub target_icc: Option<PathBuf>,
@@ -172,6 +172,7 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
                     &keyframes,
                     pixel_format,
                     force_bit_depth,
+                    args.dither,
                     width,
                     height,
                 )
@@ -184,6 +185,7 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
                     &keyframes,
                     pixel_format,
                     Some(png::BitDepth::Eight),
+                    args.dither,
                     width,
                     height,
                 )
@@ -196,6 +198,7 @@ pub fn handle_decode(args: DecodeArgs) -> Result<()> {
                     &keyframes,
                     pixel_format,
                     Some(png::BitDepth::Sixteen),
+                    false,
                     width,
                     height,
                 )
@@ -131,14 +131,13 @@ pub fn handle_info(args: InfoArgs) -> Result<()> {
             );
         }
         if animated && is_keyframe {
-            if frame_header.duration == 0xffffffff {
-                println!("  End of a page");
-            } else {
-                println!(
+            match frame_header.frame_duration() {
+                FrameDuration::UntilUserInput => println!("  End of a page"),
+                FrameDuration::Ticks(duration) => println!(
                     "  Duration: {} tick{}",
-                    frame_header.duration,
-                    if frame_header.duration == 1 { "" } else { "s" }
-                );
+                    duration,
+                    if duration == 1 { "" } else { "s" }
+                ),
             }
         }
 
@@ -1,13 +1,15 @@
 use std::io::prelude::*;
 
-use jxl_oxide::{FrameBuffer, JxlImage, PixelFormat, Render};
+use jxl_oxide::{Endianness, FrameBuffer, JxlImage, PixelFormat, Render};
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn write_png<W: Write>(
     output: W,
     image: &JxlImage,
     keyframes: &[Render],
     pixfmt: PixelFormat,
     force_bit_depth: Option<png::BitDepth>,
+    dither: bool,
     width: u32,
     height: u32,
 ) -> std::io::Result<()> {
@@ -93,19 +95,10 @@ pub(crate) fn write_png<W: Write>(
         stream.write_to_buffer(fb.buf_mut());
 
         if sixteen_bits {
-            let mut buf = vec![0u8; fb.width() * fb.height() * fb.channels() * 2];
-            for (b, s) in buf.chunks_exact_mut(2).zip(fb.buf()) {
-                let w = (*s * 65535.0 + 0.5).clamp(0.0, 65535.0) as u16;
-                let [b0, b1] = w.to_be_bytes();
-                b[0] = b0;
-                b[1] = b1;
-            }
+            let buf = fb.to_u16_bytes(16, Endianness::Big);
             writer.write_image_data(&buf)?;
         } else {
-            let mut buf = vec![0u8; fb.width() * fb.height() * fb.channels()];
-            for (b, s) in buf.iter_mut().zip(fb.buf()) {
-                *b = (*s * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
-            }
+            let buf = fb.to_u8(keyframe.color_channels().len(), dither);
             writer.write_image_data(&buf)?;
         }
     }
@@ -140,6 +140,7 @@ fn run_once(image: &mut JxlImage, current_iter: usize, output_dir: Option<&Path>
         &[render],
         image.pixel_format(),
         None,
+        false,
         width,
         height,
     )
@@ -23,6 +23,7 @@ fn decode(c: &mut Criterion) {
     bench_one(c, &bench_path, "starrail.d1-e6", &pool);
     bench_one(c, &bench_path, "genshin-cafe.d2-e6-epf2", &pool);
     bench_one(c, &bench_path, "genshin-cafe.d2-e6-epf3", &pool);
+    bench_animation(c, &pool);
 }
 
 fn bench_one(c: &mut Criterion, bench_path: &Path, name: &str, pool: &JxlThreadPool) {
@@ -69,5 +70,37 @@ fn bench_one(c: &mut Criterion, bench_path: &Path, name: &str, pool: &JxlThreadP
     g.finish();
 }
 
+fn bench_animation(c: &mut Criterion, pool: &JxlThreadPool) {
+    let mut g = c.benchmark_group("animation_vardct_500x300");
+
+    let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/synthetic/animation_vardct_500x300.jxl");
+    let data = std::fs::read(path).unwrap();
+
+    let image = jxl_oxide::JxlImage::builder()
+        .pool(pool.clone())
+        .read(Cursor::new(&data))
+        .unwrap();
+    let num_frames = image.num_loaded_keyframes();
+    g.throughput(criterion::Throughput::Elements(
+        image.width() as u64 * image.height() as u64 * num_frames as u64,
+    ));
+
+    // Renders every frame from a single image, so that scratch buffers are reused.
+    g.bench_function("all-frames", |b| {
+        b.iter_with_large_drop(|| {
+            let image = jxl_oxide::JxlImage::builder()
+                .pool(pool.clone())
+                .read(Cursor::new(&data))
+                .unwrap();
+            (0..num_frames)
+                .map(|idx| image.render_frame(black_box(idx)).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    g.finish();
+}
+
 criterion_group!(group, decode);
 criterion_main!(group);
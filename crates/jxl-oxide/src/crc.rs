@@ -0,0 +1,43 @@
+/// Running CRC-32 (ISO-HDLC, as used in zlib and PNG) of byte stream.
+#[derive(Debug, Clone)]
+pub(crate) struct Crc32 {
+    state: u32,
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut c = idx as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[idx] = c;
+        idx += 1;
+    }
+    table
+};
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    pub(crate) fn update(&mut self, buf: &[u8]) {
+        let mut state = self.state;
+        for &b in buf {
+            state = CRC_TABLE[((state ^ b as u32) & 0xff) as usize] ^ (state >> 8);
+        }
+        self.state = state;
+    }
+
+    pub(crate) fn value(&self) -> u32 {
+        !self.state
+    }
+}
@@ -49,27 +49,13 @@ impl FrameBuffer {
         let width = width as usize;
         let height = height as usize;
 
-        let (outw, outh) = match orientation {
-            1..=4 => (width, height),
-            5..=8 => (height, width),
-            _ => unreachable!(),
-        };
+        let (outw, outh) = oriented_size(orientation, width, height);
         let mut out = Self::new(outw, outh, channels);
         let buf = out.buf_mut();
         for y in 0..height {
             for x in 0..width {
                 for (c, (g, region)) in grids.iter().zip(grid_regions).enumerate() {
-                    let (outx, outy) = match orientation {
-                        1 => (x, y),
-                        2 => (width - x - 1, y),
-                        3 => (width - x - 1, height - y - 1),
-                        4 => (x, height - y - 1),
-                        5 => (y, x),
-                        6 => (height - y - 1, x),
-                        7 => (height - y - 1, width - x - 1),
-                        8 => (y, width - x - 1),
-                        _ => unreachable!(),
-                    };
+                    let (outx, outy) = orient_coord(orientation, width, height, x, y);
                     let idx = c + (outx + outy * outw) * channels;
 
                     let base_x = (left - region.left) as isize;
@@ -165,6 +151,103 @@ impl FrameBuffer {
             std::slice::from_raw_parts_mut(self.buf.as_mut_ptr() as *mut [f32; N], grouped_len)
         }
     }
+
+    /// Quantizes the contents of frame buffer to 8-bit samples.
+    ///
+    /// If `dither` is `true`, ordered dithering is applied to the first `color_channels` channels,
+    /// which reduces banding in smooth gradients. Samples are dithered as is, so dithering happens
+    /// in the perceptual domain if the image is rendered to gamma-encoded color space such as
+    /// sRGB. Other channels, including alpha, are rounded without dithering so that fully opaque
+    /// and fully transparent pixels are kept intact.
+    pub fn to_u8(&self, color_channels: usize, dither: bool) -> Vec<u8> {
+        // 8x8 Bayer matrix.
+        const BAYER: [[u8; 8]; 8] = [
+            [0, 32, 8, 40, 2, 34, 10, 42],
+            [48, 16, 56, 24, 50, 18, 58, 26],
+            [12, 44, 4, 36, 14, 46, 6, 38],
+            [60, 28, 52, 20, 62, 30, 54, 22],
+            [3, 35, 11, 43, 1, 33, 9, 41],
+            [51, 19, 59, 27, 49, 17, 57, 25],
+            [15, 47, 7, 39, 13, 45, 5, 37],
+            [63, 31, 55, 23, 61, 29, 53, 21],
+        ];
+
+        let channels = self.channels;
+        let mut out = vec![0u8; self.buf.len()];
+        for (idx, (o, &s)) in out.iter_mut().zip(&self.buf).enumerate() {
+            let c = idx % channels;
+            let offset = if dither && c < color_channels {
+                let pixel_idx = idx / channels;
+                let x = pixel_idx % self.width;
+                let y = pixel_idx / self.width;
+                (BAYER[y % 8][x % 8] as f32 + 0.5) / 64.0
+            } else {
+                0.5
+            };
+            *o = (s * 255.0 + offset).floor().clamp(0.0, 255.0) as u8;
+        }
+        out
+    }
+
+    /// Quantizes the contents of frame buffer to samples with the given bit depth, stored in
+    /// `u16`.
+    ///
+    /// Use this with [`EnumColourEncoding::bt2100_pq`] to get 10-bit PQ samples for HDR10
+    /// displays.
+    ///
+    /// # Panics
+    /// Panics if `bit_depth` is not in range `1..=16`.
+    ///
+    /// [`EnumColourEncoding::bt2100_pq`]: crate::EnumColourEncoding::bt2100_pq
+    pub fn to_u16(&self, bit_depth: u32) -> Vec<u16> {
+        assert!(
+            (1..=16).contains(&bit_depth),
+            "invalid bit depth {bit_depth}"
+        );
+
+        let max = ((1u32 << bit_depth) - 1) as f32;
+        self.buf
+            .iter()
+            .map(|&s| (s * max + 0.5).floor().clamp(0.0, max) as u16)
+            .collect()
+    }
+
+    /// Quantizes the contents of frame buffer to samples with the given bit depth, and writes
+    /// them as two bytes per sample in the given byte order.
+    ///
+    /// PNG expects big-endian samples, while most in-memory consumers expect native or
+    /// little-endian ones.
+    ///
+    /// # Panics
+    /// Panics if `bit_depth` is not in range `1..=16`.
+    pub fn to_u16_bytes(&self, bit_depth: u32, endianness: Endianness) -> Vec<u8> {
+        let samples = self.to_u16(bit_depth);
+        let mut out = Vec::with_capacity(samples.len() * 2);
+        for s in samples {
+            let bytes = match endianness {
+                Endianness::Big => s.to_be_bytes(),
+                Endianness::Little => s.to_le_bytes(),
+            };
+            out.extend_from_slice(&bytes);
+        }
+        out
+    }
+}
+
+/// Byte order of multi-byte samples.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+impl Endianness {
+    /// Byte order of the target platform.
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Self = Self::Big;
+    /// Byte order of the target platform.
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Self = Self::Little;
 }
 
 /// Image stream that writes to borrowed buffer.
@@ -407,3 +490,33 @@ struct ImageStreamSpotColor<'r> {
     rgb: (f32, f32, f32),
     solidity: f32,
 }
+
+/// Returns the size of `width` by `height` image after applying orientation.
+pub(crate) fn oriented_size(orientation: u32, width: usize, height: usize) -> (usize, usize) {
+    match orientation {
+        1..=4 => (width, height),
+        5..=8 => (height, width),
+        _ => unreachable!(),
+    }
+}
+
+/// Maps the coordinate of `width` by `height` image to the one after applying orientation.
+pub(crate) fn orient_coord(
+    orientation: u32,
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+) -> (usize, usize) {
+    match orientation {
+        1 => (x, y),
+        2 => (width - x - 1, y),
+        3 => (width - x - 1, height - y - 1),
+        4 => (x, height - y - 1),
+        5 => (y, x),
+        6 => (height - y - 1, x),
+        7 => (height - y - 1, width - x - 1),
+        8 => (y, width - x - 1),
+        _ => unreachable!(),
+    }
+}
@@ -108,6 +108,19 @@
 //! image.request_color_encoding(color_encoding);
 //! ```
 //!
+//! For HDR10 displays, request BT.2100 PQ color encoding and quantize the output to 10-bit
+//! samples.
+//!
+//! ```no_run
+//! # use jxl_oxide::{EnumColourEncoding, JxlImage, RenderingIntent};
+//! # let reader = std::io::empty();
+//! let mut image = JxlImage::builder().read(reader).expect("Failed to read image header");
+//! image.request_color_encoding(EnumColourEncoding::bt2100_pq(RenderingIntent::Relative));
+//!
+//! let render = image.render_frame(0).expect("Failed to render frame");
+//! let samples = render.image_all_channels().to_u16(10);
+//! ```
+//!
 //! External CMS is set to Little CMS 2 by default if `lcms2` feature is enabled. You can
 //! explicitly disable this by setting CMS to [`NullCms`].
 //!
@@ -142,17 +155,22 @@
 //! # Feature flags
 //! - `rayon`: Enable multithreading with Rayon. (*default*)
 //! - `lcms2`: Enable integration with Little CMS 2.
-use std::sync::Arc;
+use std::sync::{atomic::AtomicBool, Arc};
 
 use image::BitDepth;
-use jxl_bitstream::ContainerDetectingReader;
 use jxl_bitstream::Name;
 use jxl_bitstream::{Bitstream, Bundle};
+use jxl_bitstream::{
+    ContainerBoxHeader, ContainerBoxType, ContainerDetectingReader, HeaderParseResult,
+};
 use jxl_frame::FrameContext;
 use jxl_render::ImageBuffer;
 use jxl_render::ImageWithRegion;
 use jxl_render::Region;
-use jxl_render::{IndexedFrame, RenderContext};
+use jxl_render::{IndexedFrame, RenderContext, XybHook};
+
+use crc::Crc32;
+use signature::SignatureScan;
 
 pub use jxl_bitstream::Lz77Mode;
 pub use jxl_color::header as color;
@@ -160,19 +178,25 @@ pub use jxl_color::{
     ColorEncodingWithProfile, ColorManagementSystem, EnumColourEncoding, NullCms, RenderingIntent,
 };
 pub use jxl_frame::header as frame;
-pub use jxl_frame::{Frame, FrameHeader};
+pub use jxl_frame::{data::TocGroupKind, Frame, FrameHeader};
 pub use jxl_grid::{AlignedGrid, AllocTracker};
 pub use jxl_image as image;
 pub use jxl_image::{ExtraChannelType, ImageHeader};
+pub use jxl_render::{DecodedRegion, RenderFeatures};
 pub use jxl_threadpool::JxlThreadPool;
 
+mod crc;
 mod fb;
 #[cfg(feature = "lcms2")]
 mod lcms2;
+mod signature;
+mod ycbcr;
 
 #[cfg(feature = "lcms2")]
 pub use self::lcms2::Lcms2;
-pub use fb::{FrameBuffer, ImageStream};
+pub use fb::{Endianness, FrameBuffer, ImageStream};
+pub use signature::find_jxl_start;
+pub use ycbcr::{ChromaSubsampling, YcbcrImage, YcbcrMatrix};
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
@@ -191,6 +215,12 @@ fn default_pool() -> JxlThreadPool {
 pub struct JxlImageBuilder {
     pool: Option<JxlThreadPool>,
     tracker: Option<AllocTracker>,
+    cancellation_flag: Option<Arc<AtomicBool>>,
+    features: RenderFeatures,
+    xyb_hook: Option<XybHook>,
+    max_spline_area: Option<u64>,
+    codestream_crc32: bool,
+    scan_signature: bool,
     lz77_mode: Lz77Mode,
 }
 
@@ -207,6 +237,78 @@ impl JxlImageBuilder {
         self
     }
 
+    /// Limits the total size of buffers allocated while decoding to `bytes` bytes.
+    ///
+    /// This is a shorthand for setting an allocation tracker with [`AllocTracker::with_limit`].
+    /// Rendering fails with [`jxl_render::Error::MemoryBudgetExceeded`] before allocating a
+    /// buffer which would exceed the budget.
+    pub fn memory_budget(self, bytes: usize) -> Self {
+        self.alloc_tracker(AllocTracker::with_limit(bytes))
+    }
+
+    /// Sets a flag which cancels rendering when set to `true`.
+    ///
+    /// Rendering checks the flag before decoding each group, including those decoded in parallel,
+    /// and fails with a "decoding was cancelled" error if it's set. Call
+    /// [`JxlImage::reset_render_cache`] after clearing the flag to render the image again.
+    pub fn cancellation_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancellation_flag = Some(flag);
+        self
+    }
+
+    /// Sets frame features to render, such as patches, splines and noise.
+    ///
+    /// Disabling features makes the output non-conformant, but it helps inspecting the base image
+    /// without overlays or noise.
+    pub fn features(mut self, features: RenderFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Sets a hook which is called with XYB samples of every rendered frame right before color
+    /// conversion.
+    ///
+    /// `hook` receives X, Y and B channels of the rendered region after reconstruction,
+    /// restoration filters and frame features, and can modify the samples in place to apply
+    /// custom adjustments in the perceptual color space. Frames of animations are passed to the
+    /// hook before they're blended. It is not called if the image is not XYB encoded.
+    pub fn xyb_hook(
+        mut self,
+        hook: impl Fn([&mut AlignedGrid<f32>; 3]) + Send + Sync + 'static,
+    ) -> Self {
+        self.xyb_hook = Some(XybHook::new(hook));
+        self
+    }
+
+    /// Sets the maximum total estimated area of splines in a frame.
+    ///
+    /// Frames with splines exceeding the limit fail to decode, so that malicious images can't make
+    /// the decoder spend too much time drawing splines. Defaults to the limit of Level 10, which
+    /// depends on the image size.
+    pub fn max_spline_area(mut self, max_spline_area: u64) -> Self {
+        self.max_spline_area = Some(max_spline_area);
+        self
+    }
+
+    /// Sets whether to compute CRC-32 of the codestream while decoding.
+    ///
+    /// The checksum is computed over codestream bytes only, excluding container boxes. It can be
+    /// retrieved with [`JxlImage::codestream_crc32`].
+    pub fn codestream_crc32(mut self, enabled: bool) -> Self {
+        self.codestream_crc32 = enabled;
+        self
+    }
+
+    /// Sets whether to skip leading garbage before the JPEG XL signature.
+    ///
+    /// If enabled, the decoder buffers input until it finds the signature, as done by
+    /// [`find_jxl_start`], and starts decoding from there. Feeding data fails if no signature is
+    /// found within the first 1 MiB of the input.
+    pub fn scan_signature(mut self, enabled: bool) -> Self {
+        self.scan_signature = enabled;
+        self
+    }
+
     #[doc(hidden)]
     pub fn lz77_mode(mut self, lz77_mode: Lz77Mode) -> Self {
         self.lz77_mode = lz77_mode;
@@ -218,6 +320,13 @@ impl JxlImageBuilder {
         UninitializedJxlImage {
             pool: self.pool.unwrap_or_else(default_pool),
             tracker: self.tracker,
+            cancellation_flag: self.cancellation_flag,
+            features: self.features,
+            xyb_hook: self.xyb_hook,
+            max_spline_area: self.max_spline_area,
+            crc: self.codestream_crc32.then(Crc32::new),
+            signature_scan_buffer: self.scan_signature.then(Vec::new),
+            signature_scan_skipped: 0,
             reader: ContainerDetectingReader::new(),
             buffer: Vec::new(),
             lz77_mode: self.lz77_mode,
@@ -267,6 +376,72 @@ impl JxlImageBuilder {
         let file = std::fs::File::open(path)?;
         self.read(file)
     }
+
+    /// Consumes the builder, and creates a JPEG XL image decoder by reading an image which starts
+    /// at `offset` of `data`, possibly followed by other files.
+    ///
+    /// The end of the image is determined by box sizes if the image is in a container, or by the
+    /// end of the last frame if the image is a bare codestream. Returns the decoder with the
+    /// offset to the end of the image, which can be used to read the next file.
+    pub fn read_at(self, data: &[u8], offset: usize) -> Result<(JxlImage, usize)> {
+        let data = data.get(offset..).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "offset out of bounds")
+        })?;
+
+        if data.get(4..8) == Some(&ContainerBoxType::JXL.0) {
+            let len = container_len(data)?;
+            let image = self.read(&data[..len])?;
+            return Ok((image, offset + len));
+        }
+
+        let mut reader = std::io::Cursor::new(data);
+        let mut image = self.read(&mut reader)?;
+        if !image.end_of_image {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "reader ended before the last frame",
+            )
+            .into());
+        }
+        // Bytes read after the last frame belong to the next file.
+        let len = reader.position() as usize - image.buffer.len();
+        image.buffer.clear();
+        Ok((image, offset + len))
+    }
+}
+
+/// Returns the length of the container at the beginning of `data`, which ends right before the
+/// signature of another file.
+fn container_len(data: &[u8]) -> std::io::Result<usize> {
+    let mut pos = 0usize;
+    while pos < data.len() {
+        let buf = &data[pos..];
+        if pos != 0 && buf.starts_with(&[0xff, 0x0a]) {
+            break;
+        }
+
+        let HeaderParseResult::Done { header, size } = ContainerBoxHeader::parse(buf)? else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "truncated box header",
+            ));
+        };
+        if pos != 0 && header.box_type() == ContainerBoxType::JXL {
+            break;
+        }
+
+        let Some(box_size) = header.size() else {
+            return Ok(data.len());
+        };
+        pos = usize::try_from(box_size)
+            .ok()
+            .and_then(|box_size| pos.checked_add(size)?.checked_add(box_size))
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated box")
+            })?;
+    }
+    Ok(pos)
 }
 
 /// Empty, uninitialized JPEG XL image.
@@ -296,6 +471,13 @@ impl JxlImageBuilder {
 pub struct UninitializedJxlImage {
     pool: JxlThreadPool,
     tracker: Option<AllocTracker>,
+    cancellation_flag: Option<Arc<AtomicBool>>,
+    features: RenderFeatures,
+    xyb_hook: Option<XybHook>,
+    max_spline_area: Option<u64>,
+    crc: Option<Crc32>,
+    signature_scan_buffer: Option<Vec<u8>>,
+    signature_scan_skipped: usize,
     reader: ContainerDetectingReader,
     buffer: Vec<u8>,
     lz77_mode: Lz77Mode,
@@ -304,8 +486,42 @@ pub struct UninitializedJxlImage {
 impl UninitializedJxlImage {
     /// Feeds more data into the decoder.
     pub fn feed_bytes(&mut self, buf: &[u8]) -> Result<()> {
+        if let Some(scan_buffer) = &mut self.signature_scan_buffer {
+            scan_buffer.extend_from_slice(buf);
+            let discard = match signature::scan_signature(scan_buffer) {
+                SignatureScan::Found(offset) => {
+                    tracing::debug!(
+                        offset = self.signature_scan_skipped + offset,
+                        "Found signature after leading garbage"
+                    );
+                    let scan_buffer = self.signature_scan_buffer.take().unwrap();
+                    return self.feed_bytes(&scan_buffer[offset..]);
+                }
+                // Bytes before the candidate are garbage, resume scanning from the candidate.
+                SignatureScan::NeedMoreData(offset) => offset,
+                // Keep the tail which may be the start of a signature split across chunks.
+                SignatureScan::NotFound => scan_buffer
+                    .len()
+                    .saturating_sub(signature::CONTAINER_SIG.len() - 1),
+            };
+            scan_buffer.drain(..discard);
+            self.signature_scan_skipped += discard;
+            if self.signature_scan_skipped > signature::MAX_LEADING_GARBAGE {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "JPEG XL signature not found",
+                )
+                .into());
+            }
+            return Ok(());
+        }
+
         self.reader.feed_bytes(buf)?;
-        self.buffer.extend(self.reader.take_bytes());
+        let buf = &*self.reader.take_bytes();
+        if let Some(crc) = &mut self.crc {
+            crc.update(buf);
+        }
+        self.buffer.extend_from_slice(buf);
         Ok(())
     }
 
@@ -358,9 +574,12 @@ impl UninitializedJxlImage {
             let frame = match Frame::parse(
                 &mut bitstream,
                 FrameContext {
-                    image_header: image_header.clone(),
-                    tracker: self.tracker.as_ref(),
-                    pool: self.pool.clone(),
+                    max_spline_area: self.max_spline_area,
+                    ..FrameContext::new(
+                        image_header.clone(),
+                        self.tracker.as_ref(),
+                        self.pool.clone(),
+                    )
                 },
             ) {
                 Ok(x) => x,
@@ -388,13 +607,24 @@ impl UninitializedJxlImage {
 
         let render_spot_color = !image_header.metadata.grayscale();
 
-        let mut builder = RenderContext::builder().pool(self.pool.clone());
+        let mut builder = RenderContext::builder()
+            .pool(self.pool.clone())
+            .features(self.features);
         if let Some(icc) = embedded_icc {
             builder = builder.embedded_icc(icc);
         }
         if let Some(tracker) = self.tracker {
             builder = builder.alloc_tracker(tracker);
         }
+        if let Some(flag) = self.cancellation_flag {
+            builder = builder.cancellation_flag(flag);
+        }
+        if let Some(hook) = self.xyb_hook {
+            builder = builder.xyb_hook(hook);
+        }
+        if let Some(max_spline_area) = self.max_spline_area {
+            builder = builder.max_spline_area(max_spline_area);
+        }
         #[cfg_attr(not(feature = "lcms2"), allow(unused_mut))]
         let mut ctx = builder.build(image_header.clone())?;
         #[cfg(feature = "lcms2")]
@@ -403,12 +633,14 @@ impl UninitializedJxlImage {
         let mut image = JxlImage {
             pool: self.pool.clone(),
             reader: self.reader,
+            crc: self.crc,
             image_header,
             ctx,
             render_spot_color,
             end_of_image: false,
             buffer: Vec::new(),
             buffer_offset: bytes_read,
+            unconsumed: Vec::new(),
             frame_offsets: Vec::new(),
             lz77_mode: self.lz77_mode,
         };
@@ -431,12 +663,14 @@ pub enum InitializeResult {
 pub struct JxlImage {
     pool: JxlThreadPool,
     reader: ContainerDetectingReader,
+    crc: Option<Crc32>,
     image_header: Arc<ImageHeader>,
     ctx: RenderContext,
     render_spot_color: bool,
     end_of_image: bool,
     buffer: Vec<u8>,
     buffer_offset: usize,
+    unconsumed: Vec<u8>,
     frame_offsets: Vec<usize>,
     lz77_mode: Lz77Mode,
 }
@@ -450,25 +684,100 @@ impl JxlImage {
 
     /// Feeds more data into the decoder.
     pub fn feed_bytes(&mut self, buf: &[u8]) -> Result<()> {
-        self.reader.feed_bytes(buf)?;
-        let buf = &*self.reader.take_bytes();
+        let buf = &*self.take_codestream_bytes(buf)?;
+        if let Some(crc) = &mut self.crc {
+            crc.update(buf);
+        }
         self.feed_bytes_inner(buf)
     }
 
-    fn feed_bytes_inner(&mut self, mut buf: &[u8]) -> Result<()> {
+    /// Feeds `buf` into the container reader, and returns codestream bytes to be decoded,
+    /// including those left unconsumed by [`feed_bytes_with_callback`](Self::feed_bytes_with_callback).
+    fn take_codestream_bytes(&mut self, buf: &[u8]) -> Result<Vec<u8>> {
+        self.reader.feed_bytes(buf)?;
+        let bytes = self.reader.take_bytes();
+        if self.unconsumed.is_empty() {
+            return Ok(bytes);
+        }
+        let mut unconsumed = std::mem::take(&mut self.unconsumed);
+        unconsumed.extend_from_slice(&bytes);
+        Ok(unconsumed)
+    }
+
+    /// Feeds more data into the decoder, calling `on_pass` each time a pass of a frame is
+    /// completely loaded.
+    ///
+    /// `on_pass` receives the frame index, as in [`frame`](Self::frame), and the index of the
+    /// pass. It is called once per pass in increasing order, so the image can be rendered
+    /// progressively with [`render_loading_frame`](Self::render_loading_frame). The frame is
+    /// already finalized when its last pass is reported; use [`render_frame`](Self::render_frame)
+    /// if it is a keyframe. Passes loaded before calling this method are not reported.
+    ///
+    /// If `on_pass` returns an error, decoding stops and the error is returned. The rest of `buf`
+    /// is kept, and decoding resumes from there when more data is fed, e.g. with an empty slice.
+    pub fn feed_bytes_with_callback(
+        &mut self,
+        buf: &[u8],
+        mut on_pass: impl FnMut(&mut Self, usize, u32) -> Result<()>,
+    ) -> Result<()> {
+        let bytes = self.take_codestream_bytes(buf)?;
+        let mut buf = &*bytes;
+
+        let mut frame_idx = self.ctx.loaded_frames();
+        let mut next_pass = self
+            .frame(frame_idx)
+            .map(|frame| frame.num_loaded_passes())
+            .unwrap_or(0);
+        while !buf.is_empty() {
+            let count = self.feed_bytes_until(buf, true)?;
+            if let Some(crc) = &mut self.crc {
+                crc.update(&buf[..count]);
+            }
+            buf = &buf[count..];
+
+            while let Some(frame) = self.frame(frame_idx) {
+                let loaded_passes = frame.num_loaded_passes();
+                let is_loading_done = frame.is_loading_done();
+                for pass_idx in next_pass..loaded_passes {
+                    if let Err(e) = on_pass(self, frame_idx, pass_idx) {
+                        self.unconsumed = buf.to_vec();
+                        return Err(e);
+                    }
+                }
+                next_pass = loaded_passes;
+                if !is_loading_done {
+                    break;
+                }
+                frame_idx += 1;
+                next_pass = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn feed_bytes_inner(&mut self, buf: &[u8]) -> Result<()> {
+        self.feed_bytes_until(buf, false)?;
+        Ok(())
+    }
+
+    /// Feeds data into the decoder, and returns the number of bytes consumed.
+    ///
+    /// If `single_group` is `true`, returns early after a frame group is loaded.
+    fn feed_bytes_until(&mut self, mut buf: &[u8], single_group: bool) -> Result<usize> {
+        let total_len = buf.len();
         if buf.is_empty() {
-            return Ok(());
+            return Ok(0);
         }
 
         if self.end_of_image {
             self.buffer.extend_from_slice(buf);
-            return Ok(());
+            return Ok(total_len);
         }
 
         if let Some(loading_frame) = self.ctx.current_loading_frame() {
             debug_assert!(self.buffer.is_empty());
             let len = buf.len();
-            buf = loading_frame.feed_bytes(buf);
+            buf = feed_frame_bytes(loading_frame, buf, single_group);
             let count = len - buf.len();
             self.buffer_offset += count;
 
@@ -478,14 +787,16 @@ impl JxlImage {
                 if is_last {
                     self.end_of_image = true;
                     self.buffer = buf.to_vec();
-                    return Ok(());
+                    return Ok(total_len);
                 }
             }
-            if buf.is_empty() {
-                return Ok(());
+            if buf.is_empty() || single_group {
+                return Ok(total_len - buf.len());
             }
         }
 
+        let consumed_before_buffer = total_len - buf.len();
+        let prev_buffer_len = self.buffer.len();
         self.buffer.extend_from_slice(buf);
         let mut buf = &*self.buffer;
         while !buf.is_empty() {
@@ -495,7 +806,7 @@ impl JxlImage {
                 Ok(x) => x,
                 Err(e) if e.unexpected_eof() => {
                     self.buffer = buf.to_vec();
-                    return Ok(());
+                    return Ok(total_len);
                 }
                 Err(e) => {
                     return Err(e.into());
@@ -508,7 +819,7 @@ impl JxlImage {
             let read_bytes = bitstream.num_read_bits() / 8;
             buf = &buf[read_bytes..];
             let len = buf.len();
-            buf = frame.feed_bytes(buf);
+            buf = feed_frame_bytes(frame, buf, single_group);
             let read_bytes = read_bytes + (len - buf.len());
             self.buffer_offset += read_bytes;
 
@@ -518,17 +829,45 @@ impl JxlImage {
                 if is_last {
                     self.end_of_image = true;
                     self.buffer = buf.to_vec();
-                    return Ok(());
+                    return Ok(total_len);
                 }
             }
+
+            if single_group {
+                let consumed = self.buffer.len() - buf.len() - prev_buffer_len;
+                self.buffer.clear();
+                return Ok(consumed_before_buffer + consumed);
+            }
         }
 
         self.buffer.clear();
-        Ok(())
+        Ok(total_len)
     }
 }
 
+/// Feeds bytes to the frame, up to the end of the group being loaded if `single_group` is `true`.
+fn feed_frame_bytes<'buf>(frame: &mut Frame, buf: &'buf [u8], single_group: bool) -> &'buf [u8] {
+    if !single_group {
+        return frame.feed_bytes(buf);
+    }
+
+    let len = frame.bytes_left_in_group().unwrap_or(0).min(buf.len());
+    let (group, rest) = buf.split_at(len);
+    let group_rest = frame.feed_bytes(group);
+    debug_assert!(group_rest.is_empty());
+    rest
+}
+
 impl JxlImage {
+    /// Returns CRC-32 of the codestream bytes decoded so far, if enabled with
+    /// [`JxlImageBuilder::codestream_crc32`].
+    ///
+    /// The value covers the whole codestream once all input is fed.
+    #[inline]
+    pub fn codestream_crc32(&self) -> Option<u32> {
+        self.crc.as_ref().map(Crc32::value)
+    }
+
     /// Returns the image header.
     #[inline]
     pub fn image_header(&self) -> &ImageHeader {
@@ -634,6 +973,10 @@ impl JxlImage {
 
     /// Requests the decoder to render in specific color encoding, described by
     /// `EnumColourEncoding`.
+    ///
+    /// Requesting [`EnumColourEncoding::xyb`] on an XYB encoded image returns XYB samples after
+    /// reconstruction and restoration filters, without applying inverse opsin transform. Note
+    /// that B channel has Y added back, so gray pixels have zero X and equal Y and B.
     pub fn request_color_encoding(&mut self, color_encoding: EnumColourEncoding) {
         self.ctx
             .request_color_encoding(ColorEncodingWithProfile::new(color_encoding))
@@ -660,6 +1003,14 @@ impl JxlImage {
         self.ctx.request_image_region(region.into());
         self
     }
+
+    /// Discards rendered frames and intermediate render results.
+    ///
+    /// Frames which failed to render, e.g. because rendering was cancelled, can be rendered again
+    /// after calling this method.
+    pub fn reset_render_cache(&mut self) {
+        self.ctx.reset_cache();
+    }
 }
 
 impl JxlImage {
@@ -721,7 +1072,10 @@ impl JxlImage {
     /// Renders the given keyframe with optional cropping region.
     pub fn render_frame_cropped(&self, keyframe_index: usize) -> Result<Render> {
         let image = self.ctx.render_keyframe(keyframe_index)?;
+        Ok(self.wrap_render(keyframe_index, image))
+    }
 
+    fn wrap_render(&self, keyframe_index: usize, image: Arc<ImageWithRegion>) -> Render {
         let image_region = self
             .ctx
             .image_region()
@@ -730,7 +1084,7 @@ impl JxlImage {
         let frame_header = frame.header();
         let target_frame_region = image_region.translate(-frame_header.x0, -frame_header.y0);
 
-        let result = Render {
+        Render {
             keyframe_index,
             name: frame_header.name.clone(),
             duration: frame_header.duration,
@@ -739,9 +1093,10 @@ impl JxlImage {
             extra_channels: self.convert_ec_info(),
             target_frame_region,
             color_bit_depth: self.image_header.metadata.bit_depth,
+            color_encoding: self.ctx.requested_color_encoding().clone(),
+            image_header: Arc::clone(&self.image_header),
             render_spot_color: self.render_spot_color,
-        };
-        Ok(result)
+        }
     }
 
     /// Renders the currently loading keyframe.
@@ -773,6 +1128,8 @@ impl JxlImage {
             extra_channels: self.convert_ec_info(),
             target_frame_region,
             color_bit_depth: self.image_header.metadata.bit_depth,
+            color_encoding: self.ctx.requested_color_encoding().clone(),
+            image_header: Arc::clone(&self.image_header),
             render_spot_color: self.render_spot_color,
         };
         Ok(result)
@@ -891,6 +1248,8 @@ pub struct Render {
     extra_channels: Vec<ExtraChannel>,
     target_frame_region: Region,
     color_bit_depth: BitDepth,
+    color_encoding: ColorEncodingWithProfile,
+    image_header: Arc<ImageHeader>,
     render_spot_color: bool,
 }
 
@@ -908,17 +1267,36 @@ impl Render {
     }
 
     /// Returns how many ticks this frame is presented.
+    ///
+    /// `0xffffffff` means that the frame is presented until user input; use
+    /// [`frame_duration`](Render::frame_duration) to distinguish it.
     #[inline]
     pub fn duration(&self) -> u32 {
         self.duration
     }
 
+    /// Returns how long this frame is presented.
+    #[inline]
+    pub fn frame_duration(&self) -> frame::FrameDuration {
+        frame::FrameDuration::from_ticks(self.duration)
+    }
+
     /// Returns the orientation of the image.
     #[inline]
     pub fn orientation(&self) -> u32 {
         self.orientation
     }
 
+    /// Returns the region of the frame which was decoded to render this image.
+    ///
+    /// The region is in frame coordinates of color samples, before upsampling. It contains the
+    /// requested region with padding needed for reconstruction, and may be widened to cover the
+    /// whole frame if it can't be decoded partially.
+    #[inline]
+    pub fn decoded_region(&self) -> Option<DecodedRegion> {
+        self.image.decoded_region()
+    }
+
     /// Creates a stream that writes to borrowed buffer.
     ///
     /// The stream will include black and alpha channels, if exists, in addition to color channels.
@@ -983,6 +1361,203 @@ impl Render {
             .collect()
     }
 
+    fn channel_bit_depth(&self, channel_index: usize) -> BitDepth {
+        if channel_index < self.image.color_channels() {
+            self.color_bit_depth
+        } else {
+            self.extra_channels[channel_index - self.image.color_channels()].bit_depth
+        }
+    }
+
+    /// Creates a buffer of a single channel, with orientation applied.
+    ///
+    /// Channels are indexed in the same order as [`image_planar`](Render::image_planar). Returns
+    /// `None` if the channel doesn't exist.
+    pub fn image_channel(&self, channel_index: usize) -> Option<AlignedGrid<f32>> {
+        let grid = self.image.buffer().get(channel_index)?;
+        let bit_depth = self.channel_bit_depth(channel_index);
+        let (region, _) = self.image.regions_and_shifts()[channel_index];
+
+        let fb = FrameBuffer::from_grids(
+            &[grid],
+            &[bit_depth],
+            &[region],
+            self.target_frame_region,
+            self.orientation,
+        );
+        let mut out = AlignedGrid::with_alloc_tracker(fb.width(), fb.height(), None).ok()?;
+        out.buf_mut().copy_from_slice(fb.buf());
+        Some(out)
+    }
+
+    /// Creates a buffer of a single channel with integer samples as stored in the Modular image,
+    /// with orientation applied.
+    ///
+    /// Samples are copied as is without going through floating point conversion, so this can be
+    /// used to reconstruct the exact original pixels of lossless images. Returns `None` if the
+    /// channel doesn't exist, or if the channel is not kept as integers, e.g. if the image is
+    /// VarDCT or XYB encoded. Channels with floating point samples also return `None`, as their
+    /// integers are bit patterns of the floats; use [`image_channel`](Render::image_channel) for
+    /// those.
+    pub fn image_channel_int(&self, channel_index: usize) -> Option<AlignedGrid<i32>> {
+        let grid = self.image.buffer().get(channel_index)?;
+        if matches!(grid, ImageBuffer::F32(_)) {
+            return None;
+        }
+        let bit_depth = self.channel_bit_depth(channel_index);
+        if matches!(bit_depth, BitDepth::FloatSample { .. }) {
+            return None;
+        }
+        let (region, _) = self.image.regions_and_shifts()[channel_index];
+        let Region {
+            left,
+            top,
+            width,
+            height,
+        } = self.target_frame_region;
+        let width = width as usize;
+        let height = height as usize;
+
+        let (outw, outh) = fb::oriented_size(self.orientation, width, height);
+        let mut out = AlignedGrid::with_alloc_tracker(outw, outh, None).ok()?;
+        let base_x = (left - region.left) as isize;
+        let base_y = (top - region.top) as isize;
+        for y in 0..height {
+            for x in 0..width {
+                let (outx, outy) = fb::orient_coord(self.orientation, width, height, x, y);
+                let sample = x
+                    .checked_add_signed(base_x)
+                    .zip(y.checked_add_signed(base_y))
+                    .filter(|&(x, y)| x < region.width as usize && y < region.height as usize)
+                    .and_then(|(x, y)| match grid {
+                        ImageBuffer::I32(g) => g.get(x, y).copied(),
+                        ImageBuffer::I16(g) => g.get(x, y).map(|&v| v as i32),
+                        ImageBuffer::F32(_) => unreachable!(),
+                    });
+                *out.get_mut(outx, outy).unwrap() = sample.unwrap_or(0);
+            }
+        }
+        Some(out)
+    }
+
+    /// Returns the first alpha channel as an 8-bit mask, with orientation applied.
+    ///
+    /// Samples are clamped to `[0, 1]` and scaled to `[0, 255]`. Alpha samples are the same
+    /// regardless of whether the color channels are premultiplied (`alpha_associated`), so the
+    /// mask can be used as is in both cases. Returns `None` if the image doesn't have alpha.
+    pub fn alpha_mask(&self) -> Option<AlignedGrid<u8>> {
+        let alpha_idx = self.extra_channels.iter().position(|ec| ec.is_alpha())?;
+        let alpha = self.image_channel(self.image.color_channels() + alpha_idx)?;
+
+        let mut out = AlignedGrid::with_alloc_tracker(alpha.width(), alpha.height(), None).ok()?;
+        for (out, &v) in out.buf_mut().iter_mut().zip(alpha.buf()) {
+            *out = (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        }
+        Some(out)
+    }
+
+    /// Returns the index of the first alpha channel in [`image_all_channels`], and whether it is
+    /// associated with color channels.
+    ///
+    /// [`image_all_channels`]: Render::image_all_channels
+    fn alpha_channel_info(&self) -> Option<(usize, bool)> {
+        let idx = self.extra_channels.iter().position(|ec| ec.is_alpha())?;
+        let associated = matches!(
+            self.extra_channels[idx].ty,
+            ExtraChannelType::Alpha {
+                alpha_associated: true
+            }
+        );
+        Some((self.image.color_channels() + idx, associated))
+    }
+
+    /// Renders the image to interleaved 8-bit RGBA samples, with orientation applied.
+    ///
+    /// If `premultiply` is `true`, color samples are multiplied by alpha; otherwise they are
+    /// straight. Color channels are converted from the decoded data as needed, regardless of
+    /// whether they are premultiplied in the image (`alpha_associated`). Grayscale images are
+    /// expanded to RGB, and alpha is set to 255 if the image doesn't have alpha.
+    pub fn render_rgba8(&self, premultiply: bool) -> Vec<u8> {
+        let fb = self.image_all_channels();
+        let channels = fb.channels();
+        let color_channels = self.image.color_channels().min(3);
+        let alpha = self.alpha_channel_info();
+
+        let quantize = |v: f32| (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        let mut out = Vec::with_capacity(fb.width() * fb.height() * 4);
+        for pixel in fb.buf().chunks_exact(channels) {
+            let (a, scale) = alpha_and_color_scale(pixel, alpha, premultiply);
+
+            let rgb = if color_channels >= 3 {
+                [pixel[0], pixel[1], pixel[2]]
+            } else {
+                [pixel[0]; 3]
+            };
+            out.extend(rgb.map(|v| quantize(v * scale)));
+            out.push(quantize(a));
+        }
+        out
+    }
+
+    /// Renders the image to interleaved premultiplied RGBA samples in linear light, with
+    /// orientation applied.
+    ///
+    /// Color samples are converted to the linear transfer function, keeping white point and
+    /// primaries of the rendered color encoding, and then multiplied by alpha. This is the format
+    /// compositing engines usually work in. Grayscale images are expanded to RGB, and alpha is set
+    /// to 1.0 if the image doesn't have alpha.
+    ///
+    /// # Errors
+    /// Returns an error if the image is rendered to an ICC profile.
+    pub fn render_premultiplied_linear_f32(&self) -> Result<Vec<f32>> {
+        let jxl_color::ColourEncoding::Enum(encoding) = self.color_encoding.encoding() else {
+            return Err(jxl_color::Error::UnsupportedColorEncoding.into());
+        };
+        let linear = ColorEncodingWithProfile::new(EnumColourEncoding {
+            tf: color::TransferFunction::Linear,
+            ..encoding.clone()
+        });
+        let metadata = &self.image_header.metadata;
+        let transform = jxl_color::ColorTransform::new(
+            &self.color_encoding,
+            &linear,
+            &metadata.opsin_inverse_matrix,
+            &metadata.tone_mapping,
+        )?;
+
+        let fb = self.image_all_channels();
+        let channels = fb.channels();
+        let color_channels = self.image.color_channels().min(3);
+        let alpha = self.alpha_channel_info();
+
+        let num_pixels = fb.width() * fb.height();
+        let mut planes = vec![vec![0f32; num_pixels]; color_channels];
+        let mut alpha_plane = vec![1f32; num_pixels];
+        for (idx, pixel) in fb.buf().chunks_exact(channels).enumerate() {
+            // Transfer functions apply to straight color samples.
+            let (a, scale) = alpha_and_color_scale(pixel, alpha, false);
+            for (plane, &v) in planes.iter_mut().zip(pixel) {
+                plane[idx] = v * scale;
+            }
+            alpha_plane[idx] = a;
+        }
+
+        let mut plane_refs = planes.iter_mut().map(|p| &mut **p).collect::<Vec<_>>();
+        transform.run(&mut plane_refs, &NullCms)?;
+
+        let mut out = Vec::with_capacity(num_pixels * 4);
+        for (idx, &a) in alpha_plane.iter().enumerate() {
+            let rgb = if color_channels >= 3 {
+                [planes[0][idx], planes[1][idx], planes[2][idx]]
+            } else {
+                [planes[0][idx]; 3]
+            };
+            out.extend(rgb.map(|v| v * a));
+            out.push(a);
+        }
+        Ok(out)
+    }
+
     /// Returns the color channels.
     ///
     /// Orientation is not applied.
@@ -1002,6 +1577,254 @@ impl Render {
     }
 }
 
+/// Returns alpha of the interleaved `pixel` clamped to `[0, 1]`, and the scale which converts its
+/// color samples to premultiplied ones if `premultiply` is `true`, or to straight ones otherwise.
+///
+/// `alpha` is the result of `Render::alpha_channel_info`.
+fn alpha_and_color_scale(
+    pixel: &[f32],
+    alpha: Option<(usize, bool)>,
+    premultiply: bool,
+) -> (f32, f32) {
+    let (a, associated) = match alpha {
+        Some((idx, associated)) => (pixel[idx].clamp(0.0, 1.0), associated),
+        None => (1.0, false),
+    };
+    let scale = match (associated, premultiply) {
+        (false, true) => a,
+        (true, false) if a > 0.0 => 1.0 / a,
+        (true, false) => 0.0,
+        _ => 1.0,
+    };
+    (a, scale)
+}
+
+/// Decodes the first keyframe of `data`, and returns a single channel of it.
+///
+/// Channels are indexed in the same order as [`Render::image_planar`], and orientation is
+/// applied. This is a convenience wrapper around [`JxlImage::render_frame`] and
+/// [`Render::image_channel`]; every channel of the frame is still decoded and rendered, so it
+/// doesn't save any decoding work. Returns an error if the channel doesn't exist.
+pub fn decode_channel(data: &[u8], channel_index: usize) -> Result<AlignedGrid<f32>> {
+    let image = JxlImage::builder().read(std::io::Cursor::new(data))?;
+    let render = image.render_frame(0)?;
+    render.image_channel(channel_index).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "channel index out of bounds",
+        )
+        .into()
+    })
+}
+
+/// Decodes the first keyframe of each of `files` concurrently.
+///
+/// Files are distributed over the default thread pool, which is single-threaded if `rayon`
+/// feature is disabled. Each file is decoded independently, so that an error in one file
+/// doesn't affect the others. Results are in the same order as `files`.
+pub fn decode_batch(files: &[&[u8]]) -> Vec<Result<Render>> {
+    let pool = default_pool();
+    let mut jobs = files
+        .iter()
+        .map(|&data| (data, None))
+        .collect::<Vec<(&[u8], Option<Result<Render>>)>>();
+    pool.for_each_mut_slice(&mut jobs, |(data, result)| {
+        let render = JxlImage::builder()
+            .pool(pool.clone())
+            .read(std::io::Cursor::new(*data))
+            .and_then(|image| image.render_frame(0));
+        *result = Some(render);
+    });
+    jobs.into_iter()
+        .map(|(_, result)| result.unwrap())
+        .collect()
+}
+
+/// Decodes the first keyframe of `data` at reduced resolution, and resizes it so that the longer
+/// side of the image is `max_dimension` pixels.
+///
+/// The decoder picks the largest downsampling factor, out of the ones available from progressive
+/// passes (and 8x from LF image if the frame is VarDCT encoded), that still gives at least
+/// `max_dimension` pixels on the longer side. Only the groups needed to reconstruct the image at
+/// that factor are fed to the decoder; the rest of `data` is never read. The result is then
+/// resized with bilinear interpolation, with orientation applied.
+///
+/// This is useful for generating thumbnails of large images.
+pub fn decode_at_scale(data: &[u8], max_dimension: u32) -> Result<ScaledImage> {
+    const HEADER_CHUNK_SIZE: usize = 256;
+
+    if max_dimension == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "max_dimension should be positive",
+        )
+        .into());
+    }
+
+    let mut rest = data;
+    let mut uninit = JxlImage::builder().build_uninit();
+    let mut image = loop {
+        let (chunk, next) = rest.split_at(rest.len().min(HEADER_CHUNK_SIZE));
+        rest = next;
+        uninit.feed_bytes(chunk)?;
+        match uninit.try_init()? {
+            InitializeResult::Initialized(image) => break image,
+            InitializeResult::NeedMoreData(_) if rest.is_empty() => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "image header is truncated",
+                )
+                .into());
+            }
+            InitializeResult::NeedMoreData(x) => uninit = x,
+        }
+    };
+
+    let long_side = image.width().max(image.height());
+    let mut shift = 0u32;
+    while shift < 3 && long_side >> (shift + 1) >= max_dimension {
+        shift += 1;
+    }
+
+    let mut downsampling = 1u32;
+    while image.num_loaded_keyframes() == 0 {
+        let needed = match image.ctx.current_loading_frame() {
+            Some(frame) if frame.header().is_keyframe() => {
+                let (needed, frame_downsampling) = bytes_needed_for_shift(frame, shift);
+                downsampling = frame_downsampling;
+                needed
+            }
+            // Frames other than the first keyframe, e.g. reference frames, are fully loaded.
+            Some(frame) => bytes_needed_for_shift(frame, 0).0,
+            None => HEADER_CHUNK_SIZE,
+        };
+        if needed == 0 || rest.is_empty() {
+            break;
+        }
+
+        let (chunk, next) = rest.split_at(rest.len().min(needed));
+        rest = next;
+        image.feed_bytes(chunk)?;
+    }
+
+    let render = if image.num_loaded_keyframes() > 0 {
+        downsampling = 1;
+        image.render_frame(0)?
+    } else {
+        image.render_loading_frame()?
+    };
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let max_dimension = max_dimension as usize;
+    let (target_width, target_height) = if width >= height {
+        let h = (height * max_dimension + width / 2) / width;
+        (max_dimension, h.max(1))
+    } else {
+        let w = (width * max_dimension + height / 2) / height;
+        (w.max(1), max_dimension)
+    };
+
+    let num_channels = render.color_channels().len() + render.extra_channels().0.len();
+    let mut out = FrameBuffer::new(target_width, target_height, num_channels);
+    for channel_idx in 0..num_channels {
+        let grid = render.image_channel(channel_idx).unwrap();
+        let resized = grid.resize_bilinear(target_width, target_height)?;
+        for (dst, &src) in out
+            .buf_mut()
+            .iter_mut()
+            .skip(channel_idx)
+            .step_by(num_channels)
+            .zip(resized.buf())
+        {
+            *dst = src;
+        }
+    }
+
+    Ok(ScaledImage {
+        image: out,
+        downsampling,
+        bytes_read: data.len() - rest.len(),
+    })
+}
+
+/// Returns the number of bytes the loading frame needs to reconstruct the image downsampled by
+/// `1 << shift`, along with the actual downsampling factor of the reconstruction.
+fn bytes_needed_for_shift(frame: &IndexedFrame, shift: u32) -> (usize, u32) {
+    let toc = frame.toc();
+    if toc.is_single_entry() {
+        let loaded = frame.data(TocGroupKind::All).map(|b| b.len()).unwrap_or(0);
+        return (toc.total_byte_size() - loaded, 1);
+    }
+
+    let header = frame.header();
+    let (last_pass, downsampling) = if header.encoding == frame::Encoding::VarDct && shift >= 3 {
+        (None, 8)
+    } else {
+        let (&last_pass, &(minshift, _)) = frame
+            .pass_shifts()
+            .iter()
+            .find(|(_, &(minshift, _))| minshift <= shift as i32)
+            .unwrap();
+        (Some(last_pass), 1 << minshift)
+    };
+
+    let is_needed = |kind: TocGroupKind| match kind {
+        TocGroupKind::All | TocGroupKind::LfGlobal | TocGroupKind::LfGroup(_) => true,
+        TocGroupKind::HfGlobal => last_pass.is_some(),
+        TocGroupKind::GroupPass { pass_idx, .. } => last_pass.is_some_and(|p| pass_idx <= p),
+    };
+
+    // Groups are loaded in bitstream order, so every group before the last needed one should be
+    // loaded too.
+    let groups: Vec<_> = toc.iter_bitstream_order().collect();
+    let Some(last_needed) = groups.iter().rposition(|group| is_needed(group.kind)) else {
+        return (0, downsampling);
+    };
+    let needed = groups[..=last_needed]
+        .iter()
+        .map(|group| {
+            let loaded = frame.data(group.kind).map(|b| b.len()).unwrap_or(0);
+            group.size as usize - loaded
+        })
+        .sum();
+    (needed, downsampling)
+}
+
+/// Image decoded at reduced resolution, returned by [`decode_at_scale`].
+#[derive(Debug, Clone)]
+pub struct ScaledImage {
+    image: FrameBuffer,
+    downsampling: u32,
+    bytes_read: usize,
+}
+
+impl ScaledImage {
+    /// Returns the resized image.
+    #[inline]
+    pub fn image(&self) -> &FrameBuffer {
+        &self.image
+    }
+
+    /// Consumes `self` and returns the resized image.
+    #[inline]
+    pub fn into_image(self) -> FrameBuffer {
+        self.image
+    }
+
+    /// Returns the downsampling factor of the image reconstructed from the partially read data,
+    /// before resizing. `1` means the frame is decoded at full resolution.
+    #[inline]
+    pub fn downsampling(&self) -> u32 {
+        self.downsampling
+    }
+
+    /// Returns the number of bytes of the input data read by the decoder.
+    #[inline]
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
 /// Extra channel of the image.
 #[derive(Debug)]
 pub struct ExtraChannel {
@@ -1018,6 +1841,8 @@ impl ExtraChannel {
     }
 
     /// Returns the name of the channel.
+    ///
+    /// The name is an empty string if the channel is unnamed.
     #[inline]
     pub fn name(&self) -> &str {
         &self.name
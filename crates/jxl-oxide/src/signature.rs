@@ -0,0 +1,61 @@
+use jxl_bitstream::{Bitstream, Bundle};
+use jxl_image::ImageHeader;
+
+const CODESTREAM_SIG: [u8; 2] = [0xff, 0x0a];
+pub(crate) const CONTAINER_SIG: [u8; 12] =
+    [0, 0, 0, 0xc, b'J', b'X', b'L', b' ', 0xd, 0xa, 0x87, 0xa];
+
+/// Maximum number of leading garbage bytes skipped while scanning for the signature.
+pub(crate) const MAX_LEADING_GARBAGE: usize = 1 << 20;
+
+/// Result of scanning for JPEG XL signature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SignatureScan {
+    /// Signature is found at the offset.
+    Found(usize),
+    /// Signature candidate is found at the offset, but more data is needed to validate it.
+    NeedMoreData(usize),
+    /// No signature is found.
+    NotFound,
+}
+
+/// Scans `data` for the first valid JPEG XL signature.
+///
+/// Container signature is long enough to be accepted as is. Codestream signature is only two
+/// bytes, so it's accepted only if a valid image header follows.
+pub(crate) fn scan_signature(data: &[u8]) -> SignatureScan {
+    for (offset, &byte) in data.iter().enumerate() {
+        if byte != CODESTREAM_SIG[0] && byte != CONTAINER_SIG[0] {
+            continue;
+        }
+
+        let rest = &data[offset..];
+        if rest.starts_with(&CONTAINER_SIG) {
+            return SignatureScan::Found(offset);
+        }
+        if CONTAINER_SIG.starts_with(rest) || CODESTREAM_SIG.starts_with(rest) {
+            return SignatureScan::NeedMoreData(offset);
+        }
+        if rest.starts_with(&CODESTREAM_SIG) {
+            let mut bitstream = Bitstream::new(rest);
+            match ImageHeader::parse(&mut bitstream, ()) {
+                Ok(_) => return SignatureScan::Found(offset),
+                Err(e) if e.unexpected_eof() => return SignatureScan::NeedMoreData(offset),
+                Err(_) => {}
+            }
+        }
+    }
+    SignatureScan::NotFound
+}
+
+/// Finds the offset where JPEG XL image starts, skipping leading garbage in `data`.
+///
+/// Container signature is searched as is. Bare codestream signature is only two bytes long, so it
+/// is accepted only if a valid image header follows the signature. Returns `None` if no
+/// signature is found, or the image header after the signature is truncated.
+pub fn find_jxl_start(data: &[u8]) -> Option<usize> {
+    match scan_signature(data) {
+        SignatureScan::Found(offset) => Some(offset),
+        SignatureScan::NeedMoreData(_) | SignatureScan::NotFound => None,
+    }
+}
@@ -0,0 +1,211 @@
+use jxl_grid::SharedSubgrid;
+
+use crate::FrameBuffer;
+
+/// Matrix coefficients used to convert RGB to YCbCr.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum YcbcrMatrix {
+    /// ITU-R BT.601, used by SD video and JPEG.
+    Bt601,
+    /// ITU-R BT.709, used by HD video.
+    Bt709,
+}
+
+impl YcbcrMatrix {
+    /// Returns `(Kr, Kb)` of the matrix.
+    fn coefficients(self) -> (f32, f32) {
+        match self {
+            Self::Bt601 => (0.299, 0.114),
+            Self::Bt709 => (0.2126, 0.0722),
+        }
+    }
+}
+
+/// Chroma subsampling of YCbCr image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// No subsampling.
+    Yuv444,
+    /// Chroma planes are subsampled by two in both directions, with chroma samples sited at the
+    /// center of each 2x2 block.
+    Yuv420,
+}
+
+/// Planar, full-range YCbCr image.
+///
+/// Luma samples are in the range of `[0, 1]`, and chroma samples are in the range of
+/// `[-0.5, 0.5]` if the source RGB samples are in the range of `[0, 1]`.
+#[derive(Debug, Clone)]
+pub struct YcbcrImage {
+    width: usize,
+    height: usize,
+    matrix: YcbcrMatrix,
+    subsampling: ChromaSubsampling,
+    y: Vec<f32>,
+    cb: Vec<f32>,
+    cr: Vec<f32>,
+}
+
+impl YcbcrImage {
+    /// Returns the width of the luma plane.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the luma plane.
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the dimension of the chroma planes.
+    #[inline]
+    pub fn chroma_size(&self) -> (usize, usize) {
+        chroma_size(self.width, self.height, self.subsampling)
+    }
+
+    /// Returns the matrix coefficients used for the conversion.
+    #[inline]
+    pub fn matrix(&self) -> YcbcrMatrix {
+        self.matrix
+    }
+
+    /// Returns the chroma subsampling of the image.
+    #[inline]
+    pub fn subsampling(&self) -> ChromaSubsampling {
+        self.subsampling
+    }
+
+    /// Returns the luma plane, in row-major order.
+    #[inline]
+    pub fn y(&self) -> &[f32] {
+        &self.y
+    }
+
+    /// Returns the blue-difference chroma plane, in row-major order.
+    #[inline]
+    pub fn cb(&self) -> &[f32] {
+        &self.cb
+    }
+
+    /// Returns the red-difference chroma plane, in row-major order.
+    #[inline]
+    pub fn cr(&self) -> &[f32] {
+        &self.cr
+    }
+
+    /// Converts the image back to three-channel RGB frame buffer.
+    ///
+    /// Subsampled chroma planes are upsampled with bilinear interpolation.
+    pub fn to_rgb(&self) -> FrameBuffer {
+        let (kr, kb) = self.matrix.coefficients();
+        let kg = 1.0 - kr - kb;
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let chroma_grids = (self.subsampling == ChromaSubsampling::Yuv420
+            && chroma_width > 0
+            && chroma_height > 0)
+            .then(|| {
+                [&self.cb, &self.cr].map(|plane| {
+                    SharedSubgrid::from_buf(plane, chroma_width, chroma_height, chroma_width)
+                })
+            });
+
+        let mut fb = FrameBuffer::new(self.width, self.height, 3);
+        let out = fb.buf_grouped_mut::<3>();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                let (cb, cr) = match &chroma_grids {
+                    None => (self.cb[idx], self.cr[idx]),
+                    Some([cb, cr]) => {
+                        let cx = (x as f32 + 0.5) / 2.0 - 0.5;
+                        let cy = (y as f32 + 0.5) / 2.0 - 0.5;
+                        (cb.sample_bilinear(cx, cy), cr.sample_bilinear(cx, cy))
+                    }
+                };
+
+                let luma = self.y[idx];
+                let r = luma + 2.0 * (1.0 - kr) * cr;
+                let b = luma + 2.0 * (1.0 - kb) * cb;
+                let g = (luma - kr * r - kb * b) / kg;
+                out[idx] = [r, g, b];
+            }
+        }
+        fb
+    }
+}
+
+impl FrameBuffer {
+    /// Converts the first three channels of the frame buffer, interpreted as RGB, to YCbCr.
+    ///
+    /// For 4:2:0 subsampling, each chroma sample is the average of the corresponding 2x2 block,
+    /// with the edge samples repeated for odd dimensions. Note that the conversion happens on
+    /// the samples as is, so the frame buffer should be rendered to a gamma-encoded color space
+    /// such as sRGB or BT.709 for the usual video pipeline.
+    ///
+    /// # Panics
+    /// Panics if the frame buffer has less than three channels.
+    pub fn to_ycbcr(&self, matrix: YcbcrMatrix, subsampling: ChromaSubsampling) -> YcbcrImage {
+        let channels = self.channels();
+        assert!(channels >= 3, "YCbCr conversion requires RGB frame buffer");
+
+        let width = self.width();
+        let height = self.height();
+        let (kr, kb) = matrix.coefficients();
+        let kg = 1.0 - kr - kb;
+
+        let len = width * height;
+        let mut y = Vec::with_capacity(len);
+        let mut cb = Vec::with_capacity(len);
+        let mut cr = Vec::with_capacity(len);
+        for px in self.buf().chunks_exact(channels) {
+            let (r, g, b) = (px[0], px[1], px[2]);
+            let luma = kr * r + kg * g + kb * b;
+            y.push(luma);
+            cb.push((b - luma) / (2.0 * (1.0 - kb)));
+            cr.push((r - luma) / (2.0 * (1.0 - kr)));
+        }
+
+        if subsampling == ChromaSubsampling::Yuv420 {
+            cb = downsample_420(&cb, width, height);
+            cr = downsample_420(&cr, width, height);
+        }
+
+        YcbcrImage {
+            width,
+            height,
+            matrix,
+            subsampling,
+            y,
+            cb,
+            cr,
+        }
+    }
+}
+
+fn chroma_size(width: usize, height: usize, subsampling: ChromaSubsampling) -> (usize, usize) {
+    match subsampling {
+        ChromaSubsampling::Yuv444 => (width, height),
+        ChromaSubsampling::Yuv420 => (width.div_ceil(2), height.div_ceil(2)),
+    }
+}
+
+fn downsample_420(plane: &[f32], width: usize, height: usize) -> Vec<f32> {
+    let (out_width, out_height) = chroma_size(width, height, ChromaSubsampling::Yuv420);
+    let mut out = Vec::with_capacity(out_width * out_height);
+    for cy in 0..out_height {
+        let y0 = cy * 2;
+        let y1 = (y0 + 1).min(height - 1);
+        for cx in 0..out_width {
+            let x0 = cx * 2;
+            let x1 = (x0 + 1).min(width - 1);
+            let sum = plane[y0 * width + x0]
+                + plane[y0 * width + x1]
+                + plane[y1 * width + x0]
+                + plane[y1 * width + x1];
+            out.push(sum / 4.0);
+        }
+    }
+    out
+}
@@ -2,35 +2,6 @@ use jxl_oxide::JxlImage;
 
 mod util;
 
-fn read_numpy(mut r: impl std::io::Read, frames: usize, channels: usize) -> Vec<Vec<f32>> {
-    let mut magic = [0u8; 6];
-    let mut version = [0u8; 2];
-    let mut meta_len = [0u8; 2];
-
-    r.read_exact(&mut magic).unwrap();
-    r.read_exact(&mut version).unwrap();
-    r.read_exact(&mut meta_len).unwrap();
-    assert_eq!(&magic, b"\x93NUMPY");
-
-    let meta_len = u16::from_le_bytes(meta_len) as usize;
-    let mut meta = vec![0u8; meta_len];
-    r.read_exact(&mut meta).unwrap();
-
-    let mut fb = Vec::new();
-    let mut buf = vec![0u8; channels * 4];
-    while r.read_exact(&mut buf).is_ok() {
-        let mut val = [0u8; 4];
-        for c in buf.chunks_exact(4) {
-            val.copy_from_slice(c);
-            let x = u32::from_le_bytes(val);
-            fb.push(f32::from_bits(x));
-        }
-    }
-
-    let chunk_size = fb.len() / frames;
-    fb.chunks_exact(chunk_size).map(|b| b.to_vec()).collect()
-}
-
 fn download_object_with_cache(hash: &str, ext: &str) -> Vec<u8> {
     let url = format!(
         "https://storage.googleapis.com/storage/v1/b/jxl-conformance/o/objects%2F{hash}?alt=media"
@@ -139,7 +110,7 @@ macro_rules! conformance_test {
             let buf = download_object_with_cache($npy_hash, "npy");
             let target_icc = perform_ct.then(|| download_object_with_cache($icc_hash, "icc"));
 
-            let expected = read_numpy(std::io::Cursor::new(buf), $frames, $channels);
+            let expected = util::read_numpy(std::io::Cursor::new(buf), $frames, $channels);
 
             let path = util::conformance_path(stringify!($name));
             let image = JxlImage::builder().open(path).expect("Failed to open file");
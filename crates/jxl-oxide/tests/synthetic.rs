@@ -0,0 +1,2574 @@
+//! Tests using small synthetic images checked in to `tests/synthetic`.
+
+use jxl_oxide::{
+    color::{ColourSpace, Primaries, TransferFunction, WhitePoint},
+    decode_at_scale, decode_batch, find_jxl_start,
+    frame::{Encoding, FrameDuration},
+    ChromaSubsampling, CropInfo, Endianness, EnumColourEncoding, ExtraChannelType, FrameBuffer,
+    InitializeResult, JxlImage, JxlThreadPool, RenderFeatures, RenderingIntent, TocGroupKind,
+    YcbcrMatrix,
+};
+
+mod util;
+
+fn open(data: &[u8]) -> JxlImage {
+    JxlImage::builder()
+        .read(std::io::Cursor::new(data))
+        .expect("failed to open image")
+}
+
+fn srgb_linear() -> EnumColourEncoding {
+    EnumColourEncoding {
+        colour_space: ColourSpace::Rgb,
+        white_point: WhitePoint::D65,
+        primaries: Primaries::Srgb,
+        tf: TransferFunction::Linear,
+        rendering_intent: RenderingIntent::Relative,
+    }
+}
+
+/// Modular samples used in `modular_rgb.jxl` and `modular_xyb.jxl`.
+const MODULAR_SAMPLES: [i32; 3] = [100, 20, 30];
+
+#[test]
+fn modular_rgb_skips_xyb_inverse() {
+    let image = open(include_bytes!("synthetic/modular_rgb.jxl"));
+    assert!(!image.image_header().metadata.xyb_encoded);
+
+    let render = image.render_frame(0).unwrap();
+    let fb = render.image_all_channels();
+    assert_eq!(fb.channels(), 3);
+    for pixel in fb.buf().chunks_exact(3) {
+        for (&v, &sample) in pixel.iter().zip(&MODULAR_SAMPLES) {
+            assert_eq!((v * 255.0).round() as i32, sample);
+        }
+    }
+}
+
+#[test]
+fn modular_xyb_applies_xyb_inverse() {
+    let mut image = open(include_bytes!("synthetic/modular_xyb.jxl"));
+    assert!(image.image_header().metadata.xyb_encoded);
+    image.request_color_encoding(srgb_linear());
+
+    // Modular XYB samples are stored in Y, X, B-Y order, with default LF dequantization weights.
+    let [qy, qx, qb] = MODULAR_SAMPLES.map(|v| v as f32);
+    let x = qx / 4096.0;
+    let y = qy / 512.0;
+    let b = (qb + qy) / 256.0;
+
+    // Default opsin inverse matrix and bias.
+    let inv_mat = [
+        [11.031567f32, -9.866944, -0.164623],
+        [-3.254147, 4.41877, -0.164623],
+        [-3.658851, 2.712923, 1.945928],
+    ];
+    let bias = -0.003_793_073f32;
+    let lms = [y + x, y - x, b].map(|v| {
+        let v = v - bias.cbrt();
+        v * v * v + bias
+    });
+    let expected = inv_mat.map(|row| row.iter().zip(lms).map(|(m, v)| m * v).sum::<f32>());
+
+    let render = image.render_frame(0).unwrap();
+    let fb = render.image_all_channels();
+    assert_eq!(fb.channels(), 3);
+    for pixel in fb.buf().chunks_exact(3) {
+        for (&v, &expected) in pixel.iter().zip(&expected) {
+            assert!(
+                (v - expected).abs() < 1e-3,
+                "expected {expected:?}, got {pixel:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn orientation_dimensions() {
+    // 16x8 encoded image with orientation 6; samples are 240 where x < 8, and 100 elsewhere.
+    let image = open(include_bytes!("synthetic/modular_orientation6_16x8.jxl"));
+    let image_header = image.image_header();
+    assert_eq!(image_header.metadata.orientation, 6);
+    assert_eq!(image_header.dimensions(false), (16, 8));
+    assert_eq!(image_header.dimensions(true), (8, 16));
+    assert_eq!((image.width(), image.height()), (8, 16));
+
+    // Rendered image has the dimensions with orientation applied.
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!((fb.width(), fb.height()), (8, 16));
+    for (y, row) in fb.buf().chunks_exact(8 * 3).enumerate() {
+        let expected = if y < 8 { 240.0 / 255.0 } else { 100.0 / 255.0 };
+        assert!(row.iter().all(|&v| v == expected), "row {y}: {row:?}");
+    }
+}
+
+#[test]
+fn modular_ycbcr_420() {
+    // 16x16 Modular YCbCr image with 4:2:0 chroma subsampling. Samples are:
+    // - Y = 8 * (x + y + 1),
+    // - Cb = 4 * (x + y + 1) at half resolution,
+    // - Cr = -10.
+    let image = open(include_bytes!("synthetic/modular_ycbcr_420.jxl"));
+    let frame_header = image.frame_header(0).unwrap();
+    assert!(frame_header.do_ycbcr);
+    assert_eq!(frame_header.jpeg_upsampling, [0, 1, 0]);
+
+    let (width, height) = (16usize, 16usize);
+    let cb = |x: isize, y: isize| {
+        let x = x.clamp(0, width as isize / 2 - 1);
+        let y = y.clamp(0, height as isize / 2 - 1);
+        4.0 * (x + y + 1) as f32
+    };
+    // Chroma samples are upsampled with weights of 3/4 and 1/4.
+    let upsample = |x: usize, y: usize| {
+        let (cx, cy) = ((x / 2) as isize, (y / 2) as isize);
+        let nx = if x.is_multiple_of(2) { cx - 1 } else { cx + 1 };
+        let ny = if y.is_multiple_of(2) { cy - 1 } else { cy + 1 };
+        let row = |y| 0.75 * cb(cx, y) + 0.25 * cb(nx, y);
+        0.75 * row(cy) + 0.25 * row(ny)
+    };
+
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!((fb.width(), fb.height(), fb.channels()), (width, height, 3));
+    for (idx, pixel) in fb.buf_grouped::<3>().iter().enumerate() {
+        let (x, y) = (idx % width, idx / width);
+        let luma = (8 * (x + y + 1) + 128) as f32;
+        let cb = upsample(x, y);
+        let cr = -10.0;
+        let expected = [
+            luma + 1.402 * cr,
+            luma - 0.344136 * cb - 0.714136 * cr,
+            luma + 1.772 * cb,
+        ]
+        .map(|v| v / 255.0);
+        for (&v, &expected) in pixel.iter().zip(&expected) {
+            assert!(
+                (v - expected).abs() < 1e-4,
+                "at ({x}, {y}): expected {expected:?}, got {pixel:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn chroma_siting_420() {
+    // Subsampled chroma samples are centered between two luma samples, not cosited with the first
+    // one. Cb is linear in the chroma grid, so the upsampled Cb at full resolution coordinate `p`
+    // is sampled at `(p - 0.5) / 2`; cosited samples would be at `p / 2` instead, shifted by half
+    // a pixel.
+    let image = open(include_bytes!("synthetic/modular_ycbcr_420.jxl"));
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    let width = fb.width();
+    let cb_at = |x: usize, y: usize| {
+        let [r, _, b] = fb.buf_grouped::<3>()[y * width + x];
+        // Cr is -10, and R = luma + 1.402 * Cr.
+        let luma = r * 255.0 + 14.02;
+        (b * 255.0 - luma) / 1.772
+    };
+
+    for y in 1..width - 1 {
+        for x in 1..width - 1 {
+            let centered = 4.0 * ((x as f32 - 0.5) / 2.0 + (y as f32 - 0.5) / 2.0 + 1.0);
+            let cb = cb_at(x, y);
+            assert!(
+                (cb - centered).abs() < 0.05,
+                "at ({x}, {y}): {cb} != {centered}"
+            );
+        }
+    }
+
+    // Edges replicate the outermost chroma samples instead of extrapolating.
+    assert!((cb_at(0, 0) - 4.0).abs() < 0.05);
+    let last = width - 1;
+    assert!((cb_at(last, last) - 4.0 * 15.0).abs() < 0.05);
+}
+
+#[test]
+fn decode_single_channel() {
+    let data = include_bytes!("synthetic/modular_rgb.jxl");
+    let planar = open(data).render_frame(0).unwrap().image_planar();
+    assert_eq!(planar.len(), 3);
+
+    // Each call decodes the image from scratch, so the order doesn't matter.
+    for channel_index in [1, 0, 2] {
+        let channel = jxl_oxide::decode_channel(data, channel_index).unwrap();
+        let expected = &planar[channel_index];
+        assert_eq!(
+            (channel.width(), channel.height()),
+            (expected.width(), expected.height())
+        );
+        assert_eq!(channel.buf(), expected.buf());
+    }
+    assert!(jxl_oxide::decode_channel(data, 3).is_err());
+}
+
+#[test]
+fn toc_describe_multi_group() {
+    let image = open(include_bytes!("synthetic/blocks_300x260.jxl"));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    let toc = frame.toc();
+    assert!(!toc.is_single_entry());
+
+    // LfGlobal, one LfGroup, HfGlobal and 2x2 pass groups.
+    let description = toc.describe();
+    let mut lines = description.lines();
+    assert!(lines.next().unwrap().starts_with("TOC: 7 entries"));
+    let groups = lines.collect::<Vec<_>>();
+    assert_eq!(groups.len(), 7);
+    assert!(groups[0].contains("LfGlobal"));
+    assert!(groups[6].contains("GroupPass(pass 0, group 3)"));
+}
+
+#[test]
+fn partial_frame_region_decoded() {
+    let data = include_bytes!("synthetic/blocks_300x260.jxl");
+    let image = open(data);
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(frame.is_region_decoded((0, 0, 300, 260)));
+    let last_group = frame.toc().iter_bitstream_order().last().unwrap();
+
+    // Cut the image in the middle of the last pass group, which covers (256, 256)-(300, 260).
+    let len = data.len() - last_group.size as usize / 2;
+    let image = JxlImage::builder()
+        .read(std::io::Cursor::new(&data[..len]))
+        .unwrap();
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(frame.is_region_decoded((0, 0, 256, 256)));
+    assert!(frame.is_region_decoded((260, 0, 40, 200)));
+    assert!(frame.is_region_decoded((200, 200, 100, 50)));
+    assert!(!frame.is_region_decoded((200, 200, 100, 60)));
+    assert!(!frame.is_region_decoded((0, 0, 300, 260)));
+}
+
+#[test]
+fn partial_frame_missing_groups() {
+    let data = include_bytes!("synthetic/blocks_300x260.jxl");
+    let image = open(data);
+    let expected = image.render_frame(0).unwrap().image_all_channels();
+    let last_group = image
+        .frame_by_keyframe(0)
+        .unwrap()
+        .toc()
+        .iter_bitstream_order()
+        .last();
+    let last_group = last_group.unwrap();
+
+    // Cut the image in the middle of the last pass group.
+    let len = data.len() - last_group.size as usize / 2;
+    let mut image = JxlImage::builder()
+        .read(std::io::Cursor::new(&data[..len]))
+        .unwrap();
+    assert!(!image.is_loading_done());
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(!frame.is_loading_done());
+    assert_eq!(frame.missing_groups(), [last_group.kind]);
+    assert_eq!(
+        last_group.kind,
+        TocGroupKind::GroupPass {
+            pass_idx: 0,
+            group_idx: 3
+        }
+    );
+
+    // Groups other than the last one are rendered correctly.
+    let actual = image.render_loading_frame().unwrap().image_all_channels();
+    let width = expected.width();
+    let channels = expected.channels();
+    let split = 256;
+    for (y, (actual_row, expected_row)) in actual
+        .buf()
+        .chunks_exact(width * channels)
+        .zip(expected.buf().chunks_exact(width * channels))
+        .enumerate()
+    {
+        if y < split {
+            assert_eq!(actual_row, expected_row);
+        } else {
+            assert_eq!(
+                actual_row[..split * channels],
+                expected_row[..split * channels]
+            );
+        }
+    }
+}
+
+#[test]
+fn single_entry_vardct() {
+    let data = include_bytes!("fuzz_findings/squeeze_tendency_overflow.fuzz");
+    let image = open(data);
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(frame.toc().is_single_entry());
+    assert_eq!(frame.header().encoding, Encoding::VarDct);
+    let expected = image.render_frame(0).unwrap().image_all_channels();
+
+    // Feed the image byte by byte, so that each section is parsed after it becomes available.
+    let mut bytes = data.chunks(1);
+    let mut uninit = JxlImage::builder().build_uninit();
+    let mut image = loop {
+        uninit.feed_bytes(bytes.next().unwrap()).unwrap();
+        match uninit.try_init().unwrap() {
+            InitializeResult::NeedMoreData(x) => uninit = x,
+            InitializeResult::Initialized(x) => break x,
+        }
+    };
+    for byte in bytes {
+        image.feed_bytes(byte).unwrap();
+    }
+    assert!(image.is_loading_done());
+
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.width(), 16);
+    assert_eq!(actual.height(), 7);
+    assert_eq!(actual.buf(), expected.buf());
+}
+
+#[test]
+fn dither_u8() {
+    // Slow horizontal gradient spanning a few 8-bit levels, with an opaque alpha channel.
+    let (width, height) = (256, 16);
+    let mut fb = FrameBuffer::new(width, height, 2);
+    for (idx, pixel) in fb.buf_grouped_mut::<2>().iter_mut().enumerate() {
+        let x = idx % width;
+        *pixel = [(100.0 + x as f32 / 32.0) / 255.0, 1.0];
+    }
+
+    fn mean(samples: impl Iterator<Item = f32>) -> f32 {
+        let samples = samples.collect::<Vec<_>>();
+        samples.iter().sum::<f32>() / samples.len() as f32
+    }
+
+    fn flat_runs(row: &[u8]) -> usize {
+        row.windows(2).filter(|w| w[0] == w[1]).count()
+    }
+
+    let expected_mean = mean(fb.buf().iter().step_by(2).map(|&v| v * 255.0));
+    let plain = fb.to_u8(1, false);
+    let dithered = fb.to_u8(1, true);
+
+    // Alpha is not dithered.
+    assert!(dithered.iter().skip(1).step_by(2).all(|&a| a == 255));
+
+    let plain_color = plain.iter().step_by(2).copied().collect::<Vec<_>>();
+    let dithered_color = dithered.iter().step_by(2).copied().collect::<Vec<_>>();
+    let plain_runs: usize = plain_color.chunks_exact(width).map(flat_runs).sum();
+    let dithered_runs: usize = dithered_color.chunks_exact(width).map(flat_runs).sum();
+    assert!(
+        dithered_runs < plain_runs,
+        "plain: {plain_runs} flat runs, dithered: {dithered_runs} flat runs"
+    );
+
+    let dithered_mean = mean(dithered_color.into_iter().map(|v| v as f32));
+    assert!((dithered_mean - expected_mean).abs() < 0.1);
+}
+
+#[test]
+fn ycbcr_roundtrip() {
+    // 2x2 blocks of solid colors, so that 4:2:0 chroma is exact at block centers.
+    let colors = [
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [0.8, 0.6, 0.2],
+    ];
+    let (width, height) = (8, 6);
+    let mut fb = FrameBuffer::new(width, height, 3);
+    for (idx, pixel) in fb.buf_grouped_mut::<3>().iter_mut().enumerate() {
+        let (x, y) = (idx % width, idx / width);
+        *pixel = colors[(x / 2 + y / 2) % colors.len()];
+    }
+
+    for matrix in [YcbcrMatrix::Bt601, YcbcrMatrix::Bt709] {
+        let ycbcr = fb.to_ycbcr(matrix, ChromaSubsampling::Yuv444);
+        // Pure red has Cr of 0.5 regardless of the matrix.
+        assert!((ycbcr.cr()[0] - 0.5).abs() < 1e-6);
+        let rgb = ycbcr.to_rgb();
+        for (&a, &b) in rgb.buf().iter().zip(fb.buf()) {
+            assert!((a - b).abs() < 1e-5, "{matrix:?}: {a} != {b}");
+        }
+
+        let ycbcr = fb.to_ycbcr(matrix, ChromaSubsampling::Yuv420);
+        assert_eq!(ycbcr.chroma_size(), (4, 3));
+        let expected = fb.to_ycbcr(matrix, ChromaSubsampling::Yuv444);
+        for cy in 0..3 {
+            for cx in 0..4 {
+                let idx = cy * 2 * width + cx * 2;
+                assert!((ycbcr.cb()[cy * 4 + cx] - expected.cb()[idx]).abs() < 1e-6);
+                assert!((ycbcr.cr()[cy * 4 + cx] - expected.cr()[idx]).abs() < 1e-6);
+            }
+        }
+
+        // Upsampled chroma bleeds across block edges, but luma is kept intact.
+        let rgb = ycbcr.to_rgb();
+        let roundtrip = rgb.to_ycbcr(matrix, ChromaSubsampling::Yuv444);
+        for (&a, &b) in roundtrip.y().iter().zip(expected.y()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+        // Each upsampled chroma sample takes at least 9/16 of its own block, so chroma error is at
+        // most 7/16 of the largest chroma difference between blocks, which is at most 1. Chroma
+        // error is amplified by up to 2 * (1 - Kb) in RGB.
+        let kb = match matrix {
+            YcbcrMatrix::Bt601 => 0.114,
+            YcbcrMatrix::Bt709 => 0.0722,
+        };
+        let bound = 7.0 / 16.0 * 2.0 * (1.0 - kb) + 1e-5;
+        let max_error = rgb
+            .buf()
+            .iter()
+            .zip(fb.buf())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        assert!(max_error <= bound, "{matrix:?}: max error {max_error}");
+    }
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn weighted_predictor_serial_parallel() {
+    // Every channel in each of 2x2 groups is coded with the self-correcting predictor.
+    let data = include_bytes!("synthetic/weighted_predictor_300x260.jxl");
+    let serial = JxlImage::builder()
+        .pool(JxlThreadPool::none())
+        .read(std::io::Cursor::new(data))
+        .unwrap();
+    let parallel = JxlImage::builder()
+        .pool(JxlThreadPool::rayon(Some(4)))
+        .read(std::io::Cursor::new(data))
+        .unwrap();
+    assert!(parallel.pool().is_multithreaded());
+
+    let serial = serial.render_frame(0).unwrap().image_all_channels();
+    let parallel = parallel.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(serial.buf(), parallel.buf());
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn vardct_single_worker() {
+    // Pass groups are decoded by the workers of the thread pool, so the number of groups decoded
+    // at once, and the memory used by them, is bounded by the number of threads.
+    let data = include_bytes!("synthetic/vardct_500x300.jxl");
+    let render = |pool: JxlThreadPool| {
+        let image = JxlImage::builder()
+            .pool(pool)
+            .read(std::io::Cursor::new(data))
+            .unwrap();
+        assert!(image.frame_by_keyframe(0).unwrap().num_pass_groups() > 1);
+        image.render_frame(0).unwrap().image_all_channels()
+    };
+
+    let expected = render(JxlThreadPool::rayon(Some(4)));
+    let single = render(JxlThreadPool::rayon(Some(1)));
+    assert_eq!(single.buf(), expected.buf());
+    let serial = render(JxlThreadPool::none());
+    assert_eq!(serial.buf(), expected.buf());
+}
+
+#[test]
+fn toc_raster_order() {
+    let pass_group = |group_idx| TocGroupKind::GroupPass {
+        pass_idx: 0,
+        group_idx,
+    };
+
+    // 3x3 pass groups of 128x128, in a single LF group.
+    let image = open(include_bytes!(
+        "synthetic/modular_group_dim_128_300x260.jxl"
+    ));
+    let toc = image.frame_by_keyframe(0).unwrap().toc();
+    let kinds = toc
+        .iter_raster_order()
+        .map(|group| group.kind)
+        .collect::<Vec<_>>();
+    let mut expected = vec![
+        TocGroupKind::LfGlobal,
+        TocGroupKind::HfGlobal,
+        TocGroupKind::LfGroup(0),
+    ];
+    expected.extend((0..9).map(pass_group));
+    assert_eq!(kinds, expected);
+
+    // Groups are the same as in bitstream order.
+    let mut bitstream_order = toc.iter_bitstream_order().collect::<Vec<_>>();
+    let mut raster_order = toc.iter_raster_order().collect::<Vec<_>>();
+    bitstream_order.sort_by_key(|group| group.offset);
+    raster_order.sort_by_key(|group| group.offset);
+    for (a, b) in bitstream_order.iter().zip(&raster_order) {
+        assert_eq!((a.kind, a.offset, a.size), (b.kind, b.offset, b.size));
+    }
+
+    // 2x2 LF groups of 2048x2048, each row of which has 8 pass groups of 256x256 in the first LF
+    // group and 7 in the second one.
+    let image = open(include_bytes!("synthetic/vardct_3840x2160.jxl"));
+    let toc = image.frame_by_keyframe(0).unwrap().toc();
+    let kinds = toc
+        .iter_raster_order()
+        .map(|group| group.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds.len(), 1 + 4 + 1 + 15 * 9);
+    let mut expected = vec![
+        TocGroupKind::LfGlobal,
+        TocGroupKind::HfGlobal,
+        TocGroupKind::LfGroup(0),
+    ];
+    expected.extend((0..8).map(pass_group));
+    expected.push(TocGroupKind::LfGroup(1));
+    expected.extend((8..15).map(pass_group));
+    expected.extend((15..30).map(pass_group));
+    assert_eq!(kinds[..expected.len()], expected);
+    let lf_group_2 = kinds
+        .iter()
+        .position(|&kind| kind == TocGroupKind::LfGroup(2))
+        .unwrap();
+    assert_eq!(kinds[lf_group_2 - 1], pass_group(15 * 8 - 1));
+    assert_eq!(kinds[lf_group_2 + 1], pass_group(15 * 8));
+}
+
+#[test]
+fn global_ma_tree_groups_independent() {
+    let data = include_bytes!("synthetic/blocks_300x260.jxl");
+    let image = open(data);
+    let frame = image.frame_by_keyframe(0).unwrap();
+    let lf_global = frame.try_parse_lf_global::<i32>().unwrap().unwrap();
+    assert!(lf_global.gmodular.ma_config().is_some());
+    let full = image.render_frame(0).unwrap().image_all_channels();
+
+    // Decode each pass group alone, in reverse order, so that no other group is decoded before it.
+    let mut image = open(data);
+    let group_dim = image.frame_header(0).unwrap().group_dim();
+    for (gx, gy) in [(1, 1), (0, 1), (1, 0), (0, 0)] {
+        let left = gx * group_dim;
+        let top = gy * group_dim;
+        let crop = CropInfo {
+            width: group_dim.min(image.width() - left),
+            height: group_dim.min(image.height() - top),
+            left,
+            top,
+        };
+        image.set_image_region(crop);
+        image.reset_render_cache();
+        let cropped = image.render_frame_cropped(0).unwrap().image_all_channels();
+
+        for (y, row) in cropped
+            .buf_grouped::<3>()
+            .chunks_exact(crop.width as usize)
+            .enumerate()
+        {
+            let full_y = top as usize + y;
+            let full_row = &full.buf_grouped::<3>()[full_y * full.width()..][..full.width()];
+            assert_eq!(row, &full_row[left as usize..][..crop.width as usize]);
+        }
+    }
+}
+
+#[test]
+fn local_ma_trees() {
+    // Same image as `blocks_300x260.jxl`, but without global MA tree; each group has its own tree.
+    let image = open(include_bytes!("synthetic/blocks_local_tree_300x260.jxl"));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(frame.toc().iter_bitstream_order().count() > 1);
+    let lf_global = frame.try_parse_lf_global::<i32>().unwrap().unwrap();
+    assert!(lf_global.gmodular.ma_config().is_none());
+
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    let expected = open(include_bytes!("synthetic/blocks_300x260.jxl"))
+        .render_frame(0)
+        .unwrap()
+        .image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+}
+
+#[test]
+fn crop_trailing_groups() {
+    let data = include_bytes!("synthetic/blocks_300x260.jxl");
+    let image = open(data);
+    let frame_header = image.frame_header(0).unwrap();
+    assert_eq!(frame_header.groups_per_row(), 2);
+    assert_ne!(image.width() % frame_header.group_dim(), 0);
+    let full = image.render_frame(0).unwrap().image_all_channels();
+
+    let regions = [
+        // Right edge, only in trailing groups.
+        CropInfo {
+            width: 40,
+            height: 260,
+            left: 260,
+            top: 0,
+        },
+        // Bottom right corner.
+        CropInfo {
+            width: 10,
+            height: 3,
+            left: 290,
+            top: 257,
+        },
+        // Straddling group boundary, touching right edge.
+        CropInfo {
+            width: 100,
+            height: 30,
+            left: 200,
+            top: 230,
+        },
+    ];
+
+    let mut image = open(data);
+    for crop in regions {
+        image.set_image_region(crop);
+        let cropped = image.render_frame_cropped(0).unwrap().image_all_channels();
+        assert_eq!(cropped.width(), crop.width as usize);
+        assert_eq!(cropped.height(), crop.height as usize);
+
+        for (y, row) in cropped
+            .buf_grouped::<3>()
+            .chunks_exact(cropped.width())
+            .enumerate()
+        {
+            for (x, actual) in row.iter().enumerate() {
+                let full_x = crop.left as usize + x;
+                let full_y = crop.top as usize + y;
+                let expected = full.buf_grouped::<3>()[full_y * full.width() + full_x];
+                assert_eq!(*actual, expected, "{crop:?}, x={full_x}, y={full_y}");
+            }
+        }
+    }
+}
+
+#[test]
+fn ec_blending_info_per_extra_channel() {
+    let image = open(include_bytes!("synthetic/gradient_alpha_16x16.jxl"));
+    let num_ec = image.image_header().metadata.ec_info.len();
+    assert_eq!(num_ec, 1);
+
+    let frame_header = image.frame_header(0).unwrap();
+    assert_eq!(frame_header.ec_blending_info.len(), num_ec);
+
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.image_all_channels().channels(), 4);
+}
+
+#[test]
+fn alpha_mask() {
+    let image = open(include_bytes!("synthetic/gradient_alpha_16x16.jxl"));
+    let render = image.render_frame(0).unwrap();
+    let mask = render.alpha_mask().unwrap();
+    assert_eq!((mask.width(), mask.height()), (16, 16));
+
+    let rgba = render.image_all_channels();
+    for (&mask, pixel) in mask.buf().iter().zip(rgba.buf_grouped::<4>()) {
+        assert_eq!(mask, (pixel[3] * 255.0).round() as u8);
+    }
+    // Alpha of the image is `(x * 4) & 255`.
+    assert_eq!(&mask.buf()[..4], &[0, 4, 8, 12]);
+
+    let image = open(include_bytes!("synthetic/modular_rgb.jxl"));
+    assert!(image.render_frame(0).unwrap().alpha_mask().is_none());
+}
+
+#[test]
+fn render_rgba8() {
+    let image = open(include_bytes!("synthetic/gradient_alpha_16x16.jxl"));
+    let render = image.render_frame(0).unwrap();
+    let straight = render.render_rgba8(false);
+    let premultiplied = render.render_rgba8(true);
+    assert_eq!(straight.len(), 16 * 16 * 4);
+
+    let rgba = render.image_all_channels();
+    for ((straight, premultiplied), pixel) in straight
+        .chunks_exact(4)
+        .zip(premultiplied.chunks_exact(4))
+        .zip(rgba.buf_grouped::<4>())
+    {
+        assert_eq!(straight[3], premultiplied[3]);
+        let alpha = straight[3] as f32 / 255.0;
+        for c in 0..3 {
+            assert_eq!(
+                straight[c],
+                (pixel[c].clamp(0.0, 1.0) * 255.0).round() as u8
+            );
+            let expected = straight[c] as f32 * alpha;
+            assert!(
+                (premultiplied[c] as f32 - expected).abs() <= 1.0,
+                "{premultiplied:?} != {straight:?} * {alpha}"
+            );
+        }
+    }
+    // Alpha of the first pixel is zero.
+    assert_eq!(&premultiplied[..4], &[0, 0, 0, 0]);
+
+    // Opaque if the image doesn't have alpha.
+    let image = open(include_bytes!("synthetic/modular_rgb.jxl"));
+    let rgba = image.render_frame(0).unwrap().render_rgba8(true);
+    assert_eq!(&rgba[..4], &[100, 20, 30, 255]);
+}
+
+#[test]
+fn render_premultiplied_linear_f32() {
+    // Color samples are 240, alpha is 128 (about 50%).
+    let data = include_bytes!("synthetic/modular_alpha_half_8x8.jxl");
+    let premultiplied = open(data)
+        .render_frame(0)
+        .unwrap()
+        .render_premultiplied_linear_f32()
+        .unwrap();
+    assert_eq!(premultiplied.len(), 8 * 8 * 4);
+
+    let mut image = open(data);
+    image.request_color_encoding(srgb_linear());
+    let straight = image.render_frame(0).unwrap().image_all_channels();
+    for (premultiplied, straight) in premultiplied
+        .chunks_exact(4)
+        .zip(straight.buf_grouped::<4>())
+    {
+        let alpha = straight[3];
+        assert_eq!(alpha, 128.0 / 255.0);
+        assert_eq!(premultiplied[3], alpha);
+        for c in 0..3 {
+            // Premultiplication happens in linear light, after the sRGB transfer function is
+            // removed.
+            assert!(straight[c] > 0.8, "{straight:?}");
+            let expected = straight[c] * alpha;
+            assert!(
+                (premultiplied[c] - expected).abs() < 1e-5,
+                "{premultiplied:?} != {straight:?} * {alpha}"
+            );
+            assert!((premultiplied[c] / straight[c] - 0.5).abs() < 0.005);
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn cancel_render() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let data = include_bytes!("synthetic/blocks_300x260.jxl");
+    let expected = open(data).render_frame(0).unwrap().image_all_channels();
+
+    let flag = Arc::new(AtomicBool::new(false));
+    let mut image = JxlImage::builder()
+        .pool(JxlThreadPool::rayon(Some(4)))
+        .cancellation_flag(Arc::clone(&flag))
+        .read(std::io::Cursor::new(data))
+        .unwrap();
+
+    flag.store(true, Ordering::Relaxed);
+    let err = image.render_frame(0).unwrap_err();
+    assert_eq!(err.to_string(), "decoding was cancelled");
+
+    // Rendering again after clearing the flag succeeds.
+    flag.store(false, Ordering::Relaxed);
+    image.reset_render_cache();
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+}
+
+#[test]
+fn cancel_render_in_progress() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    // 135 groups, decoded in parallel.
+    let data = include_bytes!("synthetic/vardct_3840x2160.jxl");
+    let expected = open(data).render_frame(0).unwrap().image_all_channels();
+    let last_group = open(data)
+        .frame_by_keyframe(0)
+        .unwrap()
+        .toc()
+        .iter_bitstream_order()
+        .last()
+        .unwrap();
+
+    // Decode every group but the last one, then set the flag before the rest arrives so
+    // that the render is cancelled without depending on timing.
+    let split = data.len() - last_group.size as usize;
+    let flag = Arc::new(AtomicBool::new(false));
+    let mut image = JxlImage::builder()
+        .pool(JxlThreadPool::rayon(Some(4)))
+        .cancellation_flag(Arc::clone(&flag))
+        .read(std::io::Cursor::new(&data[..split]))
+        .unwrap();
+    image.render_loading_frame().unwrap();
+    flag.store(true, Ordering::Relaxed);
+    image.feed_bytes(&data[split..]).unwrap();
+    assert!(image.is_loading_done());
+
+    let err = image.render_frame(0).unwrap_err();
+    let err = err
+        .downcast_ref::<jxl_render::Error>()
+        .unwrap_or_else(|| panic!("{err}"));
+    assert!(matches!(err, jxl_render::Error::Cancelled), "{err}");
+
+    // Rendering can be retried once the flag is cleared.
+    flag.store(false, Ordering::Relaxed);
+    image.reset_render_cache();
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+}
+
+#[test]
+fn memory_budget() {
+    let data = include_bytes!("synthetic/vardct_3840x2160.jxl");
+
+    // Color channels of the frame alone take about 100 MB.
+    let image = JxlImage::builder()
+        .memory_budget(16 * 1024 * 1024)
+        .read(std::io::Cursor::new(data))
+        .unwrap();
+    let err = image.render_frame(0).unwrap_err();
+    let err = err
+        .downcast_ref::<jxl_render::Error>()
+        .unwrap_or_else(|| panic!("{err}"));
+    assert!(
+        matches!(err, jxl_render::Error::MemoryBudgetExceeded(_)),
+        "unexpected error: {err}"
+    );
+
+    let image = JxlImage::builder()
+        .memory_budget(1024 * 1024 * 1024)
+        .read(std::io::Cursor::new(data))
+        .unwrap();
+    image.render_frame(0).unwrap();
+}
+
+#[test]
+fn render_without_noise() {
+    let data = include_bytes!("synthetic/vardct_noise_64x64.jxl");
+    let render_with = |features| {
+        JxlImage::builder()
+            .features(features)
+            .read(std::io::Cursor::new(data))
+            .unwrap()
+            .render_frame(0)
+            .unwrap()
+            .image_all_channels()
+    };
+
+    // Base image is flat, and noise makes it non-flat.
+    let noisy = render_with(RenderFeatures::default());
+    assert!(noisy.buf().chunks_exact(3).any(|p| p != &noisy.buf()[..3]));
+
+    let features = RenderFeatures {
+        noise: false,
+        ..Default::default()
+    };
+    let fb = render_with(features);
+    assert!(fb.buf().chunks_exact(3).all(|p| p == &fb.buf()[..3]));
+    assert_eq!(render_with(features).buf(), fb.buf());
+}
+
+#[test]
+fn max_spline_area() {
+    // A single spline of length 300 with large sigma, exceeding the default limit for 64x64.
+    let data = include_bytes!("synthetic/vardct_spline_64x64.jxl");
+    let render_with = |builder: jxl_oxide::JxlImageBuilder| {
+        builder
+            .read(std::io::Cursor::new(data))
+            .unwrap()
+            .render_frame(0)
+    };
+
+    let err = render_with(JxlImage::builder()).unwrap_err();
+    let err = err
+        .downcast_ref::<jxl_render::Error>()
+        .unwrap_or_else(|| panic!("{err}"));
+    assert!(
+        matches!(
+            err,
+            jxl_render::Error::Frame(jxl_frame::Error::SplineAreaTooLarge { .. })
+        ),
+        "{err}"
+    );
+
+    let render = render_with(JxlImage::builder().max_spline_area(u64::MAX)).unwrap();
+    let without_splines = render_with(JxlImage::builder().max_spline_area(u64::MAX).features(
+        RenderFeatures {
+            splines: false,
+            ..Default::default()
+        },
+    ))
+    .unwrap();
+    assert_ne!(
+        render.image_all_channels().buf(),
+        without_splines.image_all_channels().buf()
+    );
+}
+
+#[test]
+fn independent_frames() {
+    let image = open(include_bytes!("synthetic/animation_blend_264x8.jxl"));
+    let independent = (0..image.num_loaded_frames())
+        .map(|idx| image.frame(idx).unwrap().header().is_independent())
+        .collect::<Vec<_>>();
+    // The second frame is added on top of the first one.
+    assert_eq!(independent, [true, false, true]);
+    let first = image.render_frame(0).unwrap().image_all_channels();
+    let second = image.render_frame(1).unwrap().image_all_channels();
+    assert_ne!(first.buf(), second.buf());
+
+    let image = open(include_bytes!("synthetic/animation_pages_264x8.jxl"));
+    for idx in 0..image.num_loaded_frames() {
+        assert!(image.frame(idx).unwrap().header().is_independent());
+    }
+    let image = open(include_bytes!("synthetic/patch_off_canvas_8x8.jxl"));
+    let last = image.num_loaded_frames() - 1;
+    assert!(!image.frame(last).unwrap().header().is_independent());
+}
+
+#[test]
+fn vardct_hf_metadata() {
+    let image = open(include_bytes!(
+        "fuzz_findings/squeeze_tendency_overflow.fuzz"
+    ));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    let frame_header = frame.header();
+    assert_eq!(frame_header.encoding, Encoding::VarDct);
+
+    let lf_global = frame.try_parse_lf_global::<i32>().unwrap().unwrap();
+    let lf_group = frame
+        .try_parse_lf_group::<i32>(
+            lf_global.vardct.as_ref(),
+            lf_global.gmodular.ma_config(),
+            None,
+            0,
+        )
+        .unwrap()
+        .unwrap();
+    let hf_meta = lf_group.hf_meta.as_ref().unwrap();
+
+    // One entry per 8x8 block.
+    let block_width = frame_header.width.div_ceil(8) as usize;
+    let block_height = frame_header.height.div_ceil(8) as usize;
+    for (width, height) in [
+        (hf_meta.block_info.width(), hf_meta.block_info.height()),
+        (hf_meta.epf_sigma.width(), hf_meta.epf_sigma.height()),
+    ] {
+        assert_eq!((width, height), (block_width, block_height));
+    }
+    // Chroma-from-luma factors are stored per 64x64 tile.
+    assert_eq!(hf_meta.x_from_y.width(), block_width.div_ceil(8));
+    assert_eq!(hf_meta.b_from_y.height(), block_height.div_ceil(8));
+}
+
+#[test]
+fn vardct_lf_coefficients() {
+    let image = open(include_bytes!(
+        "fuzz_findings/squeeze_tendency_overflow.fuzz"
+    ));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    let frame_header = frame.header();
+
+    let lf_global = frame.try_parse_lf_global::<i32>().unwrap().unwrap();
+    let lf_group = frame
+        .try_parse_lf_group::<i32>(
+            lf_global.vardct.as_ref(),
+            lf_global.gmodular.ma_config(),
+            None,
+            0,
+        )
+        .unwrap()
+        .unwrap();
+
+    // One LF sample per 8x8 block.
+    let block_width = frame_header.width.div_ceil(8) as usize;
+    let block_height = frame_header.height.div_ceil(8) as usize;
+    let lf_coeffs = lf_group.lf_coefficients().unwrap();
+    for grid in lf_coeffs {
+        assert_eq!((grid.width(), grid.height()), (block_width, block_height));
+    }
+    assert!(lf_coeffs
+        .iter()
+        .any(|grid| grid.buf().iter().any(|&v| v != 0)));
+}
+
+#[test]
+fn vardct_lf_dequant() {
+    // Same image as `squeeze_tendency_overflow`, with LF dequantization weights signalled
+    // explicitly; one has the default weights, and the other has doubled `m_y_lf`.
+    let original = open(include_bytes!(
+        "fuzz_findings/squeeze_tendency_overflow.fuzz"
+    ));
+    let explicit = open(include_bytes!("synthetic/vardct_lf_dequant_explicit.jxl"));
+    let custom = open(include_bytes!("synthetic/vardct_lf_dequant_custom.jxl"));
+
+    let lf_global = |image: &JxlImage| {
+        let frame = image.frame_by_keyframe(0).unwrap();
+        let lf_global = frame.try_parse_lf_global::<i32>().unwrap().unwrap();
+        let lf_dequant = &lf_global.lf_dequant;
+        [lf_dequant.m_x_lf, lf_dequant.m_y_lf, lf_dequant.m_b_lf]
+    };
+    assert_eq!(lf_global(&original), [1.0 / 32.0, 1.0 / 4.0, 1.0 / 2.0]);
+    assert_eq!(lf_global(&explicit), [1.0 / 32.0, 1.0 / 4.0, 1.0 / 2.0]);
+    assert_eq!(lf_global(&custom), [1.0 / 32.0, 1.0 / 2.0, 1.0 / 2.0]);
+
+    let render = |image: &JxlImage| image.render_frame(0).unwrap().image_all_channels();
+    let expected = render(&original);
+    assert_eq!(render(&explicit).buf(), expected.buf());
+    // Only the dequantization weights differ from the original.
+    assert_ne!(render(&custom).buf(), expected.buf());
+}
+
+#[test]
+fn vardct_average_color() {
+    let image = open(include_bytes!("synthetic/vardct_solid_512x64.jxl"));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    let average = frame.average_color().unwrap();
+
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    let first = &fb.buf()[..3];
+    assert!(first[0] != first[1] && first[1] != first[2]);
+    for pixel in fb.buf().chunks_exact(3) {
+        for ((&v, &expected), &avg) in pixel.iter().zip(first).zip(&average) {
+            assert!((v - expected).abs() < 1e-5);
+            assert!((v - avg).abs() < 1e-3, "{v} != {avg}");
+        }
+    }
+
+    // Same LF coefficients with `extra_precision = 2`, so dequantized LF is four times smaller.
+    let image = open(include_bytes!(
+        "synthetic/vardct_solid_extra_precision_512x64.jxl"
+    ));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    let precise_average = frame.average_color().unwrap();
+    assert_ne!(precise_average, average);
+
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    let first = &fb.buf()[..3];
+    for pixel in fb.buf().chunks_exact(3) {
+        for ((&v, &expected), &avg) in pixel.iter().zip(first).zip(&precise_average) {
+            assert!((v - expected).abs() < 1e-5);
+            assert!((v - avg).abs() < 1e-3, "{v} != {avg}");
+        }
+    }
+
+    // Modular frames don't have LF image.
+    let image = open(include_bytes!("synthetic/modular_rgb.jxl"));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(frame.average_color().is_err());
+}
+
+#[test]
+fn vardct_flat_frame() {
+    // Constant LF and no HF coefficients, with Gabor-like filter and EPF enabled. Flat frames
+    // skip dequantization, transform and restoration filters, so every sample is exactly the same.
+    let image = open(include_bytes!("synthetic/vardct_solid_1024x256.jxl"));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(frame.header().restoration_filter.gab.enabled());
+    let average = frame.average_color().unwrap();
+
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!((fb.width(), fb.height()), (1024, 256));
+    let first = &fb.buf()[..3];
+    for pixel in fb.buf().chunks_exact(3) {
+        assert_eq!(pixel, first);
+    }
+    for (&v, &avg) in first.iter().zip(&average) {
+        assert!((v - avg).abs() < 1e-3, "{v} != {avg}");
+    }
+
+    // LF of this frame is a horizontal ramp, so it takes the general path.
+    let image = open(include_bytes!("synthetic/vardct_ramp_264x8.jxl"));
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    let first = &fb.buf()[..3];
+    assert!(fb.buf().chunks_exact(3).any(|pixel| pixel != first));
+}
+
+#[test]
+fn vardct_hf_global() {
+    // Multi-group frame, and a frame with a single TOC entry.
+    for data in [
+        &include_bytes!("synthetic/vardct_500x300.jxl")[..],
+        &include_bytes!("fuzz_findings/squeeze_tendency_overflow.fuzz")[..],
+    ] {
+        let image = open(data);
+        let frame = image.frame_by_keyframe(0).unwrap();
+        assert_eq!(frame.header().encoding, Encoding::VarDct);
+
+        let hf_global = frame.try_parse_hf_global::<i32>(None).unwrap().unwrap();
+        assert_eq!(hf_global.num_hf_presets, 1);
+        assert_eq!(
+            hf_global.hf_passes.len(),
+            frame.header().passes.num_passes as usize
+        );
+    }
+}
+
+#[test]
+fn vardct_tile_xyb() {
+    // 32x32 VarDCT image in a single TOC entry, with constant LF and no HF coefficients.
+    let mut image = open(include_bytes!("synthetic/vardct_solid_32x32.jxl"));
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert!(frame.toc().is_single_entry());
+    assert_eq!(frame.header().encoding, Encoding::VarDct);
+
+    image.request_color_encoding(EnumColourEncoding::xyb(RenderingIntent::Relative));
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!((fb.width(), fb.height(), fb.channels()), (32, 32, 3));
+
+    // LF quantized to (Y, X, B) = (64, 8, 8), dequantized with default weights, global_scale of
+    // 1024 and quant_lf of 16. B is offset by Y by the default chroma-from-luma.
+    let expected = [1.0 / 128.0, 0.5, 0.125 + 0.5];
+    for pixel in fb.buf_grouped::<3>() {
+        for (&v, &expected) in pixel.iter().zip(&expected) {
+            assert!((v - expected).abs() < 1e-5, "{v} != {expected}");
+        }
+    }
+}
+
+#[test]
+fn vardct_missing_hf_global() {
+    // Codestream of `squeeze_tendency_overflow.fuzz` whose TOC entry is cut right after LfGroup,
+    // so that the frame is fully loaded without HfGlobal.
+    let image = open(include_bytes!("synthetic/vardct_missing_hf_global.jxl"));
+    assert!(image.is_loading_done());
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert_eq!(frame.header().encoding, Encoding::VarDct);
+
+    let err = image.render_frame(0).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "frame error: incomplete frame data: hf_global is missing"
+    );
+}
+
+#[test]
+fn codestream_crc32() {
+    fn crc32(data: &[u8]) -> Option<u32> {
+        JxlImage::builder()
+            .codestream_crc32(true)
+            .read(std::io::Cursor::new(data))
+            .unwrap()
+            .codestream_crc32()
+    }
+
+    let data = include_bytes!("synthetic/blocks_300x260.jxl");
+    assert_eq!(open(data).codestream_crc32(), None);
+
+    // Bare codestream, so the checksum matches CRC-32 of the whole file.
+    let crc = crc32(data);
+    assert_eq!(crc, Some(0x085baa56));
+    assert_eq!(crc32(data), crc);
+
+    let mut corrupted = data.to_vec();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0x10;
+    assert_ne!(crc32(&corrupted), crc);
+
+    // Container boxes are excluded; the codestream is split into two `jxlp` boxes.
+    let data = include_bytes!("fuzz_findings/squeeze_tendency_overflow.fuzz");
+    assert_eq!(crc32(data), Some(0x39e3ab46));
+}
+
+#[test]
+fn read_concatenated() {
+    let files: [&[u8]; 3] = [
+        include_bytes!("synthetic/blocks_300x260.jxl"),
+        include_bytes!("fuzz_findings/squeeze_tendency_overflow.fuzz"),
+        include_bytes!("synthetic/modular_rgb.jxl"),
+    ];
+    let data = files.concat();
+
+    let mut offset = 0;
+    for file in files {
+        let expected = open(file).render_frame(0).unwrap().image_all_channels();
+
+        let (image, end) = JxlImage::builder().read_at(&data, offset).unwrap();
+        assert_eq!(end - offset, file.len());
+        let actual = image.render_frame(0).unwrap().image_all_channels();
+        assert_eq!(actual.buf(), expected.buf());
+        offset = end;
+    }
+    assert_eq!(offset, data.len());
+    assert!(JxlImage::builder().read_at(&data, offset).is_err());
+}
+
+#[test]
+fn leading_garbage() {
+    // Garbage with decoys: a truncated container signature, and a codestream signature followed
+    // by an invalid image header.
+    let garbage: &[u8] = b"garbage\0\0\0\x0cJXL \xff\x0a\0\0\0\0\0\0\0\0more";
+    assert_eq!(find_jxl_start(garbage), None);
+
+    for file in [
+        &include_bytes!("synthetic/modular_rgb.jxl")[..],
+        &include_bytes!("fuzz_findings/squeeze_tendency_overflow.fuzz")[..],
+    ] {
+        let expected = open(file).render_frame(0).unwrap().image_all_channels();
+        let data = [garbage, file].concat();
+        assert_eq!(find_jxl_start(&data), Some(garbage.len()));
+
+        // Without scanning, garbage is not a valid image.
+        assert!(JxlImage::builder()
+            .read(std::io::Cursor::new(&data))
+            .is_err());
+
+        let mut uninit = JxlImage::builder().scan_signature(true).build_uninit();
+        let mut chunks = data.chunks(7);
+        let mut image = loop {
+            uninit.feed_bytes(chunks.next().unwrap()).unwrap();
+            match uninit.try_init().unwrap() {
+                InitializeResult::NeedMoreData(x) => uninit = x,
+                InitializeResult::Initialized(x) => break x,
+            }
+        };
+        for chunk in chunks {
+            image.feed_bytes(chunk).unwrap();
+        }
+        let actual = image.render_frame(0).unwrap().image_all_channels();
+        assert_eq!(actual.buf(), expected.buf());
+    }
+}
+
+#[test]
+fn leading_garbage_limit() {
+    let file = include_bytes!("synthetic/modular_rgb.jxl");
+    let expected = open(file).render_frame(0).unwrap().image_all_channels();
+
+    // Scanned garbage is discarded, so large garbage doesn't need to be kept in memory.
+    let garbage = vec![0u8; (1 << 20) - 64];
+    let data = [&garbage[..], file].concat();
+    let image = JxlImage::builder()
+        .scan_signature(true)
+        .read(std::io::Cursor::new(&data))
+        .unwrap();
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+
+    // Give up if the signature is not found in the first 1 MiB.
+    let garbage = vec![0u8; (1 << 20) + 4096];
+    let data = [&garbage[..], file].concat();
+    let err = JxlImage::builder()
+        .scan_signature(true)
+        .read(std::io::Cursor::new(&data))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "JPEG XL signature not found");
+}
+
+#[test]
+fn animation_single_frame() {
+    let image = open(include_bytes!("synthetic/animation_single_frame.jxl"));
+    let animation = image.image_header().metadata.animation.as_ref().unwrap();
+    assert_eq!(animation.tps_numerator, 100);
+    assert_eq!(animation.tps_denominator, 1);
+    assert!(image.is_loading_done());
+    assert_eq!(image.num_loaded_keyframes(), 1);
+    assert_eq!(image.num_loaded_frames(), 1);
+
+    let frame_header = image.frame_header(0).unwrap();
+    assert!(frame_header.is_last);
+    assert_eq!(frame_header.duration, 50);
+
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.duration(), 50);
+    let fb = render.image_all_channels();
+    for pixel in fb.buf().chunks_exact(3) {
+        for (&v, &sample) in pixel.iter().zip(&MODULAR_SAMPLES) {
+            assert_eq!((v * 255.0).round() as i32, sample);
+        }
+    }
+}
+
+#[test]
+fn invalid_passes() {
+    for (data, reason) in [
+        (
+            &include_bytes!("synthetic/passes_downsample_increasing.jxl")[..],
+            "downsampling factors are not strictly decreasing",
+        ),
+        (
+            &include_bytes!("synthetic/passes_last_pass_out_of_range.jxl")[..],
+            "invalid last_pass in passes",
+        ),
+        (
+            &include_bytes!("synthetic/passes_last_pass_duplicate.jxl")[..],
+            "invalid last_pass in passes",
+        ),
+    ] {
+        let err = JxlImage::builder()
+            .read(std::io::Cursor::new(data))
+            .unwrap_err();
+        assert!(err.to_string().contains(reason), "{err}");
+        assert!(
+            err.to_string().contains("invalid pass configuration"),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn frame_group_geometry() {
+    for (data, group_dim) in [
+        (&include_bytes!("synthetic/modular_rgb.jxl")[..], 256),
+        (&include_bytes!("synthetic/blocks_300x260.jxl")[..], 256),
+        (
+            &include_bytes!("synthetic/weighted_predictor_300x260.jxl")[..],
+            256,
+        ),
+        (&include_bytes!("synthetic/modular_ycbcr_420.jxl")[..], 256),
+        (
+            &include_bytes!("synthetic/modular_group_dim_128_300x260.jxl")[..],
+            128,
+        ),
+        (
+            &include_bytes!("fuzz_findings/squeeze_tendency_overflow.fuzz")[..],
+            256,
+        ),
+    ] {
+        let image = open(data);
+        let frame = image.frame_by_keyframe(0).unwrap();
+        let header = frame.header();
+        assert_eq!(frame.group_dim(), group_dim);
+        assert_eq!(frame.lf_group_dim(), group_dim * 8);
+
+        let groups_per_row = header.color_sample_width().div_ceil(group_dim);
+        let groups_per_col = header.color_sample_height().div_ceil(group_dim);
+        assert_eq!(frame.num_pass_groups(), groups_per_row * groups_per_col);
+        let lf_groups_per_row = header.color_sample_width().div_ceil(group_dim * 8);
+        let lf_groups_per_col = header.color_sample_height().div_ceil(group_dim * 8);
+        assert_eq!(frame.num_lf_groups(), lf_groups_per_row * lf_groups_per_col);
+
+        let toc = frame.toc();
+        if toc.is_single_entry() {
+            assert_eq!(frame.num_lf_groups(), 1);
+            assert_eq!(frame.num_pass_groups(), 1);
+            continue;
+        }
+        let mut num_lf_groups = 0;
+        let mut num_pass_groups = 0;
+        for group in toc.iter_bitstream_order() {
+            match group.kind {
+                TocGroupKind::LfGroup(_) => num_lf_groups += 1,
+                TocGroupKind::GroupPass { .. } => num_pass_groups += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(num_lf_groups, frame.num_lf_groups());
+        assert_eq!(
+            num_pass_groups,
+            frame.num_pass_groups() * header.passes.num_passes
+        );
+    }
+}
+
+#[test]
+fn group_dim_128() {
+    // 300x260 Modular image with group_size_shift = 0. Every sample is West + 1, so the contents
+    // restart at each pass group.
+    let data = include_bytes!("synthetic/modular_group_dim_128_300x260.jxl");
+    let mut image = open(data);
+    let header = image.frame_header(0).unwrap();
+    assert_eq!(header.group_dim(), 128);
+    assert_eq!(header.lf_group_dim(), 1024);
+    assert_eq!((header.groups_per_row(), header.num_groups()), (3, 9));
+    assert_eq!(header.num_lf_groups(), 1);
+    assert_eq!(header.group_size_for(8), (300 - 256, 260 - 256));
+
+    assert!(header.is_group_collides_region(4, (130, 130, 10, 10)));
+    assert!(!header.is_group_collides_region(0, (130, 130, 10, 10)));
+    let colliding = (0..9)
+        .filter(|&idx| header.is_group_collides_region(idx, (120, 0, 10, 10)))
+        .collect::<Vec<_>>();
+    assert_eq!(colliding, [0, 1]);
+    assert_eq!(header.lf_group_idx_from_group_idx(8), 0);
+
+    let expected = |x: u32, y: u32| ((x % 128) + (y % 128) + 1) as f32 / 255.0;
+    let full = image.render_frame(0).unwrap().image_all_channels();
+    for (idx, pixel) in full.buf_grouped::<3>().iter().enumerate() {
+        let (x, y) = ((idx % 300) as u32, (idx / 300) as u32);
+        assert_eq!(pixel[0], expected(x, y), "x={x}, y={y}");
+    }
+
+    // Crossing group boundaries at x = 256 and y = 128.
+    let crop = CropInfo {
+        width: 40,
+        height: 20,
+        left: 250,
+        top: 120,
+    };
+    image.set_image_region(crop);
+    let cropped = image.render_frame_cropped(0).unwrap().image_all_channels();
+    assert_eq!((cropped.width(), cropped.height()), (40, 20));
+    for (idx, pixel) in cropped.buf_grouped::<3>().iter().enumerate() {
+        let x = crop.left + (idx % 40) as u32;
+        let y = crop.top + (idx / 40) as u32;
+        assert_eq!(*pixel, [expected(x, y); 3], "x={x}, y={y}");
+    }
+}
+
+#[test]
+fn modular_use_lf_frame() {
+    let data = include_bytes!("synthetic/modular_use_lf_frame.jxl");
+    let err = JxlImage::builder()
+        .read(std::io::Cursor::new(data))
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("inconsistent frame header: Modular frame cannot use LF frame"),
+        "{err}"
+    );
+}
+
+#[test]
+fn blend_alpha_not_alpha() {
+    let data = include_bytes!("synthetic/blend_alpha_not_alpha.jxl");
+    let err = JxlImage::builder()
+        .read(std::io::Cursor::new(data))
+        .unwrap_err();
+    assert!(
+        err.to_string().contains(
+            "frame is inconsistent with image metadata: \
+            blending_info.alpha_channel is not the type of Alpha"
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn lossless_integer_samples() {
+    // Each 32x32 block of the image has color `(29 * bx, 31 * by, 13 * (bx + by))`, modulo 256.
+    let render = open(include_bytes!("synthetic/blocks_300x260.jxl"))
+        .render_frame(0)
+        .unwrap();
+    let channels = (0..3)
+        .map(|idx| render.image_channel_int(idx).unwrap())
+        .collect::<Vec<_>>();
+    assert!(render.image_channel_int(3).is_none());
+    for y in 0..260 {
+        for x in 0..300 {
+            let (bx, by) = (x as i32 / 32, y as i32 / 32);
+            let expected = [29 * bx, 31 * by, 13 * (bx + by)].map(|v| v & 255);
+            let actual = channels
+                .iter()
+                .map(|g| *g.get(x, y).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected, "x={x}, y={y}");
+        }
+    }
+
+    // Alpha of the image is `(x * 4) & 255`.
+    let render = open(include_bytes!("synthetic/gradient_alpha_16x16.jxl"))
+        .render_frame(0)
+        .unwrap();
+    let alpha = render.image_channel_int(3).unwrap();
+    for y in 0..16 {
+        for x in 0..16 {
+            assert_eq!(*alpha.get(x, y).unwrap(), (x as i32 * 4) & 255);
+        }
+    }
+
+    // XYB encoded image goes through floating point processing.
+    let render = open(include_bytes!("synthetic/modular_xyb.jxl"))
+        .render_frame(0)
+        .unwrap();
+    assert!(render.image_channel_int(0).is_none());
+}
+
+#[test]
+fn decode_at_scale_thumbnail() {
+    // 3840x2160 VarDCT image with zero HF coefficients, padded so that each pass group takes 64
+    // bytes.
+    let data = include_bytes!("synthetic/vardct_3840x2160.jxl");
+    let thumbnail = decode_at_scale(data, 256).unwrap();
+    let fb = thumbnail.image();
+    assert_eq!((fb.width(), fb.height(), fb.channels()), (256, 144, 3));
+    assert_eq!(thumbnail.downsampling(), 8);
+    // LF groups take only a few bytes, and pass groups are not read at all.
+    assert!(
+        thumbnail.bytes_read() * 4 < data.len(),
+        "read {} bytes out of {}",
+        thumbnail.bytes_read(),
+        data.len()
+    );
+
+    // Luma of the image increases to the right and to the bottom within each 2048x2048 LF group.
+    let buf = fb.buf_grouped::<3>();
+    for y in 0..144 {
+        for x in 1..128 {
+            assert!(
+                buf[y * 256 + x][1] >= buf[y * 256 + x - 1][1],
+                "x={x}, y={y}"
+            );
+        }
+    }
+    for x in 0..256 {
+        for y in 1..136 {
+            assert!(
+                buf[y * 256 + x][1] >= buf[(y - 1) * 256 + x][1],
+                "x={x}, y={y}"
+            );
+        }
+    }
+
+    // Single-pass Modular image needs all of the data.
+    let data = include_bytes!("synthetic/blocks_300x260.jxl");
+    let thumbnail = decode_at_scale(data, 64).unwrap();
+    let fb = thumbnail.image();
+    assert_eq!((fb.width(), fb.height()), (64, 55));
+    assert_eq!(thumbnail.downsampling(), 1);
+    assert_eq!(thumbnail.bytes_read(), data.len());
+}
+
+#[test]
+fn crop_upsampled_frame() {
+    // 1000x600 VarDCT image, coded at 500x300 and upsampled by 2.
+    let data = include_bytes!("synthetic/vardct_upsampling2_1000x600.jxl");
+    let image = open(data);
+    let frame_header = image.frame_header(0).unwrap();
+    assert_eq!(frame_header.upsampling, 2);
+    assert_eq!(frame_header.color_sample_width(), 500);
+    let full = image.render_frame(0).unwrap().image_all_channels();
+
+    let regions = [
+        // Odd offset and size in the output space.
+        CropInfo {
+            width: 101,
+            height: 37,
+            left: 333,
+            top: 171,
+        },
+        // Only in the right half, which maps to the second pass group column after upsampling is
+        // taken into account.
+        CropInfo {
+            width: 200,
+            height: 100,
+            left: 760,
+            top: 499,
+        },
+        // Straddling the pass group boundary at x = 512 of the output space.
+        CropInfo {
+            width: 16,
+            height: 16,
+            left: 505,
+            top: 3,
+        },
+    ];
+
+    let mut image = open(data);
+    for crop in regions {
+        image.set_image_region(crop);
+        let cropped = image.render_frame_cropped(0).unwrap().image_all_channels();
+        assert_eq!(cropped.width(), crop.width as usize);
+        assert_eq!(cropped.height(), crop.height as usize);
+
+        for (y, row) in cropped
+            .buf_grouped::<3>()
+            .chunks_exact(cropped.width())
+            .enumerate()
+        {
+            for (x, actual) in row.iter().enumerate() {
+                let full_x = crop.left as usize + x;
+                let full_y = crop.top as usize + y;
+                let expected = full.buf_grouped::<3>()[full_y * full.width() + full_x];
+                assert_eq!(*actual, expected, "{crop:?}, x={full_x}, y={full_y}");
+            }
+        }
+    }
+}
+
+#[test]
+fn vardct_alpha() {
+    // 512x512 VarDCT image with alpha of 200 everywhere. Extra channels are always Modular coded,
+    // stored in pass groups alongside HF coefficients of color channels.
+    let image = open(include_bytes!("synthetic/vardct_alpha_512x512.jxl"));
+    assert_eq!(image.frame_header(0).unwrap().encoding, Encoding::VarDct);
+    assert!(image.pixel_format().has_alpha());
+    let render = image.render_frame(0).unwrap();
+
+    let mask = render.alpha_mask().unwrap();
+    assert_eq!((mask.width(), mask.height()), (512, 512));
+    assert!(mask.buf().iter().all(|&v| v == 200));
+
+    let rgba = render.image_all_channels();
+    assert_eq!(rgba.channels(), 4);
+    assert!(rgba
+        .buf_grouped::<4>()
+        .iter()
+        .all(|px| px[3] == 200.0 / 255.0));
+
+    let mut stream = render.stream();
+    assert_eq!(stream.channels(), 4);
+    let mut buf = vec![0f32; 512 * 512 * 4];
+    assert_eq!(stream.write_to_buffer(&mut buf), buf.len());
+    assert_eq!(buf, rgba.buf());
+}
+
+#[test]
+fn truncated_extra_channel_data() {
+    // Alpha samples of the VarDCT image are stored in pass groups, so cutting the data in the
+    // middle of a pass group leaves the alpha channel short.
+    let data = include_bytes!("synthetic/vardct_alpha_512x512.jxl");
+    let image = open(data);
+    let groups: Vec<_> = image
+        .frame_by_keyframe(0)
+        .unwrap()
+        .toc()
+        .iter_bitstream_order()
+        .collect();
+    let mut end = data.len();
+    for group in groups.iter().rev() {
+        let len = end - group.size as usize / 2;
+        end -= group.size as usize;
+
+        let image = JxlImage::builder()
+            .read(std::io::Cursor::new(&data[..len]))
+            .unwrap();
+        match image.render_frame(0) {
+            // Every channel declared in the image header should be present.
+            Ok(render) => assert_eq!(render.image_all_channels().channels(), 4),
+            Err(e) => {
+                // Short data should be reported as such, not as a channel count mismatch.
+                let e = e
+                    .downcast_ref::<jxl_render::Error>()
+                    .unwrap_or_else(|| panic!("{e}"));
+                assert!(
+                    e.unexpected_eof() || matches!(e, jxl_render::Error::IncompleteFrame),
+                    "{e}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn pass_callback() {
+    // Three passes, two pass groups each.
+    let data = include_bytes!("synthetic/vardct_passes3_264x8.jxl");
+    let expected = open(data).render_frame(0).unwrap().image_all_channels();
+
+    let init = || {
+        let mut uninit = JxlImage::builder().build_uninit();
+        let mut offset = 0;
+        loop {
+            uninit.feed_bytes(&data[offset..offset + 1]).unwrap();
+            offset += 1;
+            match uninit.try_init().unwrap() {
+                InitializeResult::NeedMoreData(x) => uninit = x,
+                InitializeResult::Initialized(x) => break (x, offset),
+            }
+        }
+    };
+
+    let (mut image, offset) = init();
+    let mut passes = Vec::new();
+    image
+        .feed_bytes_with_callback(&data[offset..], |image, frame_idx, pass_idx| {
+            passes.push((frame_idx, pass_idx));
+            let render = if pass_idx == 2 {
+                assert!(image.is_loading_done());
+                image.render_frame(0)?
+            } else {
+                assert!(!image.is_loading_done());
+                image.render_loading_frame()?
+            };
+            assert_eq!(render.image_all_channels().width(), 264);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(passes, [(0, 0), (0, 1), (0, 2)]);
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+
+    // Returning an error aborts loading, and it can be resumed later.
+    let mut full = JxlImage::builder().codestream_crc32(true).build_uninit();
+    full.feed_bytes(data).unwrap();
+    let InitializeResult::Initialized(full) = full.try_init().unwrap() else {
+        panic!("image is not initialized");
+    };
+    let expected_crc = full.codestream_crc32().unwrap();
+
+    let mut uninit = JxlImage::builder().codestream_crc32(true).build_uninit();
+    uninit.feed_bytes(&data[..offset]).unwrap();
+    let InitializeResult::Initialized(mut image) = uninit.try_init().unwrap() else {
+        panic!("image is not initialized");
+    };
+    let err = image
+        .feed_bytes_with_callback(&data[offset..], |_, _, _| Err("abort".into()))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "abort");
+    assert!(!image.is_loading_done());
+    assert_ne!(image.codestream_crc32().unwrap(), expected_crc);
+    image.feed_bytes(&[]).unwrap();
+    assert!(image.is_loading_done());
+    assert_eq!(image.codestream_crc32().unwrap(), expected_crc);
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+
+    let (mut image, offset) = init();
+    let mut passes = Vec::new();
+    let err = image
+        .feed_bytes_with_callback(&data[offset..], |_, frame_idx, pass_idx| {
+            passes.push((frame_idx, pass_idx));
+            Err("abort".into())
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "abort");
+    assert_eq!(passes, [(0, 0)]);
+    assert!(!image.is_loading_done());
+}
+
+#[test]
+fn multi_pass_coefficients() {
+    let render = |data: &[u8]| open(data).render_frame(0).unwrap().image_all_channels();
+
+    // Same LF as `vardct_ramp_264x8.jxl`, with all HF coefficients zero in every pass.
+    let expected = render(include_bytes!("synthetic/vardct_ramp_264x8.jxl"));
+    let actual = render(include_bytes!("synthetic/vardct_passes3_264x8.jxl"));
+    assert_eq!(actual.buf(), expected.buf());
+
+    // Every varblock has a single HF coefficient of 1, 2 and 3 in each pass, with pass shifts of 2,
+    // 1 and 0. Accumulated coefficients should be the same as a single pass with coefficients of
+    // 1 * 4 + 2 * 2 + 3 = 11.
+    let expected = render(include_bytes!("synthetic/vardct_hf_264x8.jxl"));
+    let actual = render(include_bytes!("synthetic/vardct_passes3_hf_264x8.jxl"));
+    assert_eq!(actual.buf(), expected.buf());
+    let base = render(include_bytes!("synthetic/vardct_ramp_264x8.jxl"));
+    assert_ne!(actual.buf(), base.buf());
+}
+
+#[test]
+fn reverse_toc_order() {
+    // Same image as `vardct_ramp_264x8.jxl`, but with a permuted TOC storing sections in reverse.
+    let data = include_bytes!("synthetic/vardct_reverse_toc_264x8.jxl");
+    let expected = open(include_bytes!("synthetic/vardct_ramp_264x8.jxl"))
+        .render_frame(0)
+        .unwrap()
+        .image_all_channels();
+
+    let image = open(data);
+    let kinds = image
+        .frame_by_keyframe(0)
+        .unwrap()
+        .toc()
+        .iter_bitstream_order()
+        .map(|group| group.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TocGroupKind::GroupPass {
+                pass_idx: 0,
+                group_idx: 1
+            },
+            TocGroupKind::GroupPass {
+                pass_idx: 0,
+                group_idx: 0
+            },
+            TocGroupKind::HfGlobal,
+            TocGroupKind::LfGroup(0),
+            TocGroupKind::LfGlobal,
+        ]
+    );
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+
+    // Pass groups arrive before LfGlobal; they are kept until it is loaded.
+    let lf_global_size = image
+        .frame_by_keyframe(0)
+        .unwrap()
+        .toc()
+        .iter_bitstream_order()
+        .last()
+        .unwrap()
+        .size as usize;
+    let split = data.len() - lf_global_size;
+    let mut image = JxlImage::builder()
+        .read(std::io::Cursor::new(&data[..split]))
+        .unwrap();
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert_eq!(frame.missing_groups(), [TocGroupKind::LfGlobal]);
+    assert!(image.render_loading_frame().is_err());
+
+    image.feed_bytes(&data[split..]).unwrap();
+    assert!(image.is_loading_done());
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+}
+
+#[test]
+fn permuted_toc_regions() {
+    // Same image as `vardct_ramp_264x8.jxl`, with sections stored in the order of LfGroup,
+    // HfGlobal, LfGlobal, then pass groups 1 and 0. Unlike reversal, the permutation is not its
+    // own inverse.
+    let data = include_bytes!("synthetic/vardct_permuted_toc_264x8.jxl");
+    let expected = open(include_bytes!("synthetic/vardct_ramp_264x8.jxl"))
+        .render_frame(0)
+        .unwrap()
+        .image_all_channels();
+
+    let image = open(data);
+    let frame = image.frame_by_keyframe(0).unwrap();
+    let toc = frame.toc();
+    let groups = toc.iter_bitstream_order().collect::<Vec<_>>();
+    let kinds = groups.iter().map(|group| group.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TocGroupKind::LfGroup(0),
+            TocGroupKind::HfGlobal,
+            TocGroupKind::LfGlobal,
+            TocGroupKind::GroupPass {
+                pass_idx: 0,
+                group_idx: 1
+            },
+            TocGroupKind::GroupPass {
+                pass_idx: 0,
+                group_idx: 0
+            },
+        ]
+    );
+    for (idx, pair) in groups.windows(2).enumerate() {
+        assert_eq!(toc.group_index_bitstream_order(pair[0].kind), idx);
+        assert_eq!(pair[0].offset + pair[0].size as usize, pair[1].offset);
+    }
+    let actual = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(actual.buf(), expected.buf());
+
+    // Load everything but pass group 0, which is stored last.
+    let split = data.len() - groups.last().unwrap().size as usize;
+    let mut image = JxlImage::builder()
+        .read(std::io::Cursor::new(&data[..split]))
+        .unwrap();
+    let frame = image.frame_by_keyframe(0).unwrap();
+    assert_eq!(
+        frame.missing_groups(),
+        [TocGroupKind::GroupPass {
+            pass_idx: 0,
+            group_idx: 0
+        }]
+    );
+    assert!(frame.is_region_decoded((256, 0, 8, 8)));
+    assert!(!frame.is_region_decoded((248, 0, 16, 8)));
+
+    // Pass group 1 is decoded to its own region.
+    let crop = CropInfo {
+        width: 8,
+        height: 8,
+        left: 256,
+        top: 0,
+    };
+    image.set_image_region(crop);
+    let cropped = image
+        .render_loading_frame_cropped()
+        .unwrap()
+        .image_all_channels();
+    assert_eq!((cropped.width(), cropped.height()), (8, 8));
+    for (y, row) in cropped.buf_grouped::<3>().chunks_exact(8).enumerate() {
+        let full_row = &expected.buf_grouped::<3>()[y * expected.width()..][..expected.width()];
+        assert_eq!(row, &full_row[256..]);
+    }
+}
+
+#[test]
+fn vardct_interleaved_be() {
+    // Interleaved output of a lossy frame goes through the same path as Modular: XYB is converted
+    // to the image's color encoding (sRGB), and alpha is interleaved after color channels.
+    let image = open(include_bytes!("synthetic/vardct_alpha_512x512.jxl"));
+    let bits = image.image_header().metadata.bit_depth.bits_per_sample();
+    assert_eq!(bits, 8);
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    let bytes = fb.to_u16_bytes(bits, Endianness::Big);
+    assert_eq!(bytes.len(), 512 * 512 * 4 * 2);
+
+    // LF of Y is a horizontal ramp, with X and B zero; the first row is grey with alpha of 200.
+    let first_row = &bytes[..512 * 4 * 2];
+    assert_eq!(&first_row[..8], &[0, 1, 0, 1, 0, 1, 0, 200]);
+    let mut prev = 0;
+    for px in first_row.chunks_exact(8) {
+        let [r, g, b, a] = [0, 1, 2, 3].map(|c| u16::from_be_bytes([px[2 * c], px[2 * c + 1]]));
+        assert_eq!((r, g, b, a), (r, r, r, 200), "{px:?}");
+        assert!(r >= prev, "{px:?}");
+        prev = r;
+    }
+    assert!(prev > 1);
+}
+
+#[test]
+fn tone_mapping_metadata() {
+    let render_linear = |image: &mut JxlImage| {
+        image.request_color_encoding(srgb_linear());
+        let fb = image.render_frame(0).unwrap().image_all_channels();
+        fb.buf()[1]
+    };
+
+    // 12-bit PQ image with intensity_target = 4000, min_nits = 0.0625, and linear_below = 0.5
+    // relative to the display.
+    let mut image = open(include_bytes!("synthetic/pq_tone_mapping_8x8.jxl"));
+    let tone_mapping = &image.image_header().metadata.tone_mapping;
+    assert_eq!(tone_mapping.intensity_target, 4000.0);
+    assert_eq!(tone_mapping.min_nits, 0.0625);
+    assert!(tone_mapping.relative_to_max_display);
+    assert_eq!(tone_mapping.linear_below, 0.5);
+    assert_eq!(tone_mapping.linear_below_nits(255.0), 127.5);
+    let tone_mapped = render_linear(&mut image);
+
+    // Same image with linear_below = 4000 nits, which covers the whole luminance range.
+    let mut image = open(include_bytes!("synthetic/pq_linear_below_8x8.jxl"));
+    let tone_mapping = &image.image_header().metadata.tone_mapping;
+    assert!(!tone_mapping.relative_to_max_display);
+    assert_eq!(tone_mapping.linear_below_nits(255.0), 4000.0);
+    let linear = render_linear(&mut image);
+
+    // PQ EOTF, relative to intensity_target.
+    let e = (3000.0f64 / 4095.0).powf(1.0 / 78.84375);
+    let nits = ((e - 0.8359375).max(0.0) / (18.8515625 - 18.6875 * e)).powf(1.0 / 0.1593017578125)
+        * 10000.0;
+    let expected = (nits / 4000.0) as f32;
+    assert!((linear - expected).abs() < 1e-3, "{linear} != {expected}");
+
+    // Tone mapping brightens the image for the 255 nits SDR display.
+    assert!(tone_mapped > linear && tone_mapped <= 1.0, "{tone_mapped}");
+}
+
+#[test]
+fn hdr10_output() {
+    let mut image = open(include_bytes!("synthetic/modular_rgb.jxl"));
+    image.request_color_encoding(EnumColourEncoding::bt2100_pq(RenderingIntent::Relative));
+    let render = image.render_frame(0).unwrap();
+    let samples = render.image_all_channels().to_u16(10);
+
+    // sRGB to linear, then BT.709 primaries to BT.2020 primaries.
+    let linear = MODULAR_SAMPLES.map(|v| {
+        let v = v as f64 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let mat = [
+        [0.627404, 0.329283, 0.043313],
+        [0.069097, 0.919540, 0.011362],
+        [0.016391, 0.088013, 0.895595],
+    ];
+    let expected = mat.map(|row| {
+        let v: f64 = row.iter().zip(&linear).map(|(m, v)| m * v).sum();
+        // PQ inverse EOTF, with the default intensity target of 255 nits.
+        let y = (v * 255.0 / 10000.0).powf(0.1593017578125);
+        let pq = ((0.8359375 + 18.8515625 * y) / (1.0 + 18.6875 * y)).powf(78.84375);
+        (pq * 1023.0).round() as i32
+    });
+
+    for pixel in samples.chunks_exact(3) {
+        for (&v, &expected) in pixel.iter().zip(&expected) {
+            assert!(
+                (v as i32 - expected).abs() <= 1,
+                "{pixel:?} != {expected:?}"
+            );
+        }
+    }
+
+    // Linear BT.2100 image with intensity_target = 1000, where every sample is at the peak (4095
+    // of 12 bits). 1000 nits is 0.7518 in PQ, or 769 in 10 bits.
+    let mut image = open(include_bytes!("synthetic/linear_1000nits_8x8.jxl"));
+    let tone_mapping = &image.image_header().metadata.tone_mapping;
+    assert_eq!(tone_mapping.intensity_target, 1000.0);
+    image.request_color_encoding(EnumColourEncoding::bt2100_pq(RenderingIntent::Relative));
+    let samples = image
+        .render_frame(0)
+        .unwrap()
+        .image_all_channels()
+        .to_u16(10);
+    assert_eq!(samples.len(), 8 * 8 * 3);
+    for &v in &samples {
+        assert_eq!(v, 769);
+    }
+}
+
+#[test]
+fn u16_byte_order() {
+    let image = open(include_bytes!("synthetic/modular_16bit_8x8.jxl"));
+    assert_eq!(
+        image.image_header().metadata.bit_depth.bits_per_sample(),
+        16
+    );
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+
+    let be = fb.to_u16_bytes(16, Endianness::Big);
+    let le = fb.to_u16_bytes(16, Endianness::Little);
+    assert_eq!(be.len(), 8 * 8 * 3 * 2);
+    assert_eq!(&be[..2], &[0x12, 0x34]);
+    for (be, le) in be.chunks_exact(2).zip(le.chunks_exact(2)) {
+        assert_eq!([be[1], be[0]], le);
+    }
+
+    let native = fb.to_u16_bytes(16, Endianness::NATIVE);
+    let expected = fb
+        .to_u16(16)
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect::<Vec<_>>();
+    assert_eq!(native, expected);
+}
+
+#[test]
+fn extra_channel_name() {
+    let image = open(include_bytes!("synthetic/modular_named_ec_8x8.jxl"));
+    let ec_info = &image.image_header().metadata.ec_info;
+    assert_eq!(ec_info.len(), 1);
+    assert_eq!(&*ec_info[0].name, "Depth 深度 🌡");
+
+    let render = image.render_frame(0).unwrap();
+    let (info, buffers) = render.extra_channels();
+    assert_eq!(info.len(), 1);
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(info[0].name(), "Depth 深度 🌡");
+    assert_eq!(info[0].ty(), ExtraChannelType::Depth);
+    assert!(!info[0].is_alpha());
+}
+
+#[test]
+fn float_extra_channel() {
+    let image = open(include_bytes!("synthetic/modular_float_depth_8x8.jxl"));
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.extra_channels().0[0].ty(), ExtraChannelType::Depth);
+
+    // float16 0x3555, which is not representable in 8 or 16 bits integer samples.
+    let depth = render.image_channel(3).unwrap();
+    assert!(depth.buf().iter().all(|&v| v == 0.33325195));
+    assert!(render.image_channel_int(3).is_none());
+
+    let fb = render.image_all_channels();
+    assert!(fb.buf().chunks_exact(4).all(|px| px[3] == 0.33325195));
+
+    // Integer color channels are still available as is.
+    let red = render.image_channel_int(0).unwrap();
+    assert!(red.buf().iter().all(|&v| v == 128));
+}
+
+/// Collects messages of `tracing` events at `INFO` or more severe levels.
+#[derive(Default)]
+struct EventCollector {
+    messages: std::sync::Mutex<Vec<String>>,
+}
+
+struct MessageVisitor<'a>(&'a mut String);
+
+impl tracing::field::Visit for MessageVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            *self.0 = format!("{value:?}");
+        }
+    }
+}
+
+impl tracing::Subscriber for EventCollector {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        *metadata.level() <= tracing::Level::INFO
+    }
+
+    fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut message = String::new();
+        event.record(&mut MessageVisitor(&mut message));
+        self.messages.lock().unwrap().push(message);
+    }
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[test]
+fn squeeze_crop_fallback_is_traced() {
+    let data = include_bytes!("synthetic/modular_squeeze_8x8.jxl");
+    let mut image = JxlImage::builder()
+        .pool(JxlThreadPool::none())
+        .read(std::io::Cursor::new(data))
+        .unwrap();
+    let expected = image.render_frame(0).unwrap().image_all_channels();
+
+    image.set_image_region(CropInfo {
+        width: 4,
+        height: 4,
+        left: 4,
+        top: 4,
+    });
+    image.reset_render_cache();
+    let collector = std::sync::Arc::new(EventCollector::default());
+    let cropped = tracing::subscriber::with_default(collector.clone(), || {
+        image.render_frame_cropped(0).unwrap().image_all_channels()
+    });
+
+    let messages = collector.messages.lock().unwrap();
+    assert!(messages
+        .iter()
+        .any(|m| m == "GlobalModular has squeeze, decoding from top-left"));
+
+    // Decoding itself is not affected.
+    for (y, row) in cropped.buf_grouped::<3>().chunks_exact(4).enumerate() {
+        let expected_row = &expected.buf_grouped::<3>()[(y + 4) * 8 + 4..][..4];
+        assert_eq!(row, expected_row);
+    }
+}
+
+#[test]
+fn decoded_region() {
+    let decoded_region = |data: &[u8], crop: CropInfo| {
+        let mut image = open(data);
+        image.set_image_region(crop);
+        let decoded = image.render_frame(0).unwrap().decoded_region().unwrap();
+        let region = decoded.region;
+        (
+            (region.left, region.top, region.width, region.height),
+            decoded.full_decode,
+        )
+    };
+    let crop = CropInfo {
+        width: 4,
+        height: 4,
+        left: 4,
+        top: 2,
+    };
+
+    // Squeeze widens the region to the whole frame.
+    let data = include_bytes!("synthetic/modular_squeeze_8x8.jxl");
+    assert_eq!(decoded_region(data, crop), ((0, 0, 8, 8), true));
+
+    // Frames without such transforms or filters are decoded only in the requested region. Frames
+    // below are not upsampled and are placed at the origin, so frame coordinates are the same as
+    // image coordinates.
+    let data = include_bytes!("synthetic/modular_rgb.jxl");
+    assert_eq!(decoded_region(data, crop), ((4, 2, 4, 4), false));
+
+    // Restoration filters need padding, and VarDCT frames are decoded by groups.
+    let data = include_bytes!("synthetic/vardct_500x300.jxl");
+    let image = open(data);
+    let filter = &image.frame_header(0).unwrap().restoration_filter;
+    assert!(filter.epf.enabled() || filter.gab.enabled());
+    let crop = CropInfo {
+        width: 20,
+        height: 20,
+        left: 250,
+        top: 120,
+    };
+    let ((left, top, width, height), full_decode) = decoded_region(data, crop);
+    assert!(!full_decode);
+    // The padded crop spans the first two 256x256 groups horizontally, clipped to the frame
+    // size rounded up to 8x8 blocks.
+    assert_eq!((left, top, width, height), (0, 0, 504, 256));
+}
+
+/// Upsamples the grid by 2 with the given weights, as specified in the JPEG XL specification.
+fn upsample2(buf: &[f32], width: usize, height: usize, weights: &[f32; 15]) -> Vec<f32> {
+    let mut kernel = [0f32; 25];
+    let mut weights = weights.iter();
+    for y in 0..5 {
+        for x in y..5 {
+            let w = *weights.next().unwrap();
+            kernel[y * 5 + x] = w;
+            kernel[x * 5 + y] = w;
+        }
+    }
+
+    let mirror = |v: isize, size: usize| -> usize {
+        let size = size as isize;
+        let v = if v < 0 { -v - 1 } else { v };
+        (if v >= size { 2 * size - v - 1 } else { v }) as usize
+    };
+    let mut out = vec![0f32; width * height * 4];
+    for y in 0..height * 2 {
+        for x in 0..width * 2 {
+            let (flip_h, flip_v) = (x % 2 == 1, y % 2 == 1);
+            let mut sum = 0f32;
+            let mut min = f32::INFINITY;
+            let mut max = f32::NEG_INFINITY;
+            for iy in 0..5 {
+                let ky = if flip_v { 4 - iy } else { iy };
+                let sy = mirror((y / 2) as isize + iy as isize - 2, height);
+                for ix in 0..5 {
+                    let kx = if flip_h { 4 - ix } else { ix };
+                    let sx = mirror((x / 2) as isize + ix as isize - 2, width);
+                    let sample = buf[sy * width + sx];
+                    sum += kernel[ky * 5 + kx] * sample;
+                    min = min.min(sample);
+                    max = max.max(sample);
+                }
+            }
+            out[y * width * 2 + x] = sum.clamp(min, max);
+        }
+    }
+    out
+}
+
+#[test]
+fn epf_before_upsampling() {
+    // Both images have the same coded data with Gabor-like filter and EPF enabled, only the
+    // upsampling factor differs.
+    let render_xyb = |data: &[u8]| {
+        let mut image = open(data);
+        image.request_color_encoding(EnumColourEncoding::xyb(RenderingIntent::Relative));
+        image.render_frame(0).unwrap()
+    };
+    let image = open(include_bytes!("synthetic/vardct_upsampling2_1000x600.jxl"));
+    let restoration_filter = &image.frame_header(0).unwrap().restoration_filter;
+    assert!(restoration_filter.epf.enabled());
+    let weights = image.image_header().metadata.up2_weight;
+
+    let coded = render_xyb(include_bytes!("synthetic/vardct_500x300.jxl"));
+    let upsampled = render_xyb(include_bytes!("synthetic/vardct_upsampling2_1000x600.jxl"));
+
+    // Restoration filters run on the coded resolution with unscaled sigma, then the result is
+    // upsampled.
+    for c in 0..3 {
+        let coded = coded.image_channel(c).unwrap();
+        let upsampled = upsampled.image_channel(c).unwrap();
+        assert_eq!((upsampled.width(), upsampled.height()), (1000, 600));
+
+        let expected = upsample2(coded.buf(), 500, 300, &weights);
+        for (idx, (&actual, &expected)) in upsampled.buf().iter().zip(&expected).enumerate() {
+            assert!(
+                (actual - expected).abs() < 1e-5,
+                "channel {c} at ({}, {}): {actual} != {expected}",
+                idx % 1000,
+                idx / 1000,
+            );
+        }
+    }
+}
+
+#[test]
+fn animation_scratch_reuse() {
+    let data = include_bytes!("synthetic/animation_vardct_500x300.jxl");
+    let image = open(data);
+    assert_eq!(image.num_loaded_keyframes(), 3);
+
+    // Rendering frames in sequence reuses restoration filter buffers of previous frames; the
+    // output must not depend on what was left in them.
+    let mut prev: Option<Vec<f32>> = None;
+    for idx in 0..3 {
+        let render = image.render_frame(idx).unwrap();
+        assert_eq!(render.duration(), 10);
+        let actual = render.image_all_channels();
+
+        let fresh = open(data).render_frame(idx).unwrap().image_all_channels();
+        assert_eq!(actual.buf(), fresh.buf(), "frame {idx}");
+
+        if let Some(prev) = prev {
+            assert_ne!(prev, actual.buf(), "frame {idx}");
+        }
+        prev = Some(actual.buf().to_vec());
+    }
+}
+
+#[test]
+fn frame_duration_until_user_input() {
+    let image = open(include_bytes!("synthetic/animation_vardct_500x300.jxl"));
+    let render = image.render_frame(0).unwrap();
+    assert_eq!(render.frame_duration(), FrameDuration::Ticks(10));
+
+    // Pages of a multi-page image are presented until user input.
+    let image = open(include_bytes!("synthetic/animation_pages_264x8.jxl"));
+    assert_eq!(image.num_loaded_keyframes(), 2);
+    for idx in 0..2 {
+        let header = image.frame_header(idx).unwrap();
+        assert_eq!(header.frame_duration(), FrameDuration::UntilUserInput);
+        let render = image.render_frame(idx).unwrap();
+        assert_eq!(render.duration(), u32::MAX);
+        assert_eq!(render.frame_duration(), FrameDuration::UntilUserInput);
+    }
+}
+
+#[test]
+fn xyb_passthrough() {
+    let data = include_bytes!("synthetic/vardct_500x300.jxl");
+    let mut image = open(data);
+    image.request_color_encoding(EnumColourEncoding::xyb(RenderingIntent::Relative));
+    let xyb = image.render_frame(0).unwrap().image_all_channels();
+    let srgb = open(data).render_frame(0).unwrap().image_all_channels();
+    assert_eq!(xyb.channels(), 3);
+
+    // The image is gray, so X is zero and B equals Y. Samples are not converted to RGB.
+    let mut max_y = 0f32;
+    for (&[x, y, b], &[r, g, _]) in xyb.buf_grouped::<3>().iter().zip(srgb.buf_grouped::<3>()) {
+        assert!(x.abs() < 1e-6, "X = {x}");
+        assert!((b - y).abs() < 1e-6, "B - Y = {}", b - y);
+        assert!((r - g).abs() < 1e-5);
+        max_y = max_y.max(y);
+    }
+    assert!(max_y > 0.5);
+}
+
+#[test]
+fn xyb_hook() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let data = include_bytes!("synthetic/vardct_500x300.jxl");
+    let mut image = open(data);
+    image.request_color_encoding(EnumColourEncoding::xyb(RenderingIntent::Relative));
+    let xyb = image.render_frame(0).unwrap().image_all_channels();
+
+    // Brighten the gray image by adding a constant to Y, and to B so that it stays gray.
+    const DELTA: f32 = 0.05;
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut image = JxlImage::builder()
+        .xyb_hook({
+            let calls = Arc::clone(&calls);
+            move |[_, y, b]| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                for v in y.buf_mut().iter_mut().chain(b.buf_mut()) {
+                    *v += DELTA;
+                }
+            }
+        })
+        .read(std::io::Cursor::new(data))
+        .unwrap();
+    image.request_color_encoding(srgb_linear());
+    let linear = image.render_frame(0).unwrap().image_all_channels();
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+    // Inverse opsin of gray pixels with the default matrix and bias.
+    let bias = -0.0037930732f32;
+    let to_linear = |y: f32| (y - bias.cbrt()).powi(3) + bias;
+    for (&[_, y, _], &[r, g, b]) in xyb.buf_grouped::<3>().iter().zip(linear.buf_grouped::<3>()) {
+        let expected = to_linear(y + DELTA);
+        for v in [r, g, b] {
+            assert!((v - expected).abs() < 1e-4, "{v} != {expected}");
+        }
+        assert!(expected > to_linear(y));
+    }
+
+    // Images not encoded in XYB don't call the hook.
+    let image = JxlImage::builder()
+        .xyb_hook(|_| panic!("hook called"))
+        .read(std::io::Cursor::new(include_bytes!(
+            "synthetic/modular_rgb.jxl"
+        )))
+        .unwrap();
+    image.render_frame(0).unwrap();
+}
+
+#[test]
+fn xyb_hook_animation() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // Every frame but the last is converted from XYB before blending, so the hook should run
+    // while rendering each frame, not when the keyframe is post-processed.
+    for data in [
+        &include_bytes!("synthetic/animation_vardct_500x300.jxl")[..],
+        &include_bytes!("synthetic/animation_blend_264x8.jxl")[..],
+    ] {
+        let expected = open(data);
+        assert!(expected.image_header().metadata.xyb_encoded);
+        let num_keyframes = expected.num_loaded_keyframes();
+        assert!(num_keyframes > 1);
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let image = JxlImage::builder()
+            .pool(JxlThreadPool::none())
+            .xyb_hook({
+                let calls = Arc::clone(&calls);
+                move |[_, y, _]| {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    for v in y.buf_mut() {
+                        *v += 0.05;
+                    }
+                }
+            })
+            .read(std::io::Cursor::new(data))
+            .unwrap();
+        for keyframe_idx in 0..num_keyframes {
+            let before = calls.load(Ordering::Relaxed);
+            let hooked = image
+                .render_frame(keyframe_idx)
+                .unwrap()
+                .image_all_channels();
+            assert_eq!(calls.load(Ordering::Relaxed) - before, 1, "{keyframe_idx}");
+
+            let fb = expected
+                .render_frame(keyframe_idx)
+                .unwrap()
+                .image_all_channels();
+            assert_ne!(hooked.buf(), fb.buf(), "{keyframe_idx}");
+        }
+
+        // Rendered frames are cached, and the hook isn't called again.
+        image.render_frame(0).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), num_keyframes);
+    }
+}
+
+#[test]
+fn patch_alpha_channel() {
+    // Extra channels are alpha, alpha and depth. Every channel of the patch is blended with
+    // BlendAbove, using extra channel 2 (depth) or 3 (nonexistent) as alpha.
+    for (data, msg) in [
+        (
+            &include_bytes!("synthetic/patch_alpha_not_alpha_8x8.jxl")[..],
+            "patch alpha channel is not the type of Alpha",
+        ),
+        (
+            &include_bytes!("synthetic/patch_alpha_out_of_range_8x8.jxl")[..],
+            "patch alpha channel index out of bounds",
+        ),
+    ] {
+        let err = open(data).render_frame(0).unwrap_err();
+        let err = err
+            .downcast_ref::<jxl_render::Error>()
+            .unwrap_or_else(|| panic!("{err}"));
+        assert!(
+            matches!(
+                err,
+                jxl_render::Error::Frame(jxl_frame::Error::InconsistentMetadata(m)) if *m == msg
+            ),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn patch_from_off_canvas_reference() {
+    // Reference-only frame is 16x8 on a 8x8 canvas with samples `x + y + 1`; the visible frame
+    // copies the 8x8 region at (8, 0) of it, which lies entirely outside the canvas.
+    let mut image = open(include_bytes!("synthetic/patch_off_canvas_8x8.jxl"));
+    assert_eq!(image.num_loaded_keyframes(), 1);
+    let reference = image.frame(0).unwrap().header();
+    assert_eq!((reference.width, reference.height), (16, 8));
+    assert_eq!(reference.save_as_reference, 1);
+
+    let expected = |x: u32, y: u32| (x + 8 + y + 1) as f32 / 255.0;
+    let fb = image.render_frame(0).unwrap().image_all_channels();
+    for (idx, pixel) in fb.buf_grouped::<3>().iter().enumerate() {
+        let (x, y) = ((idx % 8) as u32, (idx / 8) as u32);
+        assert!((pixel[0] - expected(x, y)).abs() < 1e-6, "x={x}, y={y}");
+        assert_eq!(pixel[0], pixel[1]);
+        assert_eq!(pixel[0], pixel[2]);
+    }
+
+    // Cropping the output doesn't crop the reference frame.
+    let crop = CropInfo {
+        width: 3,
+        height: 2,
+        left: 5,
+        top: 6,
+    };
+    image.set_image_region(crop);
+    let fb = image.render_frame_cropped(0).unwrap().image_all_channels();
+    assert_eq!((fb.width(), fb.height()), (3, 2));
+    for (idx, pixel) in fb.buf_grouped::<3>().iter().enumerate() {
+        let x = crop.left + (idx % 3) as u32;
+        let y = crop.top + (idx / 3) as u32;
+        assert!((pixel[0] - expected(x, y)).abs() < 1e-6, "x={x}, y={y}");
+    }
+}
+
+#[test]
+fn batch_decode() {
+    let files: [&[u8]; 4] = [
+        include_bytes!("synthetic/modular_rgb.jxl"),
+        include_bytes!("synthetic/modular_use_lf_frame.jxl"),
+        include_bytes!("synthetic/vardct_500x300.jxl"),
+        include_bytes!("synthetic/blocks_300x260.jxl"),
+    ];
+    let results = decode_batch(&files);
+    assert_eq!(results.len(), files.len());
+
+    // The invalid file fails without affecting others.
+    let err = results[1].as_ref().unwrap_err();
+    assert!(
+        err.to_string().contains("inconsistent frame header"),
+        "{err}"
+    );
+    for idx in [0, 2, 3] {
+        let render = results[idx].as_ref().unwrap();
+        let expected = open(files[idx]).render_frame(0).unwrap();
+        assert_eq!(
+            render.image_all_channels().buf(),
+            expected.image_all_channels().buf(),
+            "file {idx}"
+        );
+    }
+    assert!(decode_batch(&[]).is_empty());
+}
+
+/// Snapshot tests comparing renders of synthetic images against `.npy` files next to them.
+///
+/// Snapshots hold interleaved `f32` samples in the color space of the image. They are computed
+/// from the known contents of the synthetic images, not decoded with libjxl, so these are
+/// regression tests rather than conformance tests.
+mod snapshot {
+    use super::*;
+
+    /// Tolerance of a snapshot test.
+    #[derive(Debug, Copy, Clone)]
+    enum Tolerance {
+        /// Every sample must be equal to the snapshot. Used for lossless images.
+        Exact,
+        /// PSNR over all samples, with the peak value of 1.0, must be at least the given value in
+        /// dB. Used for lossy images.
+        MinPsnr(f64),
+    }
+
+    fn run_snapshot_test(jxl: &[u8], npy: &[u8], channels: usize, tolerance: Tolerance) {
+        let image = JxlImage::builder()
+            .read(std::io::Cursor::new(jxl))
+            .expect("failed to open image");
+        let expected = util::read_numpy(npy, 1, channels);
+        let fb = image.render_frame(0).unwrap().image_all_channels();
+        assert_eq!(fb.channels(), channels);
+        let actual = fb.buf();
+        let expected = &expected[0];
+        assert_eq!(actual.len(), expected.len());
+
+        match tolerance {
+            Tolerance::Exact => {
+                for (idx, (&output, &reference)) in actual.iter().zip(expected).enumerate() {
+                    assert_eq!(output, reference, "sample #{idx} differs");
+                }
+            }
+            Tolerance::MinPsnr(min_psnr) => {
+                let sum_se = actual
+                    .iter()
+                    .zip(expected)
+                    .map(|(&output, &reference)| (output as f64 - reference as f64).powi(2))
+                    .sum::<f64>();
+                let mse = sum_se / actual.len() as f64;
+                let psnr = -10.0 * mse.log10();
+                eprintln!("PSNR = {psnr} dB");
+                assert!(
+                    psnr >= min_psnr,
+                    "PSNR {psnr} dB is lower than {min_psnr} dB"
+                );
+            }
+        }
+    }
+
+    macro_rules! snapshot_test {
+        ($($name:ident ($channels:literal, $tolerance:expr)),* $(,)?) => {
+            $(
+                #[test]
+                fn $name() {
+                    run_snapshot_test(
+                        include_bytes!(concat!("synthetic/", stringify!($name), ".jxl")),
+                        include_bytes!(concat!("synthetic/", stringify!($name), ".npy")),
+                        $channels,
+                        $tolerance,
+                    );
+                }
+            )*
+        };
+    }
+
+    snapshot_test! {
+        // Every pixel is (100, 20, 30) / 255.
+        modular_rgb(3, Tolerance::Exact),
+        // Constant LF with zero HF, converted with the default opsin inverse matrix and the sRGB
+        // transfer function.
+        vardct_solid_264x8(3, Tolerance::MinPsnr(40.0)),
+    }
+}
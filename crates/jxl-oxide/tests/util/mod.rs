@@ -7,3 +7,32 @@ pub fn conformance_path(name: &str) -> std::path::PathBuf {
     path.push("input.jxl");
     path
 }
+
+pub fn read_numpy(mut r: impl std::io::Read, frames: usize, channels: usize) -> Vec<Vec<f32>> {
+    let mut magic = [0u8; 6];
+    let mut version = [0u8; 2];
+    let mut meta_len = [0u8; 2];
+
+    r.read_exact(&mut magic).unwrap();
+    r.read_exact(&mut version).unwrap();
+    r.read_exact(&mut meta_len).unwrap();
+    assert_eq!(&magic, b"\x93NUMPY");
+
+    let meta_len = u16::from_le_bytes(meta_len) as usize;
+    let mut meta = vec![0u8; meta_len];
+    r.read_exact(&mut meta).unwrap();
+
+    let mut fb = Vec::new();
+    let mut buf = vec![0u8; channels * 4];
+    while r.read_exact(&mut buf).is_ok() {
+        let mut val = [0u8; 4];
+        for c in buf.chunks_exact(4) {
+            val.copy_from_slice(c);
+            let x = u32::from_le_bytes(val);
+            fb.push(f32::from_bits(x));
+        }
+    }
+
+    let chunk_size = fb.len() / frames;
+    fb.chunks_exact(chunk_size).map(|b| b.to_vec()).collect()
+}
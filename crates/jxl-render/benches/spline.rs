@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jxl_frame::data::{QuantSpline, Splines};
+use jxl_threadpool::JxlThreadPool;
+
+fn spline(c: &mut Criterion) {
+    // Wide horizontal spline with large sigma, so that most of the time is spent on evaluating
+    // Gaussian falloff.
+    let mut xyb_dct = [[0; 32]; 3];
+    xyb_dct[1][0] = 10;
+    let mut sigma_dct = [0; 32];
+    sigma_dct[0] = 40;
+    let splines = Splines {
+        quant_splines: vec![QuantSpline {
+            quant_points: vec![(16, 128), (1008, 128)],
+            manhattan_distance: 992,
+            xyb_dct,
+            sigma_dct,
+        }],
+        quant_adjust: 0,
+    };
+
+    let pool = JxlThreadPool::none();
+    let mut g = c.benchmark_group("spline");
+    g.throughput(criterion::Throughput::Elements(1024 * 256));
+    g.bench_function("wide", |b| {
+        b.iter_with_large_drop(|| {
+            jxl_render::render_spline_layer(1024, 256, black_box(&splines), None, None, &pool)
+                .unwrap()
+        })
+    });
+
+    // Long zigzag spline with narrow width and all coefficients set, so that most of the time is
+    // spent on evaluating continuous IDCT of samples.
+    let xyb_dct = [[3; 32], [10; 32], [-2; 32]];
+    let sigma_dct = std::array::from_fn(|i| if i == 0 { 4 } else { (i % 3) as i32 - 1 });
+    let quant_points = (0..64)
+        .map(|i| (16 + i * 15, if i % 2 == 0 { 32 } else { 224 }))
+        .collect::<Vec<(i64, i64)>>();
+    let manhattan_distance = quant_points
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0).unsigned_abs() + (w[1].1 - w[0].1).unsigned_abs())
+        .sum();
+    let splines = Splines {
+        quant_splines: vec![QuantSpline {
+            quant_points,
+            manhattan_distance,
+            xyb_dct,
+            sigma_dct,
+        }],
+        quant_adjust: 0,
+    };
+    g.bench_function("long_narrow", |b| {
+        b.iter_with_large_drop(|| {
+            jxl_render::render_spline_layer(1024, 256, black_box(&splines), None, None, &pool)
+                .unwrap()
+        })
+    });
+    g.finish();
+}
+
+criterion_group!(benches, spline);
+criterion_main!(benches);
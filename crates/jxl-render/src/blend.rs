@@ -214,6 +214,9 @@ pub(crate) fn blend<S: Sample>(
     let color_channels = new_grid.color_channels();
     let mut output_grid = ImageWithRegion::new(color_channels, tracker);
     output_grid.set_ct_done(new_grid.ct_done());
+    if let Some(decoded_region) = new_grid.decoded_region() {
+        output_grid.set_decoded_region(decoded_region);
+    }
 
     for (idx, blending_info) in std::iter::repeat(&header.blending_info)
         .take(color_channels)
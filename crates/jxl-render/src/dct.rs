@@ -0,0 +1,2 @@
+//! Constants used in DCT of VarDCT frames.
+pub use crate::vardct::dct_common::{scale_f, SCALE_F};
@@ -3,7 +3,6 @@
 pub enum Error {
     Bitstream(jxl_bitstream::Error),
     Decoder(jxl_coding::Error),
-    Buffer(jxl_grid::Error),
     Modular(jxl_modular::Error),
     Frame(jxl_frame::Error),
     Color(jxl_color::Error),
@@ -13,6 +12,10 @@ pub enum Error {
     InvalidReference(u32),
     NotReady,
     NotSupported(&'static str),
+    Cancelled,
+    MemoryBudgetExceeded(usize),
+    ChannelCountMismatch { expected: usize, actual: usize },
+    InvalidSpline(&'static str),
 }
 
 impl From<jxl_bitstream::Error> for Error {
@@ -29,22 +32,43 @@ impl From<jxl_coding::Error> for Error {
 
 impl From<jxl_grid::Error> for Error {
     fn from(err: jxl_grid::Error) -> Self {
-        Self::Buffer(err)
+        match err {
+            jxl_grid::Error::OutOfMemory(bytes) => Self::MemoryBudgetExceeded(bytes),
+        }
     }
 }
 
 impl From<jxl_modular::Error> for Error {
     fn from(err: jxl_modular::Error) -> Self {
+        if let Some(bytes) = exceeded_memory_budget(&err) {
+            return Self::MemoryBudgetExceeded(bytes);
+        }
         Self::Modular(err)
     }
 }
 
 impl From<jxl_frame::Error> for Error {
     fn from(err: jxl_frame::Error) -> Self {
+        if let Some(bytes) = exceeded_memory_budget(&err) {
+            return Self::MemoryBudgetExceeded(bytes);
+        }
         Self::Frame(err)
     }
 }
 
+/// Returns the size of failed allocation if `err` is caused by exceeding the limit of the
+/// allocation tracker.
+fn exceeded_memory_budget(err: &(dyn std::error::Error + 'static)) -> Option<usize> {
+    let mut err = Some(err);
+    while let Some(e) = err {
+        if let Some(jxl_grid::Error::OutOfMemory(bytes)) = e.downcast_ref::<jxl_grid::Error>() {
+            return Some(*bytes);
+        }
+        err = e.source();
+    }
+    None
+}
+
 impl From<jxl_color::Error> for Error {
     fn from(err: jxl_color::Error) -> Self {
         Self::Color(err)
@@ -58,7 +82,6 @@ impl std::fmt::Display for Error {
         match self {
             Bitstream(err) => write!(f, "bitstream error: {}", err),
             Decoder(err) => write!(f, "entropy decoder error: {}", err),
-            Buffer(err) => write!(f, "{}", err),
             Modular(err) => write!(f, "modular subimage decode error: {}", err),
             Frame(err) => write!(f, "frame error: {}", err),
             Color(err) => write!(f, "color management error: {err}"),
@@ -70,6 +93,17 @@ impl std::fmt::Display for Error {
             InvalidReference(idx) => write!(f, "invalid reference {idx}"),
             NotReady => write!(f, "image is not ready to be rendered"),
             NotSupported(msg) => write!(f, "not supported: {}", msg),
+            Cancelled => write!(f, "decoding was cancelled"),
+            MemoryBudgetExceeded(bytes) => {
+                write!(f, "memory budget exceeded while allocating {bytes} byte(s)")
+            }
+            ChannelCountMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "channel count mismatch: expected {expected}, got {actual}"
+                )
+            }
+            InvalidSpline(msg) => write!(f, "invalid spline: {}", msg),
         }
     }
 }
@@ -81,7 +115,6 @@ impl std::error::Error for Error {
         match self {
             Bitstream(err) => Some(err),
             Decoder(err) => Some(err),
-            Buffer(err) => Some(err),
             Modular(err) => Some(err),
             Frame(err) => Some(err),
             Color(err) => Some(err),
@@ -4,6 +4,6 @@ mod spot_colors;
 mod upsampling;
 
 pub use noise::render_noise;
-pub use spline::render_spline;
+pub use spline::{render_dequantized_spline_layer, render_spline, render_spline_layer, Spline};
 pub use spot_colors::render_spot_color;
 pub use upsampling::upsample;
@@ -1,15 +1,20 @@
-use std::ops::{Add, Mul, Sub};
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
 use jxl_frame::{
     data::{QuantSpline, Splines},
     FrameHeader,
 };
+use jxl_grid::{AlignedGrid, AllocTracker, MutableSubgrid};
+use jxl_threadpool::JxlThreadPool;
 
-use crate::ImageWithRegion;
+use crate::{ImageWithRegion, Region};
 
 /// Holds control point coordinates and dequantized DCT32 coefficients of XYB channels, σ parameter of the spline
-#[derive(Debug)]
-struct Spline {
+///
+/// `Display` writes the spline in jxl_from_tree syntax, which can be read back with
+/// [`Spline::parse_from_tree`].
+#[derive(Debug, Clone)]
+pub struct Spline {
     points: Vec<Point>,
     xyb_dct: [[f32; 32]; 3],
     sigma_dct: [f32; 32],
@@ -38,7 +43,8 @@ struct SplineArc {
 }
 
 impl Spline {
-    fn dequant(
+    /// Dequantizes a spline decoded from the bitstream.
+    pub fn dequant(
         quant_spline: &QuantSpline,
         quant_adjust: i32,
         base_correlations_xb: Option<(f32, f32)>,
@@ -49,39 +55,100 @@ impl Spline {
             .map(|&(x, y)| Point::new(x as f32, y as f32))
             .collect();
 
-        let mut xyb_dct = [[0f32; 32]; 3];
-        let mut sigma_dct = [0f32; 32];
+        let (xyb_dct, sigma_dct) = quant_spline.dequant_dct(quant_adjust, base_correlations_xb);
 
-        let quant_adjust = quant_adjust as f32;
-        let inverted_qa = if quant_adjust >= 0.0 {
-            1.0 / (1.0 + quant_adjust / 8.0)
-        } else {
-            1.0 - quant_adjust / 8.0
-        };
+        Spline {
+            points,
+            xyb_dct,
+            sigma_dct,
+        }
+    }
 
-        const CHANNEL_WEIGHTS: [f32; 4] = [0.0042, 0.075, 0.07, 0.3333];
-        for chan_idx in 0..3 {
-            for i in 0..32 {
-                xyb_dct[chan_idx][i] = quant_spline.xyb_dct[chan_idx][i] as f32
-                    * CHANNEL_WEIGHTS[chan_idx]
-                    * inverted_qa;
-            }
+    /// Parses a spline in jxl_from_tree syntax, as written by the `Display` impl.
+    ///
+    /// Returns [`Error::InvalidSpline`](crate::Error::InvalidSpline) if the input is malformed.
+    pub fn parse_from_tree(s: &str) -> crate::Result<Self> {
+        let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next() != Some("Spline") {
+            return Err(crate::Error::InvalidSpline("expected `Spline`"));
         }
-        let (corr_x, corr_b) = base_correlations_xb.unwrap_or((0.0, 1.0));
-        for i in 0..32 {
-            xyb_dct[0][i] += corr_x * xyb_dct[1][i];
-            xyb_dct[2][i] += corr_b * xyb_dct[1][i];
+
+        let mut dct = [[0f32; 32]; 4];
+        for (row, coeffs) in dct.iter_mut().enumerate() {
+            let line = lines.next().ok_or_else(|| {
+                tracing::error!(row, "Missing spline coefficients");
+                crate::Error::InvalidSpline("missing coefficients")
+            })?;
+            let mut count = 0usize;
+            for v in line.split_whitespace() {
+                let value = v.parse::<f32>().map_err(|e| {
+                    tracing::error!(row, value = v, %e, "Invalid spline coefficient");
+                    crate::Error::InvalidSpline("invalid coefficient")
+                })?;
+                if let Some(coeff) = coeffs.get_mut(count) {
+                    *coeff = value;
+                }
+                count += 1;
+            }
+            if count != coeffs.len() {
+                tracing::error!(row, count, "Spline coefficient count mismatch");
+                return Err(crate::Error::InvalidSpline(
+                    "expected 32 coefficients per row",
+                ));
+            }
         }
 
-        for (sigma_dct, quant_sigma_dct) in sigma_dct.iter_mut().zip(quant_spline.sigma_dct) {
-            *sigma_dct = quant_sigma_dct as f32 * CHANNEL_WEIGHTS[3] * inverted_qa;
+        let mut points = Vec::new();
+        loop {
+            let line = lines
+                .next()
+                .ok_or(crate::Error::InvalidSpline("missing `EndSpline`"))?;
+            if line == "EndSpline" {
+                break;
+            }
+            let coords = line
+                .split_whitespace()
+                .map(|v| {
+                    v.parse::<i32>().map_err(|e| {
+                        tracing::error!(value = v, %e, "Invalid spline control point");
+                        crate::Error::InvalidSpline("invalid coordinate")
+                    })
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            let &[x, y] = &coords[..] else {
+                tracing::error!(line, "Invalid spline control point");
+                return Err(crate::Error::InvalidSpline(
+                    "expected two coordinates per control point",
+                ));
+            };
+            points.push(Point::new(x as f32, y as f32));
+        }
+        if let Some(line) = lines.next() {
+            tracing::error!(line, "Trailing data after spline");
+            return Err(crate::Error::InvalidSpline(
+                "unexpected data after `EndSpline`",
+            ));
+        }
+        if points.is_empty() {
+            return Err(crate::Error::InvalidSpline("spline has no control points"));
         }
 
-        Spline {
+        let [x, y, b, sigma_dct] = dct;
+        Ok(Self {
             points,
-            xyb_dct,
+            xyb_dct: [x, y, b],
             sigma_dct,
-        }
+        })
+    }
+
+    fn dequant_all(splines: &Splines, base_correlations_xb: Option<(f32, f32)>) -> Vec<Self> {
+        splines
+            .quant_splines
+            .iter()
+            .map(|quant_spline| {
+                Self::dequant(quant_spline, splines.quant_adjust, base_correlations_xb)
+            })
+            .collect()
     }
 
     fn get_samples(&self) -> Vec<SplineArc> {
@@ -180,25 +247,99 @@ pub fn render_spline(
     base_grid: &mut ImageWithRegion,
     splines: &Splines,
     base_correlations_xb: Option<(f32, f32)>,
+    pool: &JxlThreadPool,
 ) -> crate::Result<()> {
     let region = base_grid.regions_and_shifts()[0].0;
+    let splines = Spline::dequant_all(splines, base_correlations_xb);
+    draw_splines(
+        (frame_header.width, frame_header.height),
+        base_grid.as_color_floats_mut(),
+        region,
+        &splines,
+        pool,
+    );
+    Ok(())
+}
+
+/// Renders splines on a zero background of the given frame size.
+///
+/// Returns three grids of color channels, without color transform applied.
+pub fn render_spline_layer(
+    width: u32,
+    height: u32,
+    splines: &Splines,
+    base_correlations_xb: Option<(f32, f32)>,
+    tracker: Option<&AllocTracker>,
+    pool: &JxlThreadPool,
+) -> crate::Result<Vec<AlignedGrid<f32>>> {
+    let splines = Spline::dequant_all(splines, base_correlations_xb);
+    render_dequantized_spline_layer(width, height, &splines, tracker, pool)
+}
 
-    for quant_spline in &splines.quant_splines {
-        let spline = Spline::dequant(quant_spline, splines.quant_adjust, base_correlations_xb);
-        tracing::trace!("{}", spline);
+/// Renders dequantized splines, such as those parsed with [`Spline::parse_from_tree`], on a zero
+/// background of the given frame size.
+///
+/// Returns three grids of color channels, without color transform applied.
+pub fn render_dequantized_spline_layer(
+    width: u32,
+    height: u32,
+    splines: &[Spline],
+    tracker: Option<&AllocTracker>,
+    pool: &JxlThreadPool,
+) -> crate::Result<Vec<AlignedGrid<f32>>> {
+    let mut layer = Vec::with_capacity(3);
+    for _ in 0..3 {
+        layer.push(AlignedGrid::with_alloc_tracker(
+            width as usize,
+            height as usize,
+            tracker,
+        )?);
+    }
+
+    let [x, y, b] = &mut *layer else {
+        unreachable!()
+    };
+    draw_splines(
+        (width, height),
+        [x, y, b],
+        Region::with_size(width, height),
+        splines,
+        pool,
+    );
+    Ok(layer)
+}
 
-        let all_samples = spline.get_samples();
-        let arclength = all_samples.len() as f32 - 2.0 + all_samples.last().unwrap().length;
-        for (i, arc) in all_samples.iter().enumerate() {
+/// Sample of a spline to be drawn, with the bounding box of its contribution.
+#[derive(Debug)]
+struct SplineSample {
+    point: Point,
+    values: [f32; 3],
+    sigma: f32,
+    inv_sigma: f32,
+    x_range: std::ops::Range<i32>,
+    y_range: std::ops::Range<i32>,
+}
+
+fn compute_samples((frame_width, frame_height): (u32, u32), spline: &Spline) -> Vec<SplineSample> {
+    tracing::trace!("{}", spline);
+
+    let all_samples = spline.get_samples();
+    let arclength = all_samples.len() as f32 - 2.0 + all_samples.last().unwrap().length;
+    let sigma_idct = ContinuousIdct::new(&spline.sigma_dct);
+    let xyb_idct = spline.xyb_dct.each_ref().map(ContinuousIdct::new);
+    all_samples
+        .iter()
+        .enumerate()
+        .map(|(i, arc)| {
             let arclength_from_start = f32::min(1.0, (i as f32) / arclength);
 
             let t = 31.0 * arclength_from_start;
-            let sigma = continuous_idct(&spline.sigma_dct, t);
+            let sigma = sigma_idct.eval(t);
             let inv_sigma = 1.0 / sigma;
             let values = [
-                continuous_idct(&spline.xyb_dct[0], t) * arc.length,
-                continuous_idct(&spline.xyb_dct[1], t) * arc.length,
-                continuous_idct(&spline.xyb_dct[2], t) * arc.length,
+                xyb_idct[0].eval(t) * arc.length,
+                xyb_idct[1].eval(t) * arc.length,
+                xyb_idct[2].eval(t) * arc.length,
             ];
 
             let max_color = f32::max(0.01, values.into_iter().reduce(f32::max).unwrap());
@@ -206,46 +347,159 @@ pub fn render_spline(
 
             let xbegin = i32::max(0, (arc.point.x - max_distance + 0.5).floor() as i32);
             let xend = i32::min(
-                (frame_header.width) as i32,
+                frame_width as i32,
                 (arc.point.x + max_distance + 1.5).floor() as i32,
             );
             let ybegin = i32::max(0, (arc.point.y - max_distance + 0.5).floor() as i32);
             let yend = i32::min(
-                (frame_header.height) as i32,
+                frame_height as i32,
                 (arc.point.y + max_distance + 1.5).floor() as i32,
             );
 
-            for (channel, buffer) in base_grid.as_color_floats_mut().iter_mut().enumerate() {
-                for y in ybegin..yend {
-                    let fy = y - region.top;
-                    if fy < 0 {
-                        continue;
-                    }
+            SplineSample {
+                point: arc.point,
+                values,
+                sigma,
+                inv_sigma,
+                x_range: xbegin..xend,
+                y_range: ybegin..yend,
+            }
+        })
+        .collect()
+}
 
-                    for x in xbegin..xend {
-                        let fx = x - region.left;
-                        if fx < 0 {
-                            continue;
-                        }
-
-                        let Some(sample) = buffer.get_mut(fx as usize, fy as usize) else {
-                            break;
-                        };
-                        let dx = (x as f32) - arc.point.x;
-                        let dy = (y as f32) - arc.point.y;
-                        let distance = f32::sqrt(dx * dx + dy * dy);
-                        const SQRT_0125: f32 = 0.353_553_38;
-                        let factor = erf((0.5 * distance + SQRT_0125) * inv_sigma)
-                            - erf((0.5 * distance - SQRT_0125) * inv_sigma);
-                        let extra = 0.25 * values[channel] * sigma * factor * factor;
-                        *sample += extra;
-                    }
-                }
+fn draw_splines(
+    frame_size: (u32, u32),
+    buffers: [&mut AlignedGrid<f32>; 3],
+    region: Region,
+    splines: &[Spline],
+    pool: &JxlThreadPool,
+) {
+    // Height of horizontal strips drawn in parallel.
+    const STRIP_HEIGHT: usize = 32;
+
+    // Nothing to draw; empty buffers can't be split into strips.
+    if buffers
+        .iter()
+        .any(|buffer| buffer.width() == 0 || buffer.height() == 0)
+    {
+        return;
+    }
+
+    let mut samples = Vec::new();
+    samples.resize_with(splines.len(), Vec::new);
+    let jobs = splines.iter().zip(&mut samples).collect();
+    pool.for_each_vec(jobs, |(spline, out)| {
+        *out = compute_samples(frame_size, spline);
+    });
+    let samples = samples.into_iter().flatten().collect::<Vec<_>>();
+
+    // Each strip is drawn by a single job, and contributions are added to each pixel in the same
+    // order regardless of the number of threads, so the result is deterministic.
+    let strips = buffers.map(|buffer| {
+        let width = buffer.width();
+        buffer.as_subgrid_mut().into_groups(width, STRIP_HEIGHT)
+    });
+    let [strips_x, strips_y, strips_b] = strips;
+    let jobs = strips_x
+        .into_iter()
+        .zip(strips_y)
+        .zip(strips_b)
+        .enumerate()
+        .map(|(idx, ((x, y), b))| (idx * STRIP_HEIGHT, [x, y, b]))
+        .collect();
+    pool.for_each_vec(jobs, |(strip_top, mut strip)| {
+        let strip_height = strip[0].height();
+        let top = region.top + strip_top as i32;
+        let bottom = top + strip_height as i32;
+        for sample in &samples {
+            let ybegin = sample.y_range.start.max(top);
+            let yend = sample.y_range.end.min(bottom);
+            for (channel, buffer) in strip.iter_mut().enumerate() {
+                draw_sample(sample, channel, buffer, region.left, top, ybegin..yend);
             }
         }
+    });
+}
+
+fn draw_sample(
+    sample: &SplineSample,
+    channel: usize,
+    buffer: &mut MutableSubgrid<f32>,
+    left: i32,
+    top: i32,
+    y_range: std::ops::Range<i32>,
+) {
+    let width = buffer.width() as i32;
+    let xbegin = sample.x_range.start.max(left);
+    let xend = sample.x_range.end.min(left + width);
+    if xbegin >= xend {
+        return;
     }
 
-    Ok(())
+    let scale = 0.25 * sample.values[channel] * sample.sigma;
+    for y in y_range {
+        let row = buffer.get_row_mut((y - top) as usize);
+        let out = &mut row[(xbegin - left) as usize..(xend - left) as usize];
+        let dy = (y as f32) - sample.point.y;
+        draw_sample_row(out, xbegin, sample.point.x, dy, sample.inv_sigma, scale);
+    }
+}
+
+/// Adds Gaussian falloff of a spline sample to a row, starting from `xbegin`.
+///
+/// The loop is branchless so that it can be vectorized; AVX2 is used if available on x86_64, and
+/// NEON is always available on aarch64.
+fn draw_sample_row(
+    out: &mut [f32],
+    xbegin: i32,
+    sample_x: f32,
+    dy: f32,
+    inv_sigma: f32,
+    scale: f32,
+) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+        // SAFETY: Feature set is checked above.
+        return unsafe { draw_sample_row_avx2(out, xbegin, sample_x, dy, inv_sigma, scale) };
+    }
+
+    draw_sample_row_generic(out, xbegin, sample_x, dy, inv_sigma, scale)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+#[target_feature(enable = "fma")]
+unsafe fn draw_sample_row_avx2(
+    out: &mut [f32],
+    xbegin: i32,
+    sample_x: f32,
+    dy: f32,
+    inv_sigma: f32,
+    scale: f32,
+) {
+    draw_sample_row_generic(out, xbegin, sample_x, dy, inv_sigma, scale)
+}
+
+#[inline(always)]
+fn draw_sample_row_generic(
+    out: &mut [f32],
+    xbegin: i32,
+    sample_x: f32,
+    dy: f32,
+    inv_sigma: f32,
+    scale: f32,
+) {
+    const SQRT_0125: f32 = 0.353_553_38;
+
+    let dy2 = dy * dy;
+    for (i, v) in out.iter_mut().enumerate() {
+        let dx = ((xbegin + i as i32) as f32) - sample_x;
+        let distance = f32::sqrt(dx * dx + dy2);
+        let factor = erf((0.5 * distance + SQRT_0125) * inv_sigma)
+            - erf((0.5 * distance - SQRT_0125) * inv_sigma);
+        *v += scale * factor * factor;
+    }
 }
 
 /// 2D Point in f32 coordinates
@@ -267,8 +521,12 @@ impl Point {
         }
     }
 
+    fn dot(&self, other: &Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
     fn norm_squared(&self) -> f32 {
-        self.x * self.x + self.y * self.y
+        self.dot(self)
     }
 
     fn norm(&self) -> f32 {
@@ -300,18 +558,62 @@ impl Mul<f32> for Point {
     }
 }
 
-fn continuous_idct(dct: &[f32; 32], t: f32) -> f32 {
-    let mut res = dct[0];
-    for (i, &dct) in dct.iter().enumerate().skip(1) {
-        let theta = (i as f32) * (std::f32::consts::PI / 32.0) * (t + 0.5);
-        res += std::f32::consts::SQRT_2 * dct * theta.cos();
+impl Div<f32> for Point {
+    type Output = Self;
+    fn div(self, rhs: f32) -> Self::Output {
+        Self {
+            x: self.x / rhs,
+            y: self.y / rhs,
+        }
+    }
+}
+
+impl Neg for Point {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+/// Continuous inverse DCT of 32 coefficients, evaluated at arbitrary positions.
+///
+/// Cosines of the multiples of the base angle are computed using the recurrence
+/// `cos(k x) = 2 cos(x) cos((k - 1) x) - cos((k - 2) x)`, so that only one cosine is evaluated
+/// per position.
+#[derive(Debug, Clone)]
+struct ContinuousIdct {
+    /// DCT coefficients, with AC coefficients premultiplied by `sqrt(2)`.
+    coeffs: [f32; 32],
+}
+
+impl ContinuousIdct {
+    fn new(dct: &[f32; 32]) -> Self {
+        let mut coeffs = *dct;
+        for coeff in &mut coeffs[1..] {
+            *coeff *= std::f32::consts::SQRT_2;
+        }
+        Self { coeffs }
+    }
+
+    fn eval(&self, t: f32) -> f32 {
+        // Run the recurrence in f64, as its error grows with the number of steps.
+        let theta = (std::f64::consts::PI / 32.0) * (t as f64 + 0.5);
+        let cos_theta = theta.cos();
+        let mut res = self.coeffs[0] as f64;
+        let mut cos_prev = 1.0f64;
+        let mut cos_curr = cos_theta;
+        for &coeff in &self.coeffs[1..] {
+            res += coeff as f64 * cos_curr;
+            (cos_prev, cos_curr) = (cos_curr, 2.0 * cos_theta * cos_curr - cos_prev);
+        }
+        res as f32
     }
-    res
 }
 
 /// Computes the error function
 // L1 error 7e-4.
 #[allow(clippy::excessive_precision)]
+#[inline(always)]
 fn erf(x: f32) -> f32 {
     let ax = x.abs();
 
@@ -324,10 +626,389 @@ fn erf(x: f32) -> f32 {
     let inv_denom5 = 1.0 / denom5;
     let result = -inv_denom5 * inv_denom5 + 1.0;
 
-    // Change sign if needed.
-    if x < 0.0 {
-        -result
-    } else {
-        result
+    // Change sign if needed. `result` is nonnegative, so this is branchless negation.
+    result.copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use jxl_frame::data::{QuantSpline, Splines};
+    use jxl_grid::AlignedGrid;
+    use jxl_threadpool::JxlThreadPool;
+
+    use super::{
+        draw_sample_row, draw_splines, erf, render_dequantized_spline_layer, render_spline_layer,
+        ContinuousIdct, Point, Spline,
+    };
+    use crate::Error;
+    use crate::Region;
+
+    const SQRT_0125: f32 = 0.353_553_38;
+
+    /// Horizontal spline along y = 16, from x = 4 to x = 28, with constant Y and σ.
+    fn horizontal_spline() -> Splines {
+        let mut xyb_dct = [[0; 32]; 3];
+        xyb_dct[1][0] = 10;
+        let mut sigma_dct = [0; 32];
+        sigma_dct[0] = 3;
+        Splines {
+            quant_splines: vec![QuantSpline {
+                quant_points: vec![(4, 16), (28, 16)],
+                manhattan_distance: 24,
+                xyb_dct,
+                sigma_dct,
+            }],
+            quant_adjust: 0,
+        }
+    }
+
+    #[test]
+    fn empty_spline_layer() {
+        let splines = horizontal_spline();
+        for (width, height) in [(0, 32), (32, 0), (0, 0)] {
+            let layer =
+                render_spline_layer(width, height, &splines, None, None, &JxlThreadPool::none())
+                    .unwrap();
+            for grid in layer {
+                assert_eq!(
+                    (grid.width(), grid.height()),
+                    (width as usize, height as usize)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn spline_layer() {
+        let splines = horizontal_spline();
+        let layer =
+            render_spline_layer(32, 32, &splines, None, None, &JxlThreadPool::none()).unwrap();
+        assert_eq!(layer.len(), 3);
+        let [x, y, b] = &*layer else { unreachable!() };
+
+        // X is not correlated with Y, and B is fully correlated by default.
+        assert!(x.buf().iter().all(|&v| v == 0.0));
+        assert_eq!(y.buf(), b.buf());
+
+        for px in 4..=28 {
+            assert!(*y.get(px, 16).unwrap() > 0.0, "x={px}");
+        }
+        for py in (0..8).chain(25..32) {
+            for px in 0..32 {
+                assert_eq!(*y.get(px, py).unwrap(), 0.0, "x={px}, y={py}");
+            }
+        }
+    }
+
+    #[test]
+    fn straight_spline_color() {
+        let splines = horizontal_spline();
+
+        // Draw onto a gray background covering (8, 12)-(24, 20) of the frame.
+        let background = 0.25;
+        let region = Region {
+            left: 8,
+            top: 12,
+            width: 16,
+            height: 8,
+        };
+        let mut buffers: [_; 3] = std::array::from_fn(|_| {
+            let mut grid = AlignedGrid::with_alloc_tracker(16, 8, None).unwrap();
+            grid.buf_mut().fill(background);
+            grid
+        });
+        let [x, y, b] = &mut buffers;
+        draw_splines(
+            (32, 32),
+            [x, y, b],
+            region,
+            &Spline::dequant_all(&splines, None),
+            &JxlThreadPool::none(),
+        );
+        let [x, y, b] = &buffers;
+
+        // Samples are placed at every integer x along the spline, each adding the Gaussian
+        // footprint of Y = 10 * 0.075 with σ = 3 * 0.3333.
+        let color = 10.0 * 0.075f32;
+        let sigma = 3.0 * 0.3333f32;
+        let expected_on_line = |px: i32| -> f32 {
+            (4..=28)
+                .map(|sample_x: i32| {
+                    let distance = (px - sample_x).abs() as f32;
+                    let factor = erf((0.5 * distance + SQRT_0125) / sigma)
+                        - erf((0.5 * distance - SQRT_0125) / sigma);
+                    0.25 * color * sigma * factor * factor
+                })
+                .sum()
+        };
+
+        for bx in 0..16 {
+            let px = bx as i32 + region.left;
+            let expected = background + expected_on_line(px);
+            let actual = *y.get(bx, 16 - 12).unwrap();
+            assert!(
+                (actual - expected).abs() < 1e-4,
+                "x={px}: {actual} != {expected}"
+            );
+
+            // The footprint is symmetric around the spline.
+            for dy in 1..4 {
+                let above = *y.get(bx, 4 - dy).unwrap();
+                let below = *y.get(bx, 4 + dy).unwrap();
+                assert!((above - below).abs() < 1e-6, "x={px}, dy={dy}");
+                assert!(background < above && above < actual, "x={px}, dy={dy}");
+            }
+        }
+
+        // X is not correlated with Y, and B is fully correlated by default.
+        assert!(x.buf().iter().all(|&v| v == background));
+        assert_eq!(y.buf(), b.buf());
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        // Many overlapping splines spanning several strips.
+        let mut state = 1u32;
+        let mut next = |range: u32| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 8) % range
+        };
+        let quant_splines = (0..64)
+            .map(|_| {
+                let quant_points = (0..4)
+                    .map(|_| (next(200) as i64, next(150) as i64))
+                    .collect::<Vec<_>>();
+                let manhattan_distance = quant_points
+                    .windows(2)
+                    .map(|w| w[1].0.abs_diff(w[0].0) + w[1].1.abs_diff(w[0].1))
+                    .sum();
+                let mut xyb_dct = [[0; 32]; 3];
+                let mut sigma_dct = [0; 32];
+                for coeffs in xyb_dct.iter_mut().chain([&mut sigma_dct]) {
+                    for coeff in &mut coeffs[..4] {
+                        *coeff = next(9) as i32 - 4;
+                    }
+                }
+                sigma_dct[0] = 4 + next(4) as i32;
+                QuantSpline {
+                    quant_points,
+                    manhattan_distance,
+                    xyb_dct,
+                    sigma_dct,
+                }
+            })
+            .collect();
+        let splines = Splines {
+            quant_splines,
+            quant_adjust: 0,
+        };
+
+        let serial =
+            render_spline_layer(200, 150, &splines, None, None, &JxlThreadPool::none()).unwrap();
+        let parallel = render_spline_layer(
+            200,
+            150,
+            &splines,
+            None,
+            None,
+            &JxlThreadPool::rayon(Some(4)),
+        )
+        .unwrap();
+        for (s, p) in serial.iter().zip(&parallel) {
+            let s = s.buf().iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            let p = p.buf().iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(s, p);
+        }
+        assert!(serial[1].buf().iter().any(|&v| v != 0.0));
+    }
+
+    #[test]
+    fn vectorized_row_matches_scalar() {
+        // Odd length to exercise the remainder of vectorized loop.
+        let xbegin = -37;
+        let sample_x = 12.3;
+        let inv_sigma = 1.0 / 2.7;
+        let scale = 0.8;
+        for dy in [0.0f32, 0.6, -3.5, 10.0] {
+            let mut out = vec![0.5f32; 131];
+            draw_sample_row(&mut out, xbegin, sample_x, dy, inv_sigma, scale);
+            let expected = (0..out.len())
+                .map(|i| {
+                    let dx = (xbegin + i as i32) as f32 - sample_x;
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    let factor = erf((0.5 * distance + SQRT_0125) * inv_sigma)
+                        - erf((0.5 * distance - SQRT_0125) * inv_sigma);
+                    0.5 + scale * factor * factor
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(out, expected, "dy={dy}");
+        }
+
+        // Odd function, bounded by 1.
+        for i in -400..=400 {
+            let x = i as f32 / 64.0;
+            assert_eq!(erf(-x), -erf(x));
+            assert!(erf(x).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn point_operators() {
+        let p = Point::new(3.0, -4.0);
+        let q = Point::new(0.5, 2.0);
+
+        assert_eq!(p.dot(&p), p.norm_squared());
+        assert_eq!(p.dot(&q), q.dot(&p));
+        assert_eq!(p.dot(&q), -6.5);
+
+        let origin = -p + p;
+        assert_eq!((origin.x, origin.y), (0.0, 0.0));
+
+        let half = p / 2.0;
+        assert_eq!((half.x, half.y), (1.5, -2.0));
+        let neg = -q;
+        assert_eq!((neg.x, neg.y), (-0.5, -2.0));
+    }
+
+    #[test]
+    fn continuous_idct_accuracy() {
+        // Previous implementation, which evaluates every cosine in f32.
+        fn reference(dct: &[f32; 32], t: f32) -> f32 {
+            let mut res = dct[0];
+            for (i, &dct) in dct.iter().enumerate().skip(1) {
+                let theta = (i as f32) * (std::f32::consts::PI / 32.0) * (t + 0.5);
+                res += std::f32::consts::SQRT_2 * dct * theta.cos();
+            }
+            res
+        }
+
+        fn exact(dct: &[f32; 32], t: f32) -> f64 {
+            let mut res = dct[0] as f64;
+            for (i, &dct) in dct.iter().enumerate().skip(1) {
+                let theta = (i as f64) * (std::f64::consts::PI / 32.0) * (t as f64 + 0.5);
+                res += std::f64::consts::SQRT_2 * dct as f64 * theta.cos();
+            }
+            res
+        }
+
+        let mut state = 0x1234_5678u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 8) as f32 / (1 << 24) as f32 - 0.5
+        };
+
+        // The reference rounds cosine arguments of up to ~97 rad to f32, so its own error grows
+        // with the magnitude of coefficients. Keep them within [-0.25, 0.25] where the reference is
+        // accurate enough to compare against; the new implementation is also checked against f64.
+
+        for _ in 0..16 {
+            let dct: [f32; 32] = std::array::from_fn(|_| next() / 2.0);
+            let idct = ContinuousIdct::new(&dct);
+            for step in 0..=31 * 64 {
+                let t = step as f32 / 64.0;
+                let actual = idct.eval(t);
+                let diff = (actual - reference(&dct, t)).abs();
+                assert!(diff <= 1e-5, "t = {t}, difference {diff}");
+                let error = (actual as f64 - exact(&dct, t)).abs();
+                assert!(error <= 1e-6, "t = {t}, error {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn spline_tree_roundtrip() {
+        let mut state = 0x9e37_79b9u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            state >> 8
+        };
+
+        for _ in 0..64 {
+            let num_points = 1 + next() as usize % 8;
+            let points = (0..num_points)
+                .map(|_| {
+                    let x = (next() % 2048) as i32 - 1024;
+                    let y = (next() % 2048) as i32 - 1024;
+                    Point::new(x as f32, y as f32)
+                })
+                .collect::<Vec<_>>();
+            let mut coeff = || (next() as f32 / (1 << 24) as f32 - 0.5) * 20.0;
+            let spline = Spline {
+                points,
+                xyb_dct: std::array::from_fn(|_| std::array::from_fn(|_| coeff())),
+                sigma_dct: std::array::from_fn(|_| coeff()),
+            };
+
+            let parsed = Spline::parse_from_tree(&spline.to_string()).unwrap();
+            assert_eq!(parsed.points.len(), spline.points.len());
+            for (a, b) in parsed.points.iter().zip(&spline.points) {
+                assert_eq!((a.x, a.y), (b.x, b.y));
+            }
+            let expected = spline.xyb_dct.iter().chain([&spline.sigma_dct]);
+            let actual = parsed.xyb_dct.iter().chain([&parsed.sigma_dct]);
+            for (a, b) in actual.flatten().zip(expected.flatten()) {
+                assert!((a - b).abs() <= 1e-6, "{a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn spline_tree_malformed() {
+        let coeffs = "0 ".repeat(32);
+        let valid = format!("Spline\n{coeffs}\n{coeffs}\n{coeffs}\n{coeffs}\n1 2\nEndSpline\n");
+        assert!(Spline::parse_from_tree(&valid).is_ok());
+
+        let cases = [
+            (valid.replacen("Spline", "Spine", 1), "expected `Spline`"),
+            (
+                valid.replacen("0 ", "", 1),
+                "expected 32 coefficients per row",
+            ),
+            (
+                valid.replacen("0 ", "0 0 ", 1),
+                "expected 32 coefficients per row",
+            ),
+            (valid.replacen("0 ", "zero ", 1), "invalid coefficient"),
+            (valid.replace("1 2", "1 two"), "invalid coordinate"),
+            (
+                valid.replace("1 2", "1 2 3"),
+                "expected two coordinates per control point",
+            ),
+            (valid.replace("EndSpline\n", ""), "missing `EndSpline`"),
+            (valid.replace("1 2\n", ""), "spline has no control points"),
+            (
+                format!("{valid}Spline\n"),
+                "unexpected data after `EndSpline`",
+            ),
+            (
+                "Spline\n0 0\n".to_string(),
+                "expected 32 coefficients per row",
+            ),
+            ("Spline\n".to_string(), "missing coefficients"),
+        ];
+        for (input, message) in cases {
+            let err = Spline::parse_from_tree(&input).unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidSpline(msg) if msg == message),
+                "{err:?} is not {message:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parsed_spline_layer() {
+        let splines = horizontal_spline();
+        let expected =
+            render_spline_layer(32, 32, &splines, None, None, &JxlThreadPool::none()).unwrap();
+
+        let parsed = Spline::dequant_all(&splines, None)
+            .iter()
+            .map(|spline| Spline::parse_from_tree(&spline.to_string()).unwrap())
+            .collect::<Vec<_>>();
+        let actual =
+            render_dequantized_spline_layer(32, 32, &parsed, None, &JxlThreadPool::none()).unwrap();
+        for (actual, expected) in actual.iter().zip(&expected) {
+            assert_eq!(actual.buf(), expected.buf());
+        }
     }
 }
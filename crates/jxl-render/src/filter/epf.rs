@@ -9,7 +9,7 @@ use crate::{util, ImageWithRegion, Region};
 
 pub fn apply_epf<S: Sample>(
     fb_image: &mut ImageWithRegion,
-    mut fb_scratch_arr: [AlignedGrid<f32>; 3],
+    fb_scratch_arr: &mut [AlignedGrid<f32>; 3],
     color_padded_region: Region,
     lf_groups: &HashMap<u32, LfGroup<S>>,
     frame_header: &FrameHeader,
@@ -89,7 +89,7 @@ pub fn apply_epf<S: Sample>(
     if iters == 1 || iters == 3 {
         let left = color_padded_region.left;
         let top = color_padded_region.top;
-        for (idx, grid) in fb_scratch_arr.into_iter().enumerate() {
+        for (idx, grid) in fb_scratch_arr.iter_mut().enumerate() {
             let width = grid.width() as u32;
             let height = grid.height() as u32;
             let region = Region {
@@ -98,7 +98,7 @@ pub fn apply_epf<S: Sample>(
                 left,
                 top,
             };
-            fb_image.replace_channel(idx, crate::ImageBuffer::F32(grid), region);
+            fb_image.swap_channel_f32(idx, grid, region);
         }
     }
 }
@@ -287,3 +287,107 @@ pub(super) const fn epf_dist_offsets<const STEP: usize>() -> &'static [(isize, i
         panic!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use jxl_bitstream::{Bitstream, Bundle};
+    use jxl_frame::{data::LfGroup, filter::EdgePreservingFilter, FrameHeader};
+    use jxl_grid::AlignedGrid;
+    use jxl_image::ImageHeader;
+    use jxl_modular::ChannelShift;
+    use jxl_threadpool::JxlThreadPool;
+    use jxl_vardct::{HfMetadata, HfMetadataParams};
+
+    use super::*;
+    use crate::{ImageBuffer, ImageWithRegion};
+
+    #[test]
+    fn sharpness_modulates_sigma() {
+        // 16x8 image with all-default metadata, followed by all-default VarDCT frame header and
+        // some padding.
+        let header_bytes = [0xff, 0x0a, 0x01, 0xc2, 0x01, 0, 0, 0, 0, 0, 0, 0];
+        let mut bitstream = Bitstream::new(&header_bytes);
+        let image_header = ImageHeader::parse(&mut bitstream, ()).unwrap();
+        bitstream.zero_pad_to_byte().unwrap();
+        let frame_header = FrameHeader::parse(&mut bitstream, &image_header).unwrap();
+        let EdgePreservingFilter::Enabled(epf_params) = &frame_header.restoration_filter.epf else {
+            panic!("EPF should be enabled by default");
+        };
+
+        let (width, height) = (16usize, 8usize);
+        let region = Region::with_size(width as u32, height as u32);
+        let mut fb = ImageWithRegion::new(3, None);
+        let mut original = Vec::new();
+        for c in 0..3 {
+            let mut grid = AlignedGrid::with_alloc_tracker(width, height, None).unwrap();
+            for (idx, v) in grid.buf_mut().iter_mut().enumerate() {
+                let (x, y) = (idx % width, idx / width);
+                *v = ((x + y + c) % 2) as f32 * 0.002;
+            }
+            original.push(grid.buf().to_vec());
+            fb.append_channel_shifted(ImageBuffer::F32(grid), region, ChannelShift::from_shift(0));
+        }
+        let mut scratch =
+            [(); 3].map(|_| AlignedGrid::with_alloc_tracker(width, height, None).unwrap());
+
+        // HfMetadata of two DCT8 blocks with HfMul of 1. Left block has the lowest sharpness value,
+        // right block has the highest one. Encoded as a local single-leaf tree with Zero
+        // predictor, and residuals coded with two-symbol prefix code `{0, 14}` where only the
+        // last sample (sharpness of the right block) is 7.
+        let hf_meta_bytes = [
+            0xc5, 0x11, 0x8d, 0xfc, 0xff, 0xff, 0xc0, 0x3f, 0x2e, 0x70, 0x40, 0, 0, 0, 0,
+        ];
+        let quantizer_global_scale = 16384;
+        let hf_meta = HfMetadata::parse(
+            &mut Bitstream::new(&hf_meta_bytes),
+            HfMetadataParams {
+                num_lf_groups: 1,
+                lf_group_idx: 0,
+                lf_width: width as u32,
+                lf_height: height as u32,
+                jpeg_upsampling: [0; 3],
+                bits_per_sample: 8,
+                global_ma_config: None,
+                epf: Some((epf_params.sigma.quant_mul, epf_params.sharp_lut)),
+                quantizer_global_scale,
+                tracker: None,
+                pool: &JxlThreadPool::none(),
+            },
+        )
+        .unwrap();
+        let sigma = epf_params.sigma.quant_mul * 65536.0 / quantizer_global_scale as f32;
+        assert_eq!(
+            hf_meta.epf_sigma.buf(),
+            [
+                sigma * epf_params.sharp_lut[0],
+                sigma * epf_params.sharp_lut[7]
+            ]
+        );
+        let lf_group = LfGroup::<i32> {
+            lf_coeff: None,
+            hf_meta: Some(hf_meta),
+            partial: false,
+        };
+        let lf_groups = HashMap::from([(0, lf_group)]);
+
+        apply_epf(
+            &mut fb,
+            &mut scratch,
+            region,
+            &lf_groups,
+            &frame_header,
+            epf_params,
+            &JxlThreadPool::none(),
+        );
+
+        let mut diff = [0f32; 2];
+        for (grid, original) in fb.as_color_floats_mut().into_iter().zip(&original) {
+            for (idx, (&v, &orig)) in grid.buf().iter().zip(original).enumerate() {
+                diff[(idx % width) / 8] += (v - orig).abs();
+            }
+        }
+        // Sigma is zero for the lowest sharpness value, so the block is left as is.
+        assert_eq!(diff[0], 0.0);
+        assert!(diff[1] > 0.0);
+    }
+}
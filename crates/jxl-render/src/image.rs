@@ -7,7 +7,7 @@ use jxl_modular::{ChannelShift, Sample};
 use jxl_threadpool::JxlThreadPool;
 use jxl_vardct::LfChannelDequantization;
 
-use crate::{util, FrameRender, FrameRenderHandle, Region, Result};
+use crate::{util, DecodedRegion, Error, FrameRender, FrameRenderHandle, Region, Result};
 
 #[derive(Debug)]
 pub enum ImageBuffer {
@@ -90,6 +90,22 @@ impl ImageBuffer {
         }
     }
 
+    /// Returns whether every sample of the buffer has the same value.
+    pub(crate) fn is_flat(&self) -> bool {
+        fn is_flat<V: PartialEq>(buf: &[V]) -> bool {
+            match buf.split_first() {
+                Some((first, rest)) => rest.iter().all(|v| v == first),
+                None => true,
+            }
+        }
+
+        match self {
+            Self::F32(g) => is_flat(g.buf()),
+            Self::I32(g) => is_flat(g.buf()),
+            Self::I16(g) => is_flat(g.buf()),
+        }
+    }
+
     pub fn convert_to_float_modular(
         &mut self,
         bit_depth: BitDepth,
@@ -266,6 +282,7 @@ pub struct ImageWithRegion {
     ct_done: bool,
     blend_done: bool,
     tracker: Option<AllocTracker>,
+    decoded_region: Option<DecodedRegion>,
 }
 
 impl ImageWithRegion {
@@ -277,6 +294,7 @@ impl ImageWithRegion {
             ct_done: false,
             blend_done: false,
             tracker: tracker.cloned(),
+            decoded_region: None,
         }
     }
 
@@ -292,9 +310,25 @@ impl ImageWithRegion {
             ct_done: self.ct_done,
             blend_done: false,
             tracker: self.tracker.clone(),
+            decoded_region: self.decoded_region,
         })
     }
 
+    /// Returns the region of the frame which was decoded to render this image, if available.
+    ///
+    /// The region is in frame coordinates of color samples, before upsampling, and includes
+    /// padding needed by restoration filters and upsampling. It may be widened further to
+    /// cover the whole frame, for example for Modular images with Squeeze or Palette.
+    #[inline]
+    pub fn decoded_region(&self) -> Option<DecodedRegion> {
+        self.decoded_region
+    }
+
+    #[inline]
+    pub(crate) fn set_decoded_region(&mut self, decoded_region: DecodedRegion) {
+        self.decoded_region = Some(decoded_region);
+    }
+
     #[inline]
     pub(crate) fn alloc_tracker(&self) -> Option<&AllocTracker> {
         self.tracker.as_ref()
@@ -305,6 +339,16 @@ impl ImageWithRegion {
         self.buffer.len()
     }
 
+    /// Returns [`Error::ChannelCountMismatch`] if the image doesn't have `expected` channels.
+    pub(crate) fn check_channel_count(&self, expected: usize) -> Result<()> {
+        let actual = self.channels();
+        if actual != expected {
+            tracing::error!(expected, actual, "Decoded channel count doesn't match");
+            return Err(Error::ChannelCountMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn buffer(&self) -> &[ImageBuffer] {
         &self.buffer
@@ -400,6 +444,14 @@ impl ImageWithRegion {
         }
     }
 
+    /// Returns whether each color channel is filled with a single value.
+    pub(crate) fn is_color_flat(&self) -> bool {
+        self.buffer
+            .iter()
+            .take(self.color_channels)
+            .all(ImageBuffer::is_flat)
+    }
+
     pub(crate) fn clone_gray(&mut self) -> Result<()> {
         assert_eq!(self.color_channels, 1);
 
@@ -442,6 +494,10 @@ impl ImageWithRegion {
             let buffer = buffer.upsample_nn(factor)?;
             out.append_channel_shifted(buffer, up_region, shift);
         }
+        out.decoded_region = self.decoded_region.map(|decoded| DecodedRegion {
+            region: decoded.region.upsample(factor),
+            ..decoded
+        });
         Ok(out)
     }
 
@@ -773,3 +829,32 @@ impl<S: Sample> RenderedImage<S> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_count_mismatch() {
+        // RGB image with an alpha channel whose data is missing.
+        let mut fb = ImageWithRegion::new(3, None);
+        for _ in 0..3 {
+            let buffer = ImageBuffer::zeroed_f32(8, 8, None).unwrap();
+            fb.append_channel(buffer, Region::with_size(8, 8));
+        }
+
+        let err = fb.check_channel_count(4).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::ChannelCountMismatch {
+                    expected: 4,
+                    actual: 3
+                }
+            ),
+            "{err}"
+        );
+        assert_eq!(err.to_string(), "channel count mismatch: expected 4, got 3");
+        fb.check_channel_count(3).unwrap();
+    }
+}
@@ -1,5 +1,5 @@
 //! This crate is the core of jxl-oxide that provides JPEG XL renderer.
-use std::sync::Arc;
+use std::sync::{atomic::AtomicBool, Arc};
 
 use jxl_bitstream::{Bitstream, Bundle};
 use jxl_color::{
@@ -7,12 +7,13 @@ use jxl_color::{
     EnumColourEncoding,
 };
 use jxl_frame::{header::FrameType, Frame, FrameContext};
-use jxl_grid::AllocTracker;
+use jxl_grid::{AlignedGrid, AllocTracker};
 use jxl_image::{ImageHeader, ImageMetadata};
 use jxl_modular::Sample;
 use jxl_threadpool::JxlThreadPool;
 
 mod blend;
+pub mod dct;
 mod error;
 mod features;
 mod filter;
@@ -25,9 +26,11 @@ mod util;
 mod vardct;
 
 pub use error::{Error, Result};
-pub use features::render_spot_color;
+pub use features::{
+    render_dequantized_spline_layer, render_spline_layer, render_spot_color, Spline,
+};
 pub use image::{ImageBuffer, ImageWithRegion};
-pub use region::Region;
+pub use region::{DecodedRegion, Region};
 use state::*;
 
 /// Render context that tracks loaded and rendered frames.
@@ -35,6 +38,10 @@ pub struct RenderContext {
     image_header: Arc<ImageHeader>,
     pool: JxlThreadPool,
     tracker: Option<AllocTracker>,
+    cancellation_flag: Option<Arc<AtomicBool>>,
+    features: RenderFeatures,
+    xyb_hook: Option<XybHook>,
+    max_spline_area: Option<u64>,
     pub(crate) frames: Vec<Arc<IndexedFrame>>,
     pub(crate) renders_wide: Vec<Arc<FrameRenderHandle<i32>>>,
     pub(crate) renders_narrow: Vec<Arc<FrameRenderHandle<i16>>>,
@@ -48,6 +55,7 @@ pub struct RenderContext {
     pub(crate) loading_render_cache_wide: Option<RenderCache<i32>>,
     pub(crate) loading_render_cache_narrow: Option<RenderCache<i16>>,
     pub(crate) loading_region: Option<Region>,
+    scratch_pool: Arc<ScratchPool>,
     requested_image_region: Region,
     embedded_icc: Vec<u8>,
     requested_color_encoding: ColorEncodingWithProfile,
@@ -71,6 +79,31 @@ pub struct RenderContextBuilder {
     embedded_icc: Vec<u8>,
     pool: Option<JxlThreadPool>,
     tracker: Option<AllocTracker>,
+    cancellation_flag: Option<Arc<AtomicBool>>,
+    features: RenderFeatures,
+    xyb_hook: Option<XybHook>,
+    max_spline_area: Option<u64>,
+}
+
+/// Frame features to render on top of decoded frames.
+///
+/// All features are rendered by default. Disabling some of them doesn't conform to the
+/// specification, but is useful for inspecting the base image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderFeatures {
+    pub patches: bool,
+    pub splines: bool,
+    pub noise: bool,
+}
+
+impl Default for RenderFeatures {
+    fn default() -> Self {
+        Self {
+            patches: true,
+            splines: true,
+            noise: true,
+        }
+    }
 }
 
 impl RenderContextBuilder {
@@ -89,6 +122,42 @@ impl RenderContextBuilder {
         self
     }
 
+    /// Sets a flag which cancels rendering when set to `true`.
+    ///
+    /// Rendering checks the flag before decoding each group, and returns [`Error::Cancelled`] if
+    /// it's set. Frames which were cancelled should be rendered again after calling
+    /// [`RenderContext::reset_cache`].
+    pub fn cancellation_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancellation_flag = Some(flag);
+        self
+    }
+
+    /// Sets frame features to render.
+    pub fn features(mut self, features: RenderFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Sets a hook which is called with XYB samples of every displayed frame right before color
+    /// conversion.
+    ///
+    /// The hook runs once per rendered frame, after reconstruction, restoration filters and
+    /// frame features, so it sees frames of animations before they're blended. It is not called
+    /// if the image is not XYB encoded.
+    pub fn xyb_hook(mut self, hook: XybHook) -> Self {
+        self.xyb_hook = Some(hook);
+        self
+    }
+
+    /// Sets the maximum total estimated area of splines in a frame.
+    ///
+    /// Frames with splines exceeding the limit fail to decode with
+    /// [`jxl_frame::Error::SplineAreaTooLarge`]. Defaults to the limit of Level 10.
+    pub fn max_spline_area(mut self, max_spline_area: u64) -> Self {
+        self.max_spline_area = Some(max_spline_area);
+        self
+    }
+
     pub fn build(self, image_header: Arc<ImageHeader>) -> Result<RenderContext> {
         let color_encoding = &image_header.metadata.colour_encoding;
         let requested_color_encoding = if let ColourEncoding::Enum(encoding) = color_encoding {
@@ -135,6 +204,10 @@ impl RenderContextBuilder {
         Ok(RenderContext {
             image_header,
             tracker: self.tracker,
+            cancellation_flag: self.cancellation_flag,
+            features: self.features,
+            xyb_hook: self.xyb_hook,
+            max_spline_area: self.max_spline_area,
             pool: self.pool.unwrap_or_else(JxlThreadPool::none),
             frames: Vec::new(),
             renders_wide: Vec::new(),
@@ -149,6 +222,7 @@ impl RenderContextBuilder {
             loading_render_cache_wide: None,
             loading_render_cache_narrow: None,
             loading_region: None,
+            scratch_pool: Arc::new(ScratchPool::default()),
             requested_image_region: full_image_region,
             embedded_icc: self.embedded_icc,
             requested_color_encoding,
@@ -369,9 +443,12 @@ impl RenderContext {
         let frame = match Frame::parse(
             bitstream,
             FrameContext {
-                image_header: image_header.clone(),
-                tracker: self.tracker.as_ref(),
-                pool: self.pool.clone(),
+                max_spline_area: self.max_spline_area,
+                ..FrameContext::new(
+                    image_header.clone(),
+                    self.tracker.as_ref(),
+                    self.pool.clone(),
+                )
             },
         ) {
             Ok(frame) => frame,
@@ -445,6 +522,10 @@ impl RenderContext {
         let prev_frame_visibility = self.get_previous_frames_visibility(&frame);
 
         let pool = self.pool.clone();
+        let scratch_pool = Arc::clone(&self.scratch_pool);
+        let cancellation_flag = self.cancellation_flag.clone();
+        let features = self.features;
+        let xyb_hook = self.xyb_hook.clone();
         Arc::new(move |mut state, image_region| {
             if let Some(lf) = &reference_frames.lf {
                 tracing::trace!(idx = lf.frame.idx, "Spawn LF frame renderer");
@@ -478,7 +559,11 @@ impl RenderContext {
                 &mut cache,
                 image_region,
                 pool.clone(),
+                &scratch_pool,
                 prev_frame_visibility,
+                cancellation_flag.as_deref(),
+                features,
+                xyb_hook.as_ref(),
             );
             match result {
                 Ok(grid) => FrameRender::Done(grid),
@@ -712,7 +797,11 @@ impl RenderContext {
                 &mut cache,
                 image_region,
                 self.pool.clone(),
+                &self.scratch_pool,
                 self.get_previous_frames_visibility(frame),
+                self.cancellation_flag.as_deref(),
+                self.features,
+                self.xyb_hook.as_ref(),
             );
             match image_result {
                 Ok(image) => image,
@@ -747,7 +836,11 @@ impl RenderContext {
                 &mut cache,
                 image_region,
                 self.pool.clone(),
+                &self.scratch_pool,
                 self.get_previous_frames_visibility(frame),
+                self.cancellation_flag.as_deref(),
+                self.features,
+                self.xyb_hook.as_ref(),
             );
             match image_result {
                 Ok(image) => image,
@@ -858,6 +951,30 @@ impl RenderContext {
     }
 }
 
+/// Callback which modifies XYB samples of frames before color conversion.
+///
+/// See [`RenderContextBuilder::xyb_hook`].
+#[derive(Clone)]
+pub struct XybHook(Arc<XybHookFn>);
+
+type XybHookFn = dyn Fn([&mut AlignedGrid<f32>; 3]) + Send + Sync;
+
+impl XybHook {
+    pub fn new(hook: impl Fn([&mut AlignedGrid<f32>; 3]) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, channels: [&mut AlignedGrid<f32>; 3]) {
+        (self.0)(channels)
+    }
+}
+
+impl std::fmt::Debug for XybHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("XybHook").finish_non_exhaustive()
+    }
+}
+
 /// Frame with its index in the image.
 #[derive(Debug)]
 pub struct IndexedFrame {
@@ -877,6 +994,156 @@ impl IndexedFrame {
     pub fn index(&self) -> usize {
         self.idx
     }
+
+    /// Renders splines of the frame on a zero background, without other frame contents.
+    ///
+    /// Returns three grids of color channels with the size of the frame, in the color space of the
+    /// frame (XYB if the image is XYB encoded). The grids are all zero if the frame doesn't have
+    /// splines.
+    ///
+    /// Returns [`Error::IncompleteFrame`] if LfGlobal is not loaded yet.
+    pub fn render_splines_layer(&self) -> Result<Vec<jxl_grid::AlignedGrid<f32>>> {
+        let lf_global = self
+            .try_parse_lf_global::<i32>()
+            .ok_or(Error::IncompleteFrame)??;
+        let base_correlations_xb = lf_global.vardct.as_ref().map(|x| {
+            (
+                x.lf_chan_corr.base_correlation_x,
+                x.lf_chan_corr.base_correlation_b,
+            )
+        });
+
+        let splines = lf_global.splines.unwrap_or(jxl_frame::data::Splines {
+            quant_splines: Vec::new(),
+            quant_adjust: 0,
+        });
+        features::render_spline_layer(
+            self.header().width,
+            self.header().height,
+            &splines,
+            base_correlations_xb,
+            self.alloc_tracker(),
+            self.pool(),
+        )
+    }
+}
+
+impl IndexedFrame {
+    /// Computes the average color of the frame from its LF image, without reconstructing HF
+    /// coefficients.
+    ///
+    /// The LF image is a 1:8 downsampled version of the frame, so this needs only LfGlobal and
+    /// LfGroups to be loaded. The color is in the signalled color encoding of the image if it's an
+    /// RGB or grayscale enum encoding, and in sRGB otherwise. Averaging is done before color
+    /// conversion, so the result may differ slightly from the average of rendered samples.
+    ///
+    /// Returns [`Error::NotSupported`] if the frame doesn't have its own LF image, i.e. if the
+    /// frame is Modular encoded or uses an LF frame, and [`Error::IncompleteFrame`] if LfGlobal or
+    /// any of LfGroups is not loaded yet.
+    pub fn average_color(&self) -> Result<[f32; 3]> {
+        let frame_header = self.header();
+        if frame_header.encoding != jxl_frame::header::Encoding::VarDct
+            || frame_header.flags.use_lf_frame()
+        {
+            return Err(Error::NotSupported("frame doesn't have its own LF image"));
+        }
+
+        let lf_global = self
+            .try_parse_lf_global::<i32>()
+            .ok_or(Error::IncompleteFrame)??;
+        let lf_global_vardct = lf_global.vardct.as_ref().unwrap();
+        let global_ma_config = lf_global.gmodular.ma_config();
+
+        let mut sum = [0i64; 3];
+        let mut count = [0u64; 3];
+        for lf_group_idx in 0..frame_header.num_lf_groups() {
+            let lf_group = self
+                .try_parse_lf_group::<i32>(
+                    Some(lf_global_vardct),
+                    global_ma_config,
+                    None,
+                    lf_group_idx,
+                )
+                .ok_or(Error::IncompleteFrame)??;
+            if lf_group.partial {
+                return Err(Error::IncompleteFrame);
+            }
+            let (Some(lf_coeff), Some(lf_coeffs)) =
+                (&lf_group.lf_coeff, lf_group.lf_coefficients())
+            else {
+                return Err(Error::IncompleteFrame);
+            };
+            // Same as `copy_lf_dequant`, each LF group has its own precision.
+            let precision_scale = 1i64 << (9 - lf_coeff.extra_precision);
+            for ((sum, count), grid) in sum.iter_mut().zip(&mut count).zip(lf_coeffs) {
+                *sum += grid.buf().iter().map(|&v| v as i64).sum::<i64>() * precision_scale;
+                *count += grid.buf().len() as u64;
+            }
+        }
+
+        let quantizer = &lf_global_vardct.quantizer;
+        let lf_dequant = &lf_global.lf_dequant;
+        let m_lf = [lf_dequant.m_x_lf, lf_dequant.m_y_lf, lf_dequant.m_b_lf];
+        let scale_inv = quantizer.global_scale as f64 * quantizer.quant_lf as f64;
+        let mut color: [f32; 3] = std::array::from_fn(|idx| {
+            let mean = sum[idx] as f64 / count[idx].max(1) as f64;
+            (mean * m_lf[idx] as f64 / scale_inv) as f32
+        });
+
+        // Chroma-from-luma is linear, so it can be applied to the average.
+        if frame_header.jpeg_upsampling.iter().all(|&u| u == 0) {
+            let lf_chan_corr = &lf_global_vardct.lf_chan_corr;
+            let colour_factor = lf_chan_corr.colour_factor as f32;
+            let kx = lf_chan_corr.base_correlation_x
+                + (lf_chan_corr.x_factor_lf as i32 - 128) as f32 / colour_factor;
+            let kb = lf_chan_corr.base_correlation_b
+                + (lf_chan_corr.b_factor_lf as i32 - 128) as f32 / colour_factor;
+            color[0] += kx * color[1];
+            color[2] += kb * color[1];
+        }
+
+        let metadata = &self.image_header().metadata;
+        if frame_header.do_ycbcr {
+            let mut grids = color.map(|v| {
+                let mut grid = AlignedGrid::with_alloc_tracker(1, 1, None).unwrap();
+                grid.buf_mut()[0] = v;
+                grid
+            });
+            let [cb, y, cr] = &mut grids;
+            jxl_color::ycbcr_to_rgb([cb, y, cr]);
+            color = grids.map(|grid| grid.buf()[0]);
+        } else if metadata.xyb_encoded {
+            let target = match &metadata.colour_encoding {
+                ColourEncoding::Enum(
+                    encoding @ EnumColourEncoding {
+                        colour_space: ColourSpace::Rgb | ColourSpace::Grey,
+                        ..
+                    },
+                ) => encoding.clone(),
+                _ => EnumColourEncoding::srgb(jxl_color::RenderingIntent::Perceptual),
+            };
+            let transform = jxl_color::ColorTransform::xyb_to_enum(
+                jxl_color::RenderingIntent::Perceptual,
+                &target,
+                &metadata.opsin_inverse_matrix,
+                &metadata.tone_mapping,
+            )?;
+            let [x, y, b] = &mut color;
+            let output_channels = transform.run(
+                &mut [
+                    std::slice::from_mut(x),
+                    std::slice::from_mut(y),
+                    std::slice::from_mut(b),
+                ],
+                &jxl_color::NullCms,
+            )?;
+            if output_channels == 1 {
+                color = [color[0]; 3];
+            }
+        }
+
+        Ok(color)
+    }
 }
 
 impl std::ops::Deref for IndexedFrame {
@@ -1,13 +1,16 @@
 use jxl_frame::{data::GlobalModular, FrameHeader};
 use jxl_modular::{image::TransformedModularSubimage, Sample};
 
-use crate::{util, Error, ImageWithRegion, IndexedFrame, Region, RenderCache, Result};
+use crate::{
+    util, DecodedRegion, Error, ImageWithRegion, IndexedFrame, Region, RenderCache, Result,
+};
 
 pub(crate) fn render_modular<S: Sample>(
     frame: &IndexedFrame,
     cache: &mut RenderCache<S>,
     region: Region,
     pool: &jxl_threadpool::JxlThreadPool,
+    cancel: Option<&std::sync::atomic::AtomicBool>,
 ) -> Result<ImageWithRegion> {
     let image_header = frame.image_header();
     let frame_header = frame.header();
@@ -24,7 +27,8 @@ pub(crate) fn render_modular<S: Sample>(
         cache.lf_global.as_ref().unwrap()
     };
     let mut gmodular = lf_global.gmodular.try_clone()?;
-    let modular_region = compute_modular_region(frame_header, &gmodular, region, false);
+    let decoded_region = compute_modular_region(frame_header, &gmodular, region, false);
+    let modular_region = decoded_region.region;
 
     let modular_image = gmodular.modular.image_mut().unwrap();
     let groups = modular_image.prepare_groups(frame.pass_shifts())?;
@@ -43,6 +47,7 @@ pub(crate) fn render_modular<S: Sample>(
                     lf_group_image,
                     modular_region.downsample(3),
                     pool,
+                    cancel,
                 );
                 if let Err(e) = r {
                     *result.write().unwrap() = Err(e);
@@ -98,6 +103,11 @@ pub(crate) fn render_modular<S: Sample>(
                      group_idx,
                      modular,
                  }| {
+                    if let Err(e) = util::check_cancelled(cancel) {
+                        *result.write().unwrap() = Err(e);
+                        return;
+                    }
+
                     let bitstream = match frame.pass_group_bitstream(pass_idx, group_idx) {
                         Some(Ok(bitstream)) => bitstream,
                         Some(Err(e)) => {
@@ -137,7 +147,11 @@ pub(crate) fn render_modular<S: Sample>(
 
     let mut fb = ImageWithRegion::new(frame_header.encoded_color_channels(), tracker);
     fb.extend_from_gmodular(gmodular);
+    fb.set_decoded_region(decoded_region);
 
+    // Samples of XYB-encoded images are quantized XYB, which should be dequantized before color
+    // conversion. Otherwise samples are already in the signalled color space (RGB or YCbCr) and
+    // are left as is.
     if xyb_encoded {
         tracing::trace_span!("Dequant XYB")
             .in_scope(|| fb.convert_modular_xyb(&lf_global.lf_dequant))?;
@@ -152,8 +166,9 @@ pub fn compute_modular_region<S: Sample>(
     gmodular: &GlobalModular<S>,
     region: Region,
     is_lf: bool,
-) -> Region {
-    if gmodular.modular.has_palette() || gmodular.modular.has_squeeze() {
+) -> DecodedRegion {
+    let has_squeeze = gmodular.modular.has_squeeze();
+    if gmodular.modular.has_palette() || has_squeeze {
         let mut width = frame_header.color_sample_width();
         let mut height = frame_header.color_sample_height();
         if is_lf {
@@ -162,8 +177,30 @@ pub fn compute_modular_region<S: Sample>(
         }
         let width = width.max(region.width.checked_add_signed(region.left).unwrap());
         let height = height.max(region.height.checked_add_signed(region.top).unwrap());
-        Region::with_size(width, height)
+        let modular_region = Region::with_size(width, height);
+        if modular_region != region {
+            if has_squeeze {
+                tracing::info!(
+                    ?region,
+                    is_lf,
+                    "GlobalModular has squeeze, decoding from top-left"
+                );
+            } else {
+                tracing::info!(
+                    ?region,
+                    is_lf,
+                    "GlobalModular has palette, decoding from top-left"
+                );
+            }
+        }
+        DecodedRegion {
+            region: modular_region,
+            full_decode: modular_region != region,
+        }
     } else {
-        region
+        DecodedRegion {
+            region,
+            full_decode: false,
+        }
     }
 }
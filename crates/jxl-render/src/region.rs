@@ -258,3 +258,13 @@ impl Region {
         }
     }
 }
+
+/// Region of a frame which was actually decoded while rendering it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DecodedRegion {
+    /// Decoded region in frame coordinates of color samples, before upsampling.
+    pub region: Region,
+    /// Whether the region was widened to cover the frame from its top-left corner, because
+    /// transforms such as Squeeze or Palette prevent decoding only a part of it.
+    pub full_decode: bool,
+}
@@ -1,23 +1,31 @@
+use std::sync::atomic::AtomicBool;
+
 use jxl_frame::{
     filter::{EdgePreservingFilter, Gabor},
     header::Encoding,
 };
-use jxl_grid::AlignedGrid;
 use jxl_modular::Sample;
 use jxl_threadpool::JxlThreadPool;
 
 use crate::{
-    blend, features, filter, modular, state::RenderCache, util, vardct, Error, ImageWithRegion,
-    IndexedFrame, Reference, ReferenceFrames, Region, Result,
+    blend, features, filter, modular,
+    state::{RenderCache, ScratchPool},
+    util, vardct, Error, ImageWithRegion, IndexedFrame, Reference, ReferenceFrames, Region,
+    RenderFeatures, Result, XybHook,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn render_frame<S: Sample>(
     frame: &IndexedFrame,
     reference_frames: ReferenceFrames<S>,
     cache: &mut RenderCache<S>,
     image_region: Region,
     pool: JxlThreadPool,
+    scratch_pool: &ScratchPool,
     frame_visibility: (usize, usize),
+    cancel: Option<&AtomicBool>,
+    features: RenderFeatures,
+    xyb_hook: Option<&XybHook>,
 ) -> Result<ImageWithRegion> {
     let frame_region = util::image_region_to_frame(frame, image_region, false);
     tracing::debug!(
@@ -44,7 +52,9 @@ pub(crate) fn render_frame<S: Sample>(
         .intersection(full_frame_region);
 
     let mut fb = match frame_header.encoding {
-        Encoding::Modular => modular::render_modular(frame, cache, color_padded_region, &pool)?,
+        Encoding::Modular => {
+            modular::render_modular(frame, cache, color_padded_region, &pool, cancel)?
+        }
         Encoding::VarDct => {
             let result = vardct::render_vardct(
                 frame,
@@ -52,6 +62,7 @@ pub(crate) fn render_frame<S: Sample>(
                 cache,
                 color_padded_region,
                 &pool,
+                cancel,
             );
             match (result, reference_frames.lf) {
                 (Ok(grid), _) => grid,
@@ -65,29 +76,44 @@ pub(crate) fn render_frame<S: Sample>(
         }
     };
 
+    // Later stages index extra channels by their position in the image header, so a frame buffer
+    // with a different number of channels can't be rendered.
+    fb.check_channel_count(
+        frame_header.encoded_color_channels() + image_header.metadata.ec_info.len(),
+    )?;
+
     if frame_header.do_ycbcr {
         fb.upsample_jpeg(color_padded_region, image_header.metadata.bit_depth)?;
     }
 
+    // Restoration filters don't change flat images, so they can be skipped. This is common for
+    // animation frames of a single color.
+    let restoration_filter = &frame_header.restoration_filter;
+    let has_filters = restoration_filter.gab.enabled() || restoration_filter.epf.enabled();
+    let skip_filters = has_filters && fb.is_color_flat();
+    if skip_filters {
+        tracing::trace!("Color channels are flat, skipping restoration filters");
+    }
+
     let color_channels = fb.color_channels();
     let mut scratch_buffer = None;
-    if let Gabor::Enabled(weights) = frame_header.restoration_filter.gab {
+    let gabor = if skip_filters {
+        &Gabor::Disabled
+    } else {
+        &restoration_filter.gab
+    };
+    if let &Gabor::Enabled(weights) = gabor {
         if fb.color_channels() < 3 {
             tracing::trace!("Cloning gray channel");
             fb.clone_gray()?;
         }
 
         fb.convert_modular_color(image_header.metadata.bit_depth)?;
-        let mut fb_scratch = {
-            let tracker = fb.alloc_tracker();
-            let width = color_padded_region.width as usize;
-            let height = color_padded_region.height as usize;
-            [
-                AlignedGrid::with_alloc_tracker(width, height, tracker)?,
-                AlignedGrid::with_alloc_tracker(width, height, tracker)?,
-                AlignedGrid::with_alloc_tracker(width, height, tracker)?,
-            ]
-        };
+        let mut fb_scratch = scratch_pool.take(
+            color_padded_region.width as usize,
+            color_padded_region.height as usize,
+            fb.alloc_tracker(),
+        )?;
         filter::apply_gabor_like(
             &mut fb,
             color_padded_region,
@@ -98,34 +124,40 @@ pub(crate) fn render_frame<S: Sample>(
         scratch_buffer = Some(fb_scratch);
     }
 
-    if let EdgePreservingFilter::Enabled(epf_params) = &frame_header.restoration_filter.epf {
+    let epf = if skip_filters {
+        &EdgePreservingFilter::Disabled
+    } else {
+        &restoration_filter.epf
+    };
+    if let EdgePreservingFilter::Enabled(epf_params) = epf {
         if fb.color_channels() < 3 {
             tracing::trace!("Cloning gray channel");
             fb.clone_gray()?;
         }
 
         fb.convert_modular_color(image_header.metadata.bit_depth)?;
-        let fb_scratch = if let Some(buffer) = scratch_buffer {
+        let mut fb_scratch = if let Some(buffer) = scratch_buffer.take() {
             buffer
         } else {
-            let tracker = fb.alloc_tracker();
-            let width = color_padded_region.width as usize;
-            let height = color_padded_region.height as usize;
-            [
-                AlignedGrid::with_alloc_tracker(width, height, tracker)?,
-                AlignedGrid::with_alloc_tracker(width, height, tracker)?,
-                AlignedGrid::with_alloc_tracker(width, height, tracker)?,
-            ]
+            scratch_pool.take(
+                color_padded_region.width as usize,
+                color_padded_region.height as usize,
+                fb.alloc_tracker(),
+            )?
         };
         filter::apply_epf(
             &mut fb,
-            fb_scratch,
+            &mut fb_scratch,
             color_padded_region,
             &cache.lf_groups,
             frame_header,
             epf_params,
             &pool,
         );
+        scratch_buffer = Some(fb_scratch);
+    }
+    if let Some(fb_scratch) = scratch_buffer {
+        scratch_pool.release(fb_scratch);
     }
 
     // Truncate cloned gray channels.
@@ -142,10 +174,21 @@ pub(crate) fn render_frame<S: Sample>(
         frame_visibility.0,
         frame_visibility.1,
         &pool,
+        features,
     )?;
 
     fb.upsample_nonseparable(image_header, frame_header, upsampling_valid_region, false)?;
 
+    // The hook should run before frames of animations are converted for blending below.
+    if let Some(hook) = xyb_hook {
+        if image_header.metadata.xyb_encoded
+            && frame_header.frame_type.is_normal_frame()
+            && !fb.ct_done()
+        {
+            hook.call(fb.as_color_floats_mut());
+        }
+    }
+
     if !frame_header.save_before_ct && !frame_header.is_last {
         util::convert_color_for_record(image_header, frame_header.do_ycbcr, &mut fb, &pool)?;
     }
@@ -163,6 +206,7 @@ fn render_features<S: Sample>(
     visible_frames_num: usize,
     invisible_frames_num: usize,
     pool: &JxlThreadPool,
+    features: RenderFeatures,
 ) -> Result<()> {
     let image_header = frame.image_header();
     let frame_header = frame.header();
@@ -177,7 +221,7 @@ fn render_features<S: Sample>(
         )
     });
 
-    if let Some(patches) = &lf_global.patches {
+    if let Some(patches) = lf_global.patches.as_ref().filter(|_| features.patches) {
         grid.upsample_nonseparable(image_header, frame_header, upsampling_valid_region, true)?;
 
         for patch in &patches.patches {
@@ -193,16 +237,16 @@ fn render_features<S: Sample>(
         }
     }
 
-    if let Some(splines) = &lf_global.splines {
+    if let Some(splines) = lf_global.splines.as_ref().filter(|_| features.splines) {
         if grid.color_channels() == 3 {
             grid.convert_modular_color(image_header.metadata.bit_depth)?;
-            features::render_spline(frame_header, grid, splines, base_correlations_xb)?;
+            features::render_spline(frame_header, grid, splines, base_correlations_xb, pool)?;
         } else {
             tracing::warn!("Cannot render splines on grayscale buffer; skipping");
         }
     }
 
-    if let Some(noise) = &lf_global.noise {
+    if let Some(noise) = lf_global.noise.as_ref().filter(|_| features.noise) {
         if grid.color_channels() == 3 {
             grid.convert_modular_color(image_header.metadata.bit_depth)?;
             features::render_noise(
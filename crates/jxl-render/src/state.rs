@@ -4,6 +4,7 @@ use std::{
 };
 
 use jxl_frame::data::{HfGlobal, LfGlobal, LfGroup};
+use jxl_grid::{AlignedGrid, AllocTracker};
 use jxl_modular::{ChannelShift, Sample};
 
 use crate::{
@@ -43,6 +44,75 @@ impl<S: Sample> RenderCache<S> {
     }
 }
 
+/// Pool of scratch buffers used by restoration filters, shared by the frames of an image.
+///
+/// Frames of an animation usually have the same dimension, so buffers released after rendering a
+/// frame are reused by the next one instead of being allocated again.
+#[derive(Debug, Default)]
+pub(crate) struct ScratchPool {
+    buffers: Mutex<Vec<AlignedGrid<f32>>>,
+}
+
+impl ScratchPool {
+    /// Maximum number of buffers kept in the pool.
+    const MAX_BUFFERS: usize = 6;
+
+    /// Takes three buffers of the given dimension, allocating new ones if the pool doesn't have
+    /// enough of them.
+    ///
+    /// Buffers with different dimension are reshaped if they are large enough. Contents of the
+    /// returned buffers are unspecified.
+    pub(crate) fn take(
+        &self,
+        width: usize,
+        height: usize,
+        tracker: Option<&AllocTracker>,
+    ) -> Result<[AlignedGrid<f32>; 3]> {
+        let mut reused = Vec::with_capacity(3);
+        {
+            let mut buffers = self.buffers.lock().unwrap();
+            while reused.len() < 3 {
+                let idx = buffers
+                    .iter()
+                    .position(|g| g.width() == width && g.height() == height)
+                    .or_else(|| {
+                        buffers
+                            .iter_mut()
+                            .position(|g| g.try_reshape(width, height))
+                    });
+                let Some(idx) = idx else {
+                    break;
+                };
+                reused.push(buffers.swap_remove(idx));
+            }
+        }
+        if !reused.is_empty() {
+            tracing::trace!(
+                width,
+                height,
+                count = reused.len(),
+                "Reusing scratch buffers"
+            );
+        }
+
+        while reused.len() < 3 {
+            reused.push(AlignedGrid::with_alloc_tracker(width, height, tracker)?);
+        }
+        Ok(reused.try_into().unwrap())
+    }
+
+    /// Returns buffers to the pool. Buffers exceeding the capacity of the pool are dropped.
+    pub(crate) fn release(&self, grids: impl IntoIterator<Item = AlignedGrid<f32>>) {
+        let mut buffers = self.buffers.lock().unwrap();
+        for grid in grids {
+            if buffers.len() >= Self::MAX_BUFFERS {
+                break;
+            }
+            buffers.push(grid);
+        }
+    }
+}
+
 #[derive(Default)]
 pub enum FrameRender<S: Sample> {
     #[default]
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use jxl_color::{
     ColorEncodingWithProfile, ColorTransform, ColourEncoding, ColourSpace, EnumColourEncoding,
 };
@@ -13,7 +15,8 @@ use jxl_modular::{image::TransformedModularSubimage, ChannelShift, Sample};
 use jxl_threadpool::JxlThreadPool;
 
 use crate::{
-    image::ImageBuffer, vardct::copy_lf_dequant, ImageWithRegion, IndexedFrame, Region, Result,
+    image::ImageBuffer, vardct::copy_lf_dequant, Error, ImageWithRegion, IndexedFrame, Region,
+    Result,
 };
 
 pub(crate) fn image_region_to_frame(
@@ -82,6 +85,10 @@ pub(crate) fn pad_upsampling(
     }
 }
 
+/// Maps `frame_region`, in frame coordinates after upsampling, to the region of color samples
+/// needed to render it, in coded (pre-upsampling) coordinates.
+///
+/// The region is padded for upsampling and restoration filters.
 pub(crate) fn pad_color_region(
     image_header: &ImageHeader,
     frame_header: &FrameHeader,
@@ -119,6 +126,16 @@ pub(crate) fn pad_color_region(
     color_padded_region
 }
 
+/// Returns [`Error::Cancelled`] if the cancellation flag is set.
+#[inline]
+pub(crate) fn check_cancelled(cancel: Option<&AtomicBool>) -> Result<()> {
+    if cancel.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+        tracing::debug!("Rendering cancelled");
+        return Err(Error::Cancelled);
+    }
+    Ok(())
+}
+
 pub(crate) fn load_lf_groups<S: Sample>(
     frame: &IndexedFrame,
     lf_global: &LfGlobal<S>,
@@ -126,6 +143,7 @@ pub(crate) fn load_lf_groups<S: Sample>(
     mlf_groups: Vec<TransformedModularSubimage<S>>,
     lf_region: Region,
     pool: &JxlThreadPool,
+    cancel: Option<&AtomicBool>,
 ) -> Result<Option<ImageWithRegion>> {
     #[derive(Default)]
     struct LfGroupJob<'modular, 'xyb, S: Sample> {
@@ -234,6 +252,10 @@ pub(crate) fn load_lf_groups<S: Sample>(
             ref mut modular,
             ref mut lf_xyb,
         } = *job;
+        if let Err(e) = check_cancelled(cancel) {
+            *result.write().unwrap() = Err(e);
+            return;
+        }
         let loaded = lf_group.as_ref().map(|g| !g.partial).unwrap_or(false);
 
         if !loaded {
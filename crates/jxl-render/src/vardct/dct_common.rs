@@ -74,42 +74,52 @@ pub const fn sec_half_small(n: usize) -> &'static [f32] {
     SEC_HALF_SMALL[idx]
 }
 
+/// Scaling factors of low-frequency DCT coefficients, used when LF samples are converted to the
+/// lowest frequency coefficients of large varblocks.
+///
+/// `SCALE_F[c]` is `cos(c * π / 128) * cos(c * π / 256) * cos(c * π / 512)`. If an `8N`-point
+/// signal is formed by repeating each of `N` samples 8 times, its `k`-th DCT coefficient equals
+/// `k`-th coefficient of the `N`-point DCT multiplied by `scale_f(k, 5 - log2(N))`.
+#[allow(clippy::excessive_precision)]
+pub const SCALE_F: [f32; 32] = [
+    1.0000000000000000,
+    0.9996047255830407,
+    0.9984194528776054,
+    0.9964458326264695,
+    0.9936866130906366,
+    0.9901456355893141,
+    0.9858278282666936,
+    0.9807391980963174,
+    0.9748868211368796,
+    0.9682788310563117,
+    0.9609244059440204,
+    0.9528337534340876,
+    0.9440180941651672,
+    0.9344896436056892,
+    0.9242615922757944,
+    0.9133480844001980,
+    0.9017641950288744,
+    0.8895259056651056,
+    0.8766500784429904,
+    0.8631544288990163,
+    0.8490574973847023,
+    0.8343786191696513,
+    0.8191378932865928,
+    0.8033561501721485,
+    0.7870549181591013,
+    0.7702563888779096,
+    0.7529833816270532,
+    0.7352593067735488,
+    0.7171081282466044,
+    0.6985543251889097,
+    0.6796228528314652,
+    0.6603391026591464,
+];
+
+/// Returns the scaling factor of `c`-th coefficient of `2^(5 - logb)`-point DCT.
+///
+/// # Panics
+/// Panics if `c << logb` is not less than 32.
 pub const fn scale_f(c: usize, logb: usize) -> f32 {
-    // Precomputed for c = 0..32, b = 256
-    #[allow(clippy::excessive_precision)]
-    const SCALE_F: [f32; 32] = [
-        1.0000000000000000,
-        0.9996047255830407,
-        0.9984194528776054,
-        0.9964458326264695,
-        0.9936866130906366,
-        0.9901456355893141,
-        0.9858278282666936,
-        0.9807391980963174,
-        0.9748868211368796,
-        0.9682788310563117,
-        0.9609244059440204,
-        0.9528337534340876,
-        0.9440180941651672,
-        0.9344896436056892,
-        0.9242615922757944,
-        0.9133480844001980,
-        0.9017641950288744,
-        0.8895259056651056,
-        0.8766500784429904,
-        0.8631544288990163,
-        0.8490574973847023,
-        0.8343786191696513,
-        0.8191378932865928,
-        0.8033561501721485,
-        0.7870549181591013,
-        0.7702563888779096,
-        0.7529833816270532,
-        0.7352593067735488,
-        0.7171081282466044,
-        0.6985543251889097,
-        0.6796228528314652,
-        0.6603391026591464,
-    ];
     SCALE_F[c << logb]
 }
@@ -433,4 +433,39 @@ mod tests {
             assert_eq!(q_expected, q_actual);
         }
     }
+
+    #[test]
+    fn scale_f_matches_dct() {
+        for logn in 1..=5usize {
+            let n = 1 << logn;
+            let lf = (0..n)
+                .map(|i| ((i * 7 + 3) % 5) as f32 - 2.0)
+                .collect::<Vec<_>>();
+            let mut lf_coeff = lf.clone();
+            let mut scratch = vec![0.0f32; n];
+            super::dct(&mut lf_coeff, &mut scratch, DctDirection::Forward);
+
+            // Repeat each sample 8 times.
+            let mut coeff = lf.iter().flat_map(|&v| [v; 8]).collect::<Vec<_>>();
+            let mut scratch = vec![0.0f32; n * 8];
+            super::dct(&mut coeff, &mut scratch, DctDirection::Forward);
+
+            for (k, (&lf, &actual)) in lf_coeff.iter().zip(&coeff).enumerate() {
+                let expected = lf * crate::dct::scale_f(k, 5 - logn);
+                assert!(
+                    (expected - actual).abs() < 1e-5,
+                    "n={n}, k={k}: expected {expected}, got {actual}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scale_f_table() {
+        for (c, &scale) in crate::dct::SCALE_F.iter().enumerate() {
+            let theta = c as f64 * std::f64::consts::PI / 512.0;
+            let expected = (theta * 4.0).cos() * (theta * 2.0).cos() * theta.cos();
+            assert!((expected as f32 - scale).abs() < 1e-6);
+        }
+    }
 }
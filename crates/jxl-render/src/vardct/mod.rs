@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
 
 use jxl_frame::{
     data::{HfGlobal, LfGlobal, LfGroup, PassGroupParams, PassGroupParamsVardct},
@@ -17,7 +18,7 @@ use crate::{
     RenderCache, Result,
 };
 
-mod dct_common;
+pub(crate) mod dct_common;
 mod transform_common;
 
 #[cfg(target_arch = "x86_64")]
@@ -49,6 +50,7 @@ pub(crate) fn render_vardct<S: Sample>(
     cache: &mut RenderCache<S>,
     region: Region,
     pool: &JxlThreadPool,
+    cancel: Option<&AtomicBool>,
 ) -> Result<ImageWithRegion> {
     let span = tracing::span!(tracing::Level::TRACE, "Render VarDCT");
     let _guard = span.enter();
@@ -113,10 +115,12 @@ pub(crate) fn render_vardct<S: Sample>(
         width_rounded as u32 / 8,
         height_rounded as u32 / 8,
     ));
-    let modular_region =
+    let decoded_region =
         modular::compute_modular_region(frame_header, &gmodular, aligned_region, false);
+    let modular_region = decoded_region.region;
     let modular_lf_region =
         modular::compute_modular_region(frame_header, &gmodular, aligned_lf_region, true)
+            .region
             .intersection(Region::with_size(
                 width_rounded as u32 / 8,
                 height_rounded as u32 / 8,
@@ -145,7 +149,21 @@ pub(crate) fn render_vardct<S: Sample>(
         if hf_global.is_none() {
             scope.spawn(|_| {
                 let ret = tracing::trace_span!("Parse HfGlobal").in_scope(|| -> Result<_> {
-                    *hf_global = frame.try_parse_hf_global(Some(lf_global)).transpose()?;
+                    let parsed = frame.try_parse_hf_global(Some(lf_global));
+                    // Missing HfGlobal is fine while loading, as LF image can still be rendered.
+                    // It's an error if the frame is fully loaded though.
+                    let missing = match &parsed {
+                        None => true,
+                        Some(Err(e)) => e.unexpected_eof(),
+                        Some(Ok(_)) => false,
+                    };
+                    if missing && frame.is_loading_done() {
+                        tracing::error!("HfGlobal is missing in fully loaded VarDCT frame");
+                        return Err(
+                            jxl_frame::Error::IncompleteFrameData { field: "hf_global" }.into()
+                        );
+                    }
+                    *hf_global = parsed.transpose()?;
                     Ok(())
                 });
                 if let Err(e) = ret {
@@ -162,6 +180,7 @@ pub(crate) fn render_vardct<S: Sample>(
                 lf_group_image,
                 modular_lf_region,
                 pool,
+                cancel,
             )
         })?;
 
@@ -209,6 +228,7 @@ pub(crate) fn render_vardct<S: Sample>(
                     AlignedGrid::with_alloc_tracker(width as usize, height as usize, tracker)?;
                 fb.append_channel_shifted(ImageBuffer::F32(buffer), modular_region, shift);
             }
+            fb.set_decoded_region(decoded_region);
             fb
         };
 
@@ -219,8 +239,10 @@ pub(crate) fn render_vardct<S: Sample>(
     let hf_global = cache.hf_global.as_ref();
     let lf_groups = &mut cache.lf_groups;
 
-    let it = tracing::trace_span!("Prepare PassGroup").in_scope(|| {
-        fb.color_groups_with_group_id(frame_header)
+    let (it, all_lf_groups_loaded) = tracing::trace_span!("Prepare PassGroup").in_scope(|| {
+        let groups = fb.color_groups_with_group_id(frame_header);
+        let num_groups = groups.len();
+        let it = groups
             .into_iter()
             .filter_map(|(group_idx, grid_xyb)| {
                 let lf_group_idx = frame_header.lf_group_idx_from_group_idx(group_idx);
@@ -228,7 +250,9 @@ pub(crate) fn render_vardct<S: Sample>(
 
                 Some((group_idx, grid_xyb, lf_group))
             })
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>();
+        let all_lf_groups_loaded = it.len() == num_groups;
+        (it, all_lf_groups_loaded)
     });
 
     tracing::trace_span!("Decode PassGroup").in_scope(|| {
@@ -273,6 +297,11 @@ pub(crate) fn render_vardct<S: Sample>(
 
                     let result = &result;
                     scope.spawn(move |_| {
+                        if let Err(e) = util::check_cancelled(cancel) {
+                            *result.write().unwrap() = Err(e);
+                            return;
+                        }
+
                         let vardct = Some(PassGroupParamsVardct {
                             lf_vardct: lf_global_vardct,
                             hf_global,
@@ -305,11 +334,27 @@ pub(crate) fn render_vardct<S: Sample>(
         result.into_inner().unwrap()
     })?;
 
+    let flat_color = if all_lf_groups_loaded {
+        flat_frame_color(&lf_xyb, it.iter().flat_map(|(_, grid_xyb, _)| grid_xyb))
+    } else {
+        None
+    };
+    if flat_color.is_some() {
+        tracing::trace!("Frame is flat, skipping dequantization and transform");
+    }
+
     tracing::trace_span!("Dequant and transform").in_scope(|| {
         let groups_per_row = frame_header.groups_per_row();
 
         pool.for_each_vec(it, |job| {
             let (group_idx, mut grid_xyb, lf_group) = job;
+            if let Some(color) = flat_color {
+                for (grid, value) in grid_xyb.into_iter().zip(color) {
+                    fill_subgrid(grid, value);
+                }
+                return;
+            }
+
             let grid_xyb = &mut grid_xyb;
             let group_x = group_idx % groups_per_row;
             let group_y = group_idx / groups_per_row;
@@ -376,6 +421,42 @@ pub(crate) fn render_vardct<S: Sample>(
     Ok(fb)
 }
 
+/// Returns the color of the frame if every sample of it is the same.
+///
+/// This is the case if the LF image is flat and there are no HF coefficients. Every varblock
+/// then has a single DC coefficient of the same value, and dequantization and inverse transform
+/// result in the LF color itself.
+fn flat_frame_color<'g>(
+    lf_xyb: &ImageWithRegion,
+    coeffs: impl IntoIterator<Item = &'g MutableSubgrid<'g, f32>>,
+) -> Option<[f32; 3]> {
+    if !lf_xyb.is_color_flat() {
+        return None;
+    }
+    let lf_xyb = lf_xyb.as_color_floats();
+    let mut color = [0f32; 3];
+    for (value, lf) in color.iter_mut().zip(lf_xyb) {
+        *value = *lf.buf().first()?;
+    }
+
+    for grid in coeffs {
+        for y in 0..grid.height() {
+            // Coefficients are stored as `i32`, so compare bit patterns.
+            if grid.get_row(y).iter().any(|v| v.to_bits() != 0) {
+                return None;
+            }
+        }
+    }
+
+    Some(color)
+}
+
+fn fill_subgrid(mut grid: MutableSubgrid<'_, f32>, value: f32) {
+    for y in 0..grid.height() {
+        grid.get_row_mut(y).fill(value);
+    }
+}
+
 pub fn copy_lf_dequant<S: Sample>(
     grid: &mut MutableSubgrid<f32>,
     quantizer: &Quantizer,
@@ -414,9 +495,16 @@ pub fn adaptive_lf_smoothing(
     let lf_b = (512.0 * lf_dequant.m_b_lf as f64 / scale_inv as f64) as f32;
 
     let [in_x, in_y, in_b] = lf_image;
-    let tracker = in_x.tracker();
-    let width = in_x.width();
-    let height = in_x.height();
+    let tracker = in_y.tracker();
+    let width = in_y.width();
+    let height = in_y.height();
+    if [&in_x, &in_b]
+        .into_iter()
+        .any(|g| g.width() != width || g.height() != height)
+    {
+        // Adaptive LF smoothing is not applied to chroma subsampled images.
+        return Ok(());
+    }
 
     let in_x = in_x.buf_mut();
     let in_y = in_y.buf_mut();
@@ -719,3 +807,55 @@ fn for_each_varblocks(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use jxl_bitstream::BundleDefault;
+
+    use super::*;
+
+    fn smoothing_params() -> (LfChannelDequantization, Quantizer) {
+        let lf_dequant = LfChannelDequantization::default_with_context(());
+        let quantizer = Quantizer {
+            global_scale: 2048,
+            quant_lf: 16,
+        };
+        (lf_dequant, quantizer)
+    }
+
+    #[test]
+    fn lf_smoothing_grayscale() {
+        let (lf_dequant, quantizer) = smoothing_params();
+        let (width, height) = (8, 8);
+        let mut lf = [(); 3].map(|_| AlignedGrid::with_alloc_tracker(width, height, None).unwrap());
+        for (idx, v) in lf[1].buf_mut().iter_mut().enumerate() {
+            *v = ((idx % width) as f32 * 0.001) + ((idx / width) % 2) as f32 * 0.0005;
+        }
+        let original_y = lf[1].buf().to_vec();
+
+        let [x, y, b] = &mut lf;
+        adaptive_lf_smoothing([x, y, b], &lf_dequant, &quantizer).unwrap();
+
+        // Chroma stays zero, luma is smoothed.
+        assert!(lf[0].buf().iter().all(|&v| v == 0.0));
+        assert!(lf[2].buf().iter().all(|&v| v == 0.0));
+        assert_ne!(lf[1].buf(), &original_y[..]);
+    }
+
+    #[test]
+    fn lf_smoothing_skips_subsampled() {
+        let (lf_dequant, quantizer) = smoothing_params();
+        let mut y = AlignedGrid::with_alloc_tracker(8, 8, None).unwrap();
+        let mut x = AlignedGrid::with_alloc_tracker(4, 4, None).unwrap();
+        let mut b = AlignedGrid::with_alloc_tracker(4, 4, None).unwrap();
+        for (idx, v) in y.buf_mut().iter_mut().enumerate() {
+            *v = (idx % 3) as f32 * 0.001;
+        }
+        x.buf_mut().fill(0.01);
+        let original_y = y.buf().to_vec();
+
+        adaptive_lf_smoothing([&mut x, &mut y, &mut b], &lf_dequant, &quantizer).unwrap();
+        assert_eq!(y.buf(), &original_y[..]);
+        assert!(x.buf().iter().all(|&v| v == 0.01));
+    }
+}
@@ -47,6 +47,18 @@ impl TryFrom<u8> for TransformType {
     }
 }
 
+impl TryFrom<i32> for TransformType {
+    type Error = crate::Error;
+
+    /// Converts raw transform type code decoded from `BlockInfo`, rejecting reserved codes.
+    fn try_from(value: i32) -> crate::Result<Self> {
+        u8::try_from(value)
+            .ok()
+            .and_then(|value| Self::try_from(value).ok())
+            .ok_or(crate::Error::InvalidTransformType(value))
+    }
+}
+
 impl TransformType {
     /// Returns the size of the transform type, in 8x8 blocks.
     pub fn dct_select_size(self) -> (u32, u32) {
@@ -153,3 +165,26 @@ impl TransformType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TransformType;
+
+    #[test]
+    fn reserved_transform_type() {
+        assert_eq!(TransformType::try_from(0i32).unwrap(), TransformType::Dct8);
+        assert_eq!(
+            TransformType::try_from(26i32).unwrap(),
+            TransformType::Dct128x256
+        );
+
+        // Codes that would alias valid ones if truncated to `u8` are rejected as well.
+        for code in [27, 255, 256, 257, -1, i32::MAX, i32::MIN] {
+            let err = TransformType::try_from(code).unwrap_err();
+            assert!(
+                matches!(err, crate::Error::InvalidTransformType(c) if c == code),
+                "{code}: {err}"
+            );
+        }
+    }
+}
@@ -700,3 +700,275 @@ impl DequantMatrixSet {
         &self.matrices_tr[idx][channel]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use jxl_threadpool::JxlThreadPool;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        num_bits: usize,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, bits: usize, value: u32) {
+            for idx in 0..bits {
+                let shift = self.num_bits % 8;
+                if shift == 0 {
+                    self.bytes.push(0);
+                }
+                let bit = ((value >> idx) & 1) as u8;
+                *self.bytes.last_mut().unwrap() |= bit << shift;
+                self.num_bits += 1;
+            }
+        }
+
+        /// Writes a zero or normal, exactly representable value as binary16.
+        fn write_f16(&mut self, value: f32) {
+            if value == 0.0 {
+                self.write(16, 0);
+                return;
+            }
+            let bits = value.to_bits();
+            let exponent = ((bits >> 23) & 0xff) + 15 - 127;
+            let v = ((bits >> 16) & 0x8000) | (exponent << 10) | ((bits >> 13) & 0x3ff);
+            self.write(16, v);
+        }
+
+        fn write_fixed(&mut self, params: &[&[f32]; 3]) {
+            for &param in params.iter().copied().flatten() {
+                self.write_f16(param);
+            }
+        }
+
+        /// Writes DCT parameters with a single band per channel.
+        fn write_dct_params(&mut self, bands: [f32; 3]) {
+            self.write(4, 0);
+            for band in bands {
+                self.write_f16(band / 64.0);
+            }
+        }
+
+        /// Writes a Modular image whose samples are all `value`, using a single-leaf MA tree.
+        fn write_constant_modular(&mut self, value: u32) {
+            let packed = value * 2;
+            // Local tree, default WP params, no transforms.
+            self.write(1, 0);
+            self.write(1, 1);
+            self.write(2, 0);
+
+            // Tree entropy code: 6 contexts in 5 clusters, prefix code.
+            self.write(1, 0);
+            self.write(1, 1);
+            self.write(2, 3);
+            for cluster in [0, 1, 2, 3, 4, 4] {
+                self.write(3, cluster);
+            }
+            self.write(1, 1);
+            for _ in 0..5 {
+                self.write(4, 15);
+            }
+            // Only the offset cluster has more than one symbol.
+            let offset_bits = 32 - packed.leading_zeros() as usize;
+            for cluster in 0..5 {
+                if cluster == 3 {
+                    let nbits = offset_bits - 1;
+                    self.write(1, 1);
+                    self.write(4, nbits as u32);
+                    self.write(nbits, packed - (1 << nbits));
+                } else {
+                    self.write(1, 0);
+                }
+            }
+            self.write(2, 1);
+            self.write(2, 0);
+            self.write(offset_bits, packed);
+            // The single leaf uses the Zero predictor with the offset, which takes no bits.
+
+            // Data entropy code: one symbol of zero-length code.
+            self.write(1, 0);
+            self.write(1, 1);
+            self.write(4, 15);
+            self.write(1, 0);
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            self.bytes.resize(self.bytes.len() + 16, 0);
+            self.bytes
+        }
+    }
+
+    fn parse(dct_select: TransformType, data: &[u8]) -> Result<[Vec<f32>; 3]> {
+        let pool = JxlThreadPool::none();
+        let params = DequantMatrixSetParams {
+            dct_select,
+            ..DequantMatrixSetParams::new(8, 1, None, None, &pool)
+        };
+        let mut bitstream = Bitstream::new(data);
+        DequantMatrixParams::parse(&mut bitstream, params)?.into_matrix()
+    }
+
+    fn encode(mode: u32, f: impl FnOnce(&mut BitWriter)) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.write(3, mode);
+        f(&mut w);
+        w.finish()
+    }
+
+    fn assert_matrix(actual: &[Vec<f32>; 3], expected: impl Fn(usize, usize, usize) -> f32) {
+        for (c, matrix) in actual.iter().enumerate() {
+            assert_eq!(matrix.len(), 64);
+            for (idx, &w) in matrix.iter().enumerate() {
+                let (x, y) = (idx % 8, idx / 8);
+                assert_eq!(w, expected(c, x, y), "channel {c}, ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn library() {
+        let matrix = parse(TransformType::Dct8, &encode(0, |_| {})).unwrap();
+        let expected = DequantMatrixParams::default_with(TransformType::Dct8)
+            .into_matrix()
+            .unwrap();
+        assert_eq!(matrix, expected);
+    }
+
+    #[test]
+    fn hornuss() {
+        let params: [&[f32]; 3] = [
+            &[2.0, 4.0, 8.0],
+            &[16.0, 32.0, 64.0],
+            &[128.0, 256.0, 512.0],
+        ];
+        let data = encode(1, |w| w.write_fixed(&params));
+        let matrix = parse(TransformType::Hornuss, &data).unwrap();
+        assert_matrix(&matrix, |c, x, y| match (x, y) {
+            (0, 0) => 1.0,
+            (1, 0) | (0, 1) => 1.0 / params[c][1],
+            (1, 1) => 1.0 / params[c][2],
+            _ => 1.0 / params[c][0],
+        });
+    }
+
+    #[test]
+    fn dct2() {
+        let params: [&[f32]; 3] = [
+            &[2.0, 4.0, 8.0, 16.0, 32.0, 64.0],
+            &[3.0, 5.0, 7.0, 9.0, 11.0, 13.0],
+            &[64.0, 32.0, 16.0, 8.0, 4.0, 2.0],
+        ];
+        let data = encode(2, |w| w.write_fixed(&params));
+        let matrix = parse(TransformType::Dct2, &data).unwrap();
+        assert_matrix(&matrix, |c, x, y| {
+            if x == 0 && y == 0 {
+                return 1.0;
+            }
+            let shift = x.max(y).ilog2() as usize;
+            let is_diagonal = x.min(y) >= 1 << shift;
+            1.0 / params[c][shift * 2 + is_diagonal as usize]
+        });
+    }
+
+    #[test]
+    fn dct4() {
+        let params: [&[f32]; 3] = [&[2.0, 4.0], &[0.5, 0.25], &[1.0, 8.0]];
+        let bands = [32.0, 64.0, 128.0];
+        let data = encode(3, |w| {
+            w.write_fixed(&params);
+            w.write_dct_params(bands);
+        });
+        let matrix = parse(TransformType::Dct4, &data).unwrap();
+        assert_matrix(&matrix, |c, x, y| match (x, y) {
+            (1, 0) | (0, 1) => params[c][0] / bands[c],
+            (1, 1) => params[c][1] / bands[c],
+            _ => 1.0 / bands[c],
+        });
+    }
+
+    #[test]
+    fn dct4x8() {
+        let params: [&[f32]; 3] = [&[2.0], &[4.0], &[0.5]];
+        let bands = [64.0, 256.0, 1024.0];
+        let data = encode(4, |w| {
+            w.write_fixed(&params);
+            w.write_dct_params(bands);
+        });
+        let matrix = parse(TransformType::Dct4x8, &data).unwrap();
+        assert_matrix(&matrix, |c, x, y| match (x, y) {
+            (0, 1) => params[c][0] / bands[c],
+            _ => 1.0 / bands[c],
+        });
+    }
+
+    #[test]
+    fn afv() {
+        // With zero multipliers, every interpolated weight equals the fifth parameter.
+        let params: [&[f32]; 3] = [
+            &[1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 0.0, 0.0, 0.0],
+            &[2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 0.0, 0.0, 0.0],
+            &[4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 0.0, 0.0, 0.0],
+        ];
+        let bands_4x8 = [128.0, 512.0, 2048.0];
+        let bands_4x4 = [256.0, 1024.0, 4096.0];
+        let data = encode(5, |w| {
+            w.write_fixed(&params);
+            w.write_dct_params(bands_4x8);
+            w.write_dct_params(bands_4x4);
+        });
+        let matrix = parse(TransformType::Afv0, &data).unwrap();
+        assert_matrix(&matrix, |c, x, y| {
+            let param = |idx: usize| params[c][idx] * 64.0;
+            let w = match (x % 2, y % 2) {
+                // 4x8 DCT part
+                (_, 1) if (x, y) == (0, 1) => param(0),
+                (_, 1) => bands_4x8[c],
+                // 4x4 DCT part
+                (1, 0) if (x, y) == (1, 0) => param(1),
+                (1, 0) => bands_4x4[c],
+                // AFV part
+                _ => match (x / 2, y / 2) {
+                    (0, 0) => return 1.0,
+                    (0, 1) => param(2),
+                    (1, 0) => param(3),
+                    (1, 1) => param(4),
+                    _ => param(5),
+                },
+            };
+            1.0 / w
+        });
+    }
+
+    #[test]
+    fn dct() {
+        let bands = [64.0, 128.0, 256.0];
+        let data = encode(6, |w| w.write_dct_params(bands));
+        let matrix = parse(TransformType::Dct8, &data).unwrap();
+        assert_matrix(&matrix, |c, _, _| 1.0 / bands[c]);
+
+        // Mode 6 is valid for every transform type.
+        let matrix = parse(TransformType::Dct16, &data).unwrap();
+        assert!(matrix[1].iter().all(|&w| w == 1.0 / bands[1]));
+    }
+
+    #[test]
+    fn raw() {
+        let data = encode(7, |w| {
+            w.write_f16(1.0 / 64.0);
+            w.write_constant_modular(4);
+        });
+        let matrix = parse(TransformType::Dct8, &data).unwrap();
+        // Raw weights are used as-is, without taking reciprocals.
+        assert_matrix(&matrix, |_, _, _| 4.0 / 64.0);
+    }
+
+    #[test]
+    fn invalid_mode_for_transform_type() {
+        let data = encode(1, |w| w.write_fixed(&[&[1.0; 3]; 3]));
+        assert!(parse(TransformType::Hornuss, &data).is_ok());
+        assert!(parse(TransformType::Dct16, &data).is_err());
+    }
+}
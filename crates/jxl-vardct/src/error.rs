@@ -5,6 +5,7 @@ pub enum Error {
     Decoder(jxl_coding::Error),
     Buffer(jxl_grid::Error),
     Modular(jxl_modular::Error),
+    InvalidTransformType(i32),
 }
 
 impl From<jxl_bitstream::Error> for Error {
@@ -40,6 +41,7 @@ impl std::fmt::Display for Error {
             Decoder(err) => write!(f, "entropy decoder error: {}", err),
             Buffer(err) => write!(f, "{}", err),
             Modular(err) => write!(f, "modular stream error: {}", err),
+            InvalidTransformType(code) => write!(f, "invalid transform type {}", code),
         }
     }
 }
@@ -53,6 +55,7 @@ impl std::error::Error for Error {
             Decoder(err) => Some(err),
             Buffer(err) => Some(err),
             Modular(err) => Some(err),
+            InvalidTransformType(_) => None,
         }
     }
 }
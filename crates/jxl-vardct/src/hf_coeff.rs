@@ -15,11 +15,16 @@ pub struct HfCoeffParams<'a, 'b, S: Sample> {
     pub jpeg_upsampling: [u32; 3],
     pub lf_quant: Option<[SharedSubgrid<'a, S>; 3]>,
     pub hf_pass: &'a HfPass,
+    /// Left shift applied to decoded coefficients, specified per pass in the frame header.
     pub coeff_shift: u32,
     pub tracker: Option<&'b AllocTracker>,
 }
 
 /// Decode and write HF coefficients from the bitstream.
+///
+/// Decoded coefficients are *added* to `hf_coeff_output`, so that the coefficients of every pass
+/// of the same group accumulate in the same buffer. The buffer should be zeroed before decoding
+/// the first pass.
 pub fn write_hf_coeff<S: Sample>(
     bitstream: &mut Bitstream,
     params: HfCoeffParams<S>,
@@ -152,17 +157,7 @@ pub fn write_hf_coeff<S: Sample>(
                 let idx = (ch_idx * hf_idx_mul + hf_idx) * lf_idx_mul + lf_idx;
                 let block_ctx = block_ctx_map[idx] as u32;
                 let non_zeros_ctx = {
-                    let predicted = if sy == 0 {
-                        if sx == 0 {
-                            32
-                        } else {
-                            non_zeros_grid_row[c][sx - 1]
-                        }
-                    } else if sx == 0 {
-                        non_zeros_grid_row[c][sx]
-                    } else {
-                        (non_zeros_grid_row[c][sx] + non_zeros_grid_row[c][sx - 1] + 1) >> 1
-                    };
+                    let predicted = predict_non_zeros(&non_zeros_grid_row[c], sx, sy);
                     debug_assert!(predicted < 64);
 
                     let idx = if predicted >= 8 {
@@ -248,3 +243,65 @@ pub fn write_hf_coeff<S: Sample>(
 
     Ok(())
 }
+
+/// Predicts the number of non-zero coefficients per block of the varblock at `(sx, sy)`, from the
+/// blocks above and to the left of it.
+///
+/// `non_zeros_row` holds the value of the block most recently decoded in each column. This is the
+/// block right above `(sx, sy)` for column `sx`, and the block covering `(sx - 1, sy)` for column
+/// `sx - 1`, as varblocks are decoded in raster order of their top-left corners.
+#[inline]
+fn predict_non_zeros(non_zeros_row: &[u32], sx: usize, sy: usize) -> u32 {
+    if sy == 0 {
+        if sx == 0 {
+            32
+        } else {
+            non_zeros_row[sx - 1]
+        }
+    } else if sx == 0 {
+        non_zeros_row[sx]
+    } else {
+        (non_zeros_row[sx] + non_zeros_row[sx - 1] + 1) >> 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::predict_non_zeros;
+
+    #[test]
+    fn non_zeros_prediction_varblocks() {
+        // Varblocks of a 6x4 block area as (x, y, w8, h8, non-zeros per block), in raster order
+        // of their top-left corners, with the expected prediction of each.
+        #[rustfmt::skip]
+        let varblocks: [(usize, usize, usize, usize, u32, u32); 13] = [
+            (0, 0, 2, 2, 10, 32), (2, 0, 1, 1, 3, 10), (3, 0, 2, 1, 20, 3), (5, 0, 1, 3, 7, 20),
+            (2, 1, 1, 1, 1, 7), (3, 1, 1, 1, 40, 11), (4, 1, 1, 2, 5, 30),
+            (0, 2, 1, 1, 2, 10), (1, 2, 2, 2, 60, 6), (3, 2, 1, 1, 0, 50),
+            (0, 3, 1, 1, 9, 2), (3, 3, 2, 1, 12, 30), (5, 3, 1, 1, 4, 10),
+        ];
+
+        let mut row = vec![0u32; 6];
+        // Value of every block, which is what the specification predicts from.
+        let mut grid = [[None; 6]; 4];
+        for (x, y, w8, h8, non_zeros, expected) in varblocks {
+            let top = y.checked_sub(1).map(|y| grid[y][x].unwrap());
+            let left = x.checked_sub(1).map(|x| grid[y][x].unwrap());
+            let from_grid = match (top, left) {
+                (None, None) => 32,
+                (None, Some(left)) => left,
+                (Some(top), None) => top,
+                (Some(top), Some(left)) => (top + left + 1) >> 1,
+            };
+
+            let predicted = predict_non_zeros(&row, x, y);
+            assert_eq!(predicted, from_grid, "varblock at ({x}, {y})");
+            assert_eq!(predicted, expected, "varblock at ({x}, {y})");
+
+            row[x..][..w8].fill(non_zeros);
+            for grid_row in &mut grid[y..][..h8] {
+                grid_row[x..][..w8].fill(Some(non_zeros));
+            }
+        }
+    }
+}
@@ -30,6 +30,10 @@ pub struct HfMetadata {
     /// Varblock information in an LF group.
     pub block_info: AlignedGrid<BlockInfo>,
     /// Sigma parameter grid for edge-preserving filter.
+    ///
+    /// Sigma of each block is scaled by the sharpness LUT entry selected by the block's EPF
+    /// sharpness value. Blocks with sharpness value zero get zero sigma with the default LUT, which
+    /// disables the filter for those blocks.
     pub epf_sigma: AlignedGrid<f32>,
 }
 
@@ -129,7 +133,7 @@ impl Bundle<HfMetadataParams<'_, '_, '_>> for HfMetadata {
                         )
                         .into());
                     };
-                    let dct_select = TransformType::try_from(dct_select as u8)?;
+                    let dct_select = TransformType::try_from(dct_select)?;
                     let mul = *block_info_raw.get(data_idx, 1).unwrap();
                     let hf_mul = mul + 1;
                     if hf_mul <= 0 {